@@ -0,0 +1,84 @@
+use chrono::NaiveDate;
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use pocket_topo::{builder::DocumentBuilder, index, parser, writer};
+
+const TRIPS: u16 = 50;
+const SHOTS_PER_TRIP: u16 = 100;
+const SPLAYS_PER_STATION: u16 = 4;
+const POLYGONS: u16 = 2000;
+
+// 50 trips of 100 legs, each with 4 splays and a comment on every tenth leg,
+// plus an outline and sideview of 2000 polygons each
+fn synthetic() -> Vec<u8> {
+	let time = NaiveDate::from_ymd_opt(2022, 10, 22)
+		.unwrap()
+		.and_hms_opt(0, 0, 0)
+		.unwrap();
+
+	let mut builder = DocumentBuilder::new().reference("1.0", 24.0, 42.0, 50.0, "entrance");
+
+	for trip in 0..TRIPS {
+		builder = builder.trip(time, 3.45, format!("trip {trip}"));
+
+		for minor in 0..SHOTS_PER_TRIP {
+			let from = format!("{}.{minor}", trip + 1);
+			let to = format!("{}.{}", trip + 1, minor + 1);
+
+			builder = builder.shot(&from, &to, 5.0, f64::from(minor) * 3.6, -5.5);
+
+			if minor % 10 == 0 {
+				builder = builder.comment(format!("{trip}.{minor}"));
+			}
+
+			for splay in 0..SPLAYS_PER_STATION {
+				builder = builder.splay(&from, 1.5, f64::from(splay) * 90.0, 0.0);
+			}
+		}
+	}
+
+	for polygon in 0..POLYGONS {
+		let points: Vec<_> = (0..20)
+			.map(|x| (f64::from(x) / 10.0, f64::from(polygon) / 10.0))
+			.collect();
+
+		builder = builder
+			.outline_polygon("black", &points)
+			.sideview_polygon("black", &points);
+	}
+
+	let document = builder.build().unwrap();
+
+	let mut output = Vec::new();
+	writer::write(&document, &mut output).unwrap();
+
+	output
+}
+
+fn benchmark(c: &mut Criterion) {
+	let contents = synthetic();
+
+	c.bench_function("parse", |b| {
+		b.iter(|| parser::parse(black_box(&contents)).unwrap())
+	});
+
+	c.bench_function("index", |b| {
+		b.iter(|| index::index(black_box(&contents)).unwrap())
+	});
+
+	c.bench_function("index trips", |b| {
+		b.iter(|| {
+			let index = index::index(black_box(&contents)).unwrap();
+			index.trips().unwrap()
+		})
+	});
+
+	c.bench_function("index sideview", |b| {
+		b.iter(|| {
+			let index = index::index(black_box(&contents)).unwrap();
+			index.sideview().unwrap()
+		})
+	});
+}
+
+criterion_group!(benches, benchmark);
+criterion_main!(benches);
@@ -0,0 +1,113 @@
+use std::{io, path::PathBuf};
+
+use pocket_topo::{
+	blunders::{self, Evidence, Options, Reading},
+	parser::{Document, ParseOptions},
+};
+
+use crate::Result;
+
+#[derive(Debug, clap::Args)]
+pub struct Args {
+	/// The .top file to check
+	input: PathBuf,
+
+	/// How far shots of a leg may differ in distance, in metres
+	#[arg(long, default_value_t = Options::default().distance)]
+	distance: f64,
+
+	/// How far shots of a leg may differ in direction, in degrees
+	#[arg(long, default_value_t = Options::default().angle)]
+	angle: f64,
+
+	/// The misclosure allowed per metre of loop, on top of --distance
+	#[arg(long, default_value_t = Options::default().misclosure)]
+	misclosure: f64,
+
+	/// Print JSON instead of text
+	#[arg(long)]
+	json: bool,
+}
+
+pub fn run(args: Args, options: &ParseOptions) -> Result<()> {
+	let contents = crate::read(&args.input)?;
+	let document = crate::parse(&args.input, &contents, options)?;
+
+	let options = Options {
+		distance: args.distance,
+		angle: args.angle,
+		misclosure: args.misclosure,
+	};
+	let report = blunders::detect(&document, &options);
+
+	if args.json {
+		serde_json::to_writer_pretty(io::stdout().lock(), &report)?;
+		println!();
+		return Ok(());
+	}
+
+	for (rank, suspect) in report.suspects.iter().enumerate() {
+		println!(
+			"{}. shot {} ({}): score {:.1}",
+			rank + 1,
+			suspect.shot,
+			stations(&document, suspect.shot),
+			suspect.score
+		);
+
+		for evidence in suspect.evidence.iter() {
+			println!("   {}", describe(&document, evidence));
+		}
+	}
+
+	let blunders = report.loops.iter().filter(|l| l.blunder).count();
+	println!(
+		"Loops: {} ({blunders} not closing within tolerance)",
+		report.loops.len()
+	);
+
+	Ok(())
+}
+
+fn stations(document: &Document, shot: usize) -> String {
+	let shot = &document.shots[shot];
+	format!(
+		"{} → {}",
+		crate::station(shot.from),
+		crate::station(shot.to)
+	)
+}
+
+fn describe(document: &Document, evidence: &Evidence) -> String {
+	match evidence {
+		Evidence::Backsight {
+			other,
+			distance,
+			angle,
+		} => format!(
+			"differs from shot {other} ({}) by {distance:.3} m and {angle:.1}°",
+			stations(document, *other)
+		),
+		Evidence::Misclosure {
+			share,
+			reading,
+			error,
+			explained,
+		} => {
+			let (reading, unit) = match reading {
+				Reading::Distance => ("distance", " m"),
+				Reading::Azimuth => ("azimuth", "°"),
+				Reading::Inclination => ("inclination", "°"),
+			};
+
+			format!(
+				"accounts for {:.0}% of the loop misclosure; {reading} off by {error:+.2}{unit} explains {:.0}%",
+				share * 100.0,
+				explained * 100.0
+			)
+		}
+		Evidence::MagneticAnomaly { station, deviation } => {
+			format!("shots at {station} are rotated by {deviation:+.1}°")
+		}
+	}
+}
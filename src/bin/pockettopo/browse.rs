@@ -0,0 +1,398 @@
+use std::{
+	io::{self, IsTerminal},
+	path::PathBuf,
+};
+
+use pocket_topo::{
+	browser::{Browser, Filter, Row, Tab},
+	parser::{Document, ParseOptions},
+	units, Shot, ShotFlags, StationId,
+};
+use ratatui::{
+	crossterm::event::{self, Event, KeyCode, KeyEventKind},
+	layout::{Constraint, Layout},
+	style::{Modifier, Style},
+	text::Line,
+	widgets::{Block, Paragraph, Row as TableRow, Table, TableState, Tabs, Wrap},
+	DefaultTerminal, Frame,
+};
+
+use crate::Result;
+
+#[derive(Debug, clap::Args)]
+pub struct Args {
+	/// The .top file to browse
+	input: PathBuf,
+}
+
+pub fn run(args: Args, options: &ParseOptions) -> Result<()> {
+	let contents = crate::read(&args.input)?;
+	let document = crate::parse(&args.input, &contents, options)?;
+
+	if !io::stdin().is_terminal() || !io::stdout().is_terminal() {
+		return Err("browse needs a terminal".into());
+	}
+
+	let mut terminal = ratatui::init();
+	let result = browse(&mut terminal, &document);
+	ratatui::restore();
+
+	result
+}
+
+const HELP: &str =
+	"q quit  tab switch  enter open  f/t follow from/to  s station  [/] trip  c clear";
+
+// what's being typed, if anything
+#[derive(Default)]
+struct Prompt {
+	station: Option<String>,
+	error: Option<String>,
+}
+
+fn browse(terminal: &mut DefaultTerminal, document: &Document) -> Result<()> {
+	let mut browser = Browser::new(document);
+	let mut prompt = Prompt::default();
+	let mut page = 1;
+
+	loop {
+		terminal.draw(|frame| page = draw(frame, &browser, &prompt))?;
+
+		let Event::Key(key) = event::read()? else {
+			continue;
+		};
+		if key.kind != KeyEventKind::Press {
+			continue;
+		}
+
+		if let Some(station) = &mut prompt.station {
+			match key.code {
+				KeyCode::Enter => {
+					let filter = match station.trim() {
+						"" => Ok(None),
+						station => station.parse::<StationId>().map(Some),
+					};
+
+					match filter {
+						Ok(station) => browser.set_filter(Filter {
+							station,
+							..browser.filter()
+						}),
+						Err(error) => prompt.error = Some(error.to_string()),
+					}
+					prompt.station = None;
+				}
+				KeyCode::Esc => prompt.station = None,
+				KeyCode::Backspace => {
+					station.pop();
+				}
+				KeyCode::Char(character) => station.push(character),
+				_ => {}
+			}
+			continue;
+		}
+
+		prompt.error = None;
+		let tab = Tab::ALL.iter().position(|tab| *tab == browser.tab());
+
+		match key.code {
+			KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+			KeyCode::Tab => browser.set_tab(Tab::ALL[tab.map_or(0, |tab| (tab + 1) % 3)]),
+			KeyCode::BackTab => browser.set_tab(Tab::ALL[tab.map_or(0, |tab| (tab + 2) % 3)]),
+			KeyCode::Char('1') => browser.set_tab(Tab::Trips),
+			KeyCode::Char('2') => browser.set_tab(Tab::Shots),
+			KeyCode::Char('3') => browser.set_tab(Tab::References),
+			KeyCode::Down | KeyCode::Char('j') => browser.move_by(1),
+			KeyCode::Up | KeyCode::Char('k') => browser.move_by(-1),
+			KeyCode::PageDown => browser.move_by(page as isize),
+			KeyCode::PageUp => browser.move_by(-(page as isize)),
+			KeyCode::Home | KeyCode::Char('g') => browser.select(0),
+			KeyCode::End | KeyCode::Char('G') => browser.select(usize::MAX),
+			KeyCode::Enter => browser.open(),
+			KeyCode::Char('f') => browser.follow(false),
+			KeyCode::Char('t') => browser.follow(true),
+			KeyCode::Char('s') | KeyCode::Char('/') => prompt.station = Some(String::new()),
+			KeyCode::Char(']') => browser.set_filter(Filter {
+				trip: next_trip(document, browser.filter().trip, 1),
+				..browser.filter()
+			}),
+			KeyCode::Char('[') => browser.set_filter(Filter {
+				trip: next_trip(document, browser.filter().trip, -1),
+				..browser.filter()
+			}),
+			KeyCode::Char('c') => browser.set_filter(Filter::default()),
+			_ => {}
+		}
+	}
+}
+
+// Cycles through no trip and each of the trips.
+fn next_trip(document: &Document, trip: Option<i16>, step: i16) -> Option<i16> {
+	let count = i16::try_from(document.trips.len()).unwrap_or(i16::MAX);
+	let next = trip.unwrap_or(-1) + step;
+
+	match next {
+		next if next >= count => None,
+		-1 => None,
+		next if next < -1 => count.checked_sub(1).filter(|last| *last >= 0),
+		next => Some(next),
+	}
+}
+
+// Draws the browser, and returns the number of rows in a page of the table.
+fn draw(frame: &mut Frame, browser: &Browser, prompt: &Prompt) -> usize {
+	let [tabs, table, details, footer] = Layout::vertical([
+		Constraint::Length(1),
+		Constraint::Min(3),
+		Constraint::Length(8),
+		Constraint::Length(1),
+	])
+	.areas(frame.area());
+
+	let selected = Tab::ALL.iter().position(|tab| *tab == browser.tab());
+	let titles = Tab::ALL.iter().map(|tab| tab.title());
+	frame.render_widget(
+		Tabs::new(titles)
+			.select(selected)
+			.highlight_style(Style::new().add_modifier(Modifier::REVERSED)),
+		tabs,
+	);
+
+	let document = browser.document();
+	let (header, widths) = columns(browser.tab());
+	let rows = browser
+		.rows()
+		.iter()
+		.map(|row| TableRow::new(cells(document, row)));
+
+	let mut state = TableState::new().with_selected(Some(browser.selected_index()));
+	frame.render_stateful_widget(
+		Table::new(rows, widths)
+			.header(TableRow::new(header).style(Style::new().add_modifier(Modifier::BOLD)))
+			.block(Block::bordered().title(filter(document, browser.filter())))
+			.row_highlight_style(Style::new().add_modifier(Modifier::REVERSED)),
+		table,
+		&mut state,
+	);
+
+	let text = browser
+		.selected()
+		.map_or_else(Vec::new, |row| self::details(document, row));
+	frame.render_widget(
+		Paragraph::new(text)
+			.block(Block::bordered())
+			.wrap(Wrap { trim: false }),
+		details,
+	);
+
+	let footer_text = match (&prompt.station, &prompt.error) {
+		(Some(station), _) => format!("Station (empty for all): {station}"),
+		(None, Some(error)) => error.clone(),
+		(None, None) => HELP.to_owned(),
+	};
+	frame.render_widget(Paragraph::new(footer_text), footer);
+
+	// less the borders and the header
+	usize::from(table.height.saturating_sub(3)).max(1)
+}
+
+fn filter(document: &Document, filter: Filter) -> String {
+	let mut parts = Vec::new();
+
+	if let Some(trip) = filter.trip {
+		let date = usize::try_from(trip)
+			.ok()
+			.and_then(|trip| document.trips.get(trip))
+			.map_or(String::new(), |trip| {
+				format!(" ({})", trip.time.format("%Y-%m-%d"))
+			});
+		parts.push(format!("trip {trip}{date}"));
+	}
+
+	if let Some(station) = filter.station {
+		parts.push(format!("station {station}"));
+	}
+
+	match parts.is_empty() {
+		true => " All ".to_owned(),
+		false => format!(" {} ", parts.join(", ")),
+	}
+}
+
+fn columns(tab: Tab) -> (Vec<&'static str>, Vec<Constraint>) {
+	match tab {
+		Tab::Trips => (
+			vec!["#", "Date", "Declination", "Comment"],
+			vec![
+				Constraint::Length(4),
+				Constraint::Length(16),
+				Constraint::Length(11),
+				Constraint::Fill(1),
+			],
+		),
+		Tab::Shots => (
+			vec![
+				"From",
+				"To",
+				"Distance",
+				"Azimuth",
+				"Inclination",
+				"Shots",
+				"Trip",
+				"Comment",
+			],
+			vec![
+				Constraint::Length(9),
+				Constraint::Length(9),
+				Constraint::Length(9),
+				Constraint::Length(8),
+				Constraint::Length(11),
+				Constraint::Length(5),
+				Constraint::Length(4),
+				Constraint::Fill(1),
+			],
+		),
+		Tab::References => (
+			vec!["Station", "East", "North", "Altitude", "Comment"],
+			vec![
+				Constraint::Length(9),
+				Constraint::Length(13),
+				Constraint::Length(13),
+				Constraint::Length(10),
+				Constraint::Fill(1),
+			],
+		),
+	}
+}
+
+fn cells(document: &Document, row: &Row) -> Vec<String> {
+	match row {
+		Row::Trip(index) => {
+			let trip = &document.trips[*index];
+			vec![
+				index.to_string(),
+				trip.time.format("%Y-%m-%d %H:%M").to_string(),
+				format!("{:.2}°", units::degrees(trip.declination)),
+				first_line(&trip.comment),
+			]
+		}
+		Row::Leg { from, to, shots } => {
+			let shot = &document.shots[shots[0]];
+			let comment = shots
+				.iter()
+				.find_map(|index| document.shots[*index].comment.as_deref())
+				.map_or(String::new(), first_line);
+
+			let mut cells = vec![from.to_string(), to.to_string()];
+			cells.extend(measurements(shot));
+			cells.extend([shots.len().to_string(), trip(shot), comment]);
+			cells
+		}
+		Row::Splay(index) => {
+			let shot = &document.shots[*index];
+			let comment = shot.comment.as_deref().map_or(String::new(), first_line);
+
+			let mut cells = vec![crate::station(shot.from), "-".to_owned()];
+			cells.extend(measurements(shot));
+			cells.extend(["1".to_owned(), trip(shot), comment]);
+			cells
+		}
+		Row::Reference(index) => {
+			let reference = &document.references[*index];
+			vec![
+				crate::station(reference.station),
+				format!("{:.3}", units::metres(reference.east as f64)),
+				format!("{:.3}", units::metres(reference.north as f64)),
+				format!("{:.3}", units::metres(reference.altitude)),
+				first_line(&reference.comment),
+			]
+		}
+	}
+}
+
+// the full record, with every shot of a leg
+fn details<'a>(document: &Document, row: &Row) -> Vec<Line<'a>> {
+	let mut lines = Vec::new();
+
+	match row {
+		Row::Trip(index) => {
+			let trip = &document.trips[*index];
+			lines.push(Line::from(format!(
+				"Trip {index}: {}, declination {:.2}°",
+				trip.time.format("%Y-%m-%d %H:%M"),
+				units::degrees(trip.declination),
+			)));
+			lines.extend(trip.comment.lines().map(|line| Line::from(line.to_owned())));
+		}
+		Row::Leg { shots, .. } => {
+			for index in shots {
+				lines.extend(shot(*index, &document.shots[*index]));
+			}
+		}
+		Row::Splay(index) => lines.extend(shot(*index, &document.shots[*index])),
+		Row::Reference(index) => {
+			let reference = &document.references[*index];
+			lines.push(Line::from(format!(
+				"Reference at {}: east {:.3} m, north {:.3} m, altitude {:.3} m",
+				crate::station(reference.station),
+				units::metres(reference.east as f64),
+				units::metres(reference.north as f64),
+				units::metres(reference.altitude),
+			)));
+			lines.extend(
+				reference
+					.comment
+					.lines()
+					.map(|line| Line::from(line.to_owned())),
+			);
+		}
+	}
+
+	lines
+}
+
+fn shot<'a>(index: usize, shot: &Shot) -> Vec<Line<'a>> {
+	let [distance, azimuth, inclination] = measurements(shot);
+	let flipped = match shot.flags.contains(ShotFlags::FLIPPED) {
+		true => ", flipped",
+		false => "",
+	};
+
+	let mut lines = vec![Line::from(format!(
+		"Shot {index}: {} to {}, {distance} m, azimuth {azimuth}, inclination {inclination}, trip {}{flipped}",
+		crate::station(shot.from),
+		crate::station(shot.to),
+		trip(shot),
+	))];
+
+	if let Some(comment) = &shot.comment {
+		lines.extend(comment.lines().map(|line| Line::from(format!("  {line}"))));
+	}
+
+	lines
+}
+
+fn measurements(shot: &Shot) -> [String; 3] {
+	[
+		format!("{:.3}", units::metres(shot.distance)),
+		format!("{:.2}°", units::degrees(shot.azimuth)),
+		format!("{:.2}°", units::degrees(shot.inclination)),
+	]
+}
+
+fn trip(shot: &Shot) -> String {
+	match shot.trip_index {
+		-1 => "-".to_owned(),
+		trip => trip.to_string(),
+	}
+}
+
+fn first_line(text: &str) -> String {
+	let mut lines = text.lines();
+	let first = lines.next().unwrap_or_default();
+
+	match lines.next() {
+		Some(_) => format!("{first} …"),
+		None => first.to_owned(),
+	}
+}
@@ -0,0 +1,92 @@
+use std::{
+	fs::File,
+	io::{BufWriter, Write},
+	path::{Path, PathBuf},
+};
+
+use clap::ValueEnum;
+use pocket_topo::{
+	cloud::{self, CloudPoint},
+	geodesy::Crs,
+	parser::ParseOptions,
+	reduction,
+};
+
+use crate::Result;
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum Format {
+	/// Binary PLY
+	Ply,
+
+	/// LAS 1.4
+	Las,
+}
+
+impl Format {
+	fn from_extension(path: &Path) -> Option<Self> {
+		let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+
+		match extension.as_str() {
+			"ply" => Some(Format::Ply),
+			"las" => Some(Format::Las),
+			_ => None,
+		}
+	}
+
+	fn write<W: Write>(
+		self,
+		points: &[CloudPoint],
+		crs: Option<Crs>,
+		output: &mut W,
+	) -> Result<()> {
+		match self {
+			Format::Ply => cloud::write_ply(points, output)?,
+			Format::Las => cloud::write_las(points, crs, output)?,
+		}
+
+		Ok(())
+	}
+}
+
+#[derive(Debug, clap::Args)]
+pub struct Args {
+	/// The .top file to export
+	input: PathBuf,
+
+	/// The file to write
+	output: PathBuf,
+
+	/// The format to write, instead of choosing it from the output's extension
+	#[arg(long, value_enum)]
+	format: Option<Format>,
+
+	/// The north to reduce azimuths to, by default grid north with --crs or else true north
+	#[arg(long, value_enum)]
+	north: Option<crate::North>,
+}
+
+pub fn run(args: Args, options: &ParseOptions) -> Result<()> {
+	let format = match args.format.or_else(|| Format::from_extension(&args.output)) {
+		Some(format) => format,
+		None => {
+			let error = format!("{}: unknown format, use --format", args.output.display());
+			return Err(error.into());
+		}
+	};
+
+	let contents = crate::read(&args.input)?;
+	let document = crate::parse(&args.input, &contents, options)?;
+
+	let north = crate::north(args.north, &document);
+	let points = cloud::points(&document, &reduction::Options { north });
+
+	let file = File::create(&args.output)
+		.map_err(|error| format!("{}: {error}", args.output.display()))?;
+
+	let mut output = BufWriter::new(file);
+	format.write(&points, document.crs, &mut output)?;
+	output.flush()?;
+
+	Ok(())
+}
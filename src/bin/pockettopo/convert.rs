@@ -0,0 +1,140 @@
+use std::{
+	fs::File,
+	io::{BufWriter, Write},
+	path::{Path, PathBuf},
+};
+
+use clap::ValueEnum;
+use pocket_topo::{
+	cloud, mesh,
+	parser::{Document, ParseOptions},
+	pdf, reduction, render, scene, writer,
+};
+
+use crate::Result;
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum Format {
+	/// JSON, as printed by `dump --json`
+	Json,
+
+	/// PocketTopo .top
+	Top,
+
+	/// The passage walls as Wavefront OBJ, as written by `mesh`
+	Obj,
+
+	/// The passage walls as binary PLY, as written by `mesh`
+	Ply,
+
+	/// The passage walls as binary STL, as written by `mesh`
+	Stl,
+
+	/// A binary glTF scene, as written by `scene`
+	Glb,
+
+	/// The stations and splay ends as binary PLY, as written by `cloud`, which
+	/// is never chosen by the .ply extension
+	CloudPly,
+
+	/// The stations and splay ends as LAS 1.4, as written by `cloud`
+	Las,
+
+	/// The outline drawing as PNG, as written by `render`
+	Png,
+
+	/// A map of the outline and sideview drawings, as written by `pdf`
+	Pdf,
+}
+
+impl Format {
+	fn from_extension(path: &Path) -> Option<Self> {
+		let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+
+		match extension.as_str() {
+			"json" => Some(Format::Json),
+			"top" => Some(Format::Top),
+			"obj" => Some(Format::Obj),
+			"ply" => Some(Format::Ply),
+			"stl" => Some(Format::Stl),
+			"glb" => Some(Format::Glb),
+			"las" => Some(Format::Las),
+			"png" => Some(Format::Png),
+			"pdf" => Some(Format::Pdf),
+			_ => None,
+		}
+	}
+
+	// with each export's default options
+	fn write<W: Write>(self, document: &Document, output: &mut W) -> Result<()> {
+		let north = crate::north(None, document);
+		let passage = || {
+			mesh::mesh(
+				document,
+				&mesh::Options {
+					north,
+					..mesh::Options::default()
+				},
+			)
+		};
+		let points = || cloud::points(document, &reduction::Options { north });
+
+		match self {
+			Format::Json => serde_json::to_writer_pretty(output, document)?,
+			Format::Top => writer::write(document, output)?,
+			Format::Obj => mesh::write_obj(&passage(), None, output)?,
+			Format::Ply => mesh::write_ply(&passage(), None, output)?,
+			Format::Stl => mesh::write_stl(&passage(), output)?,
+			Format::Glb => {
+				let options = scene::Options {
+					north,
+					..scene::Options::default()
+				};
+				scene::write_glb(document, &options, output)?
+			}
+			Format::CloudPly => cloud::write_ply(&points(), output)?,
+			Format::Las => cloud::write_las(&points(), document.crs, output)?,
+			Format::Png => render::write_png(document, &render::Options::default(), output)?,
+			Format::Pdf => pdf::write_pdf(document, &pdf::Options::default(), output)?,
+		}
+
+		Ok(())
+	}
+}
+
+#[derive(Debug, clap::Args)]
+pub struct Args {
+	/// The .top file to convert
+	input: PathBuf,
+
+	/// The file to write
+	output: PathBuf,
+
+	/// The format to write, instead of choosing it from the output's extension.
+	/// A .ply file is taken to be the passage walls, so the point cloud needs
+	/// `--format cloud-ply`
+	#[arg(long, value_enum)]
+	format: Option<Format>,
+}
+
+pub fn run(args: Args, options: &ParseOptions) -> Result<()> {
+	let format = match args.format.or_else(|| Format::from_extension(&args.output)) {
+		Some(format) => format,
+		None => {
+			let error = format!("{}: unknown format, use --format", args.output.display());
+			return Err(error.into());
+		}
+	};
+
+	let contents = crate::read(&args.input)?;
+	let document = crate::parse(&args.input, &contents, options)?;
+
+	let file = File::create(&args.output)
+		.map_err(|error| format!("{}: {error}", args.output.display()))?;
+
+	let mut output = BufWriter::new(file);
+	format.write(&document, &mut output)?;
+	output.flush()?;
+
+	Ok(())
+}
@@ -0,0 +1,150 @@
+use std::{
+	fs::{self, File},
+	io::{BufWriter, Write},
+	path::PathBuf,
+};
+
+use pocket_topo::{
+	geodesy::LatLon,
+	magnetic::{self, Model},
+	parser::ParseOptions,
+	writer,
+};
+
+use crate::Result;
+
+#[derive(Debug, clap::Args)]
+pub struct Args {
+	/// The .top file to check
+	input: PathBuf,
+
+	/// The latitude of the cave in degrees, instead of the first surveyed reference's with --crs
+	#[arg(long, requires = "longitude", allow_hyphen_values = true)]
+	latitude: Option<f64>,
+
+	/// The longitude of the cave in degrees, instead of the first surveyed reference's with --crs
+	#[arg(long, requires = "latitude", allow_hyphen_values = true)]
+	longitude: Option<f64>,
+
+	/// The height of the cave above the ellipsoid in metres, instead of the first surveyed
+	/// reference's altitude
+	#[arg(long, allow_hyphen_values = true)]
+	height: Option<f64>,
+
+	/// A coefficients file in the format of the WMM's WMM.COF, instead of the embedded WMM2020
+	/// and WMM2025
+	#[arg(long)]
+	coefficients: Option<PathBuf>,
+
+	/// Fail if a trip's declination differs from the model's by more than this, in degrees
+	#[arg(long)]
+	tolerance: Option<f64>,
+
+	/// Write a copy of the file with the model's declination for each trip without one
+	#[arg(long)]
+	fill: Option<PathBuf>,
+
+	/// With --fill, replace every trip's declination rather than only those which are zero
+	#[arg(long, requires = "fill")]
+	all: bool,
+}
+
+pub fn run(args: Args, options: &ParseOptions) -> Result<()> {
+	let models = match &args.coefficients {
+		Some(path) => {
+			let coefficients =
+				fs::read_to_string(path).map_err(|error| format!("{}: {error}", path.display()))?;
+			let model = Model::parse(&coefficients)
+				.map_err(|error| format!("{}: {error}", path.display()))?;
+			vec![model]
+		}
+		None => Model::embedded(),
+	};
+
+	let contents = crate::read(&args.input)?;
+	let mut document = crate::parse(&args.input, &contents, options)?;
+
+	let reference = magnetic::reference_location(&document);
+	let position = match (args.latitude, args.longitude, reference) {
+		(Some(latitude), Some(longitude), _) => LatLon {
+			latitude,
+			longitude,
+		},
+		(_, _, Some((position, _))) => position,
+		_ => {
+			let error = format!(
+				"{}: no location, use --crs with a reference or --latitude and --longitude",
+				args.input.display()
+			);
+			return Err(error.into());
+		}
+	};
+	let height = args
+		.height
+		.or(reference.map(|(_, altitude)| altitude))
+		.unwrap_or(0.0);
+
+	let declinations = magnetic::declinations(&document, &models, position, height);
+
+	println!(
+		"At {:.5}°, {:.5}°, {height:.0} m",
+		position.latitude, position.longitude
+	);
+
+	let mut failures = 0;
+	for declination in declinations.iter() {
+		let trip = &document.trips[declination.trip];
+		let model = &models[declination.source];
+		let difference = declination.difference();
+
+		let exceeded = args
+			.tolerance
+			.is_some_and(|tolerance| difference.abs() > tolerance);
+		if exceeded {
+			failures += 1;
+		}
+
+		println!(
+			"  {}: {} recorded {:.2}°, {} {:.2}°, difference {difference:+.2}°{}",
+			declination.trip,
+			trip.time.format("%Y-%m-%d"),
+			declination.recorded,
+			model.name,
+			declination.model,
+			if exceeded { " !" } else { "" },
+		);
+
+		if !declination.valid {
+			eprintln!(
+				"warning: trip {} is outside {}'s years, {} to {}",
+				declination.trip,
+				model.name,
+				model.epoch,
+				model.epoch + model.lifetime
+			);
+		}
+	}
+
+	if let Some(path) = &args.fill {
+		let changed = magnetic::fill(&mut document, &declinations, args.all);
+
+		let file = File::create(path).map_err(|error| format!("{}: {error}", path.display()))?;
+		let mut output = BufWriter::new(file);
+		writer::write(&document, &mut output)?;
+		output.flush()?;
+
+		println!("Filled {} trips", changed.len());
+	}
+
+	match failures {
+		0 => Ok(()),
+		count => {
+			let error = format!(
+				"{}: {count} trips differ from the model by more than {}°",
+				args.input.display(),
+				args.tolerance.unwrap_or_default()
+			);
+			Err(error.into())
+		}
+	}
+}
@@ -0,0 +1,131 @@
+use std::path::PathBuf;
+
+use pocket_topo::{
+	diff::{self, Change, Item, Tolerances},
+	parser::{Document, ParseOptions},
+	units, Drawing, Element, ShotFlags,
+};
+
+use crate::Result;
+
+#[derive(Debug, clap::Args)]
+pub struct Args {
+	/// The original .top file
+	old: PathBuf,
+
+	/// The updated .top file
+	new: PathBuf,
+
+	/// Differences in distances and coordinates to ignore, in metres
+	#[arg(long, default_value_t = Tolerances::default().distance)]
+	distance: f64,
+
+	/// Differences in angles to ignore, in degrees
+	#[arg(long, default_value_t = Tolerances::default().angle)]
+	angle: f64,
+
+	/// When to colour the output
+	#[arg(long, value_enum, default_value = "auto")]
+	color: crate::When,
+}
+
+pub fn run(args: Args, options: &ParseOptions) -> Result<()> {
+	let old_contents = crate::read(&args.old)?;
+	let old = crate::parse(&args.old, &old_contents, options)?;
+
+	let new_contents = crate::read(&args.new)?;
+	let new = crate::parse(&args.new, &new_contents, options)?;
+
+	let tolerances = Tolerances {
+		distance: args.distance,
+		angle: args.angle,
+	};
+
+	let color = crate::color(args.color);
+
+	for change in diff::diff(&old, &new, &tolerances) {
+		let (sign, escape, line) = match change {
+			Change::Added(item) => ('+', "\x1b[32m", describe(&new, item)),
+			Change::Removed(item) => ('-', "\x1b[31m", describe(&old, item)),
+			Change::Modified(old_item, new_item) => {
+				let line = format!(
+					"{} => {}",
+					describe(&old, old_item),
+					describe(&new, new_item)
+				);
+
+				('~', "\x1b[33m", line)
+			}
+		};
+
+		if color {
+			println!("{escape}{sign} {line}\x1b[0m");
+		} else {
+			println!("{sign} {line}");
+		}
+	}
+
+	Ok(())
+}
+
+fn describe(document: &Document, item: Item) -> String {
+	match item {
+		Item::Trip(index) => {
+			let trip = &document.trips[index];
+
+			format!(
+				"trip {index}: {} declination {:.2}°{}",
+				trip.time.format("%Y-%m-%d"),
+				units::degrees(trip.declination),
+				crate::comment(&trip.comment),
+			)
+		}
+		Item::Shot(index) => {
+			let shot = &document.shots[index];
+
+			let flipped = match shot.flags.contains(ShotFlags::FLIPPED) {
+				true => " flipped",
+				false => "",
+			};
+
+			format!(
+				"shot {} → {} (trip {}): {:.3} m {:.1}° {:.1}°{flipped}{}",
+				crate::station(shot.from),
+				crate::station(shot.to),
+				shot.trip_index,
+				units::metres(shot.distance),
+				units::degrees(shot.azimuth),
+				units::degrees(shot.inclination),
+				crate::comment(shot.comment.as_deref().unwrap_or_default()),
+			)
+		}
+		Item::Reference(index) => {
+			let reference = &document.references[index];
+
+			format!(
+				"reference {}: east {:.3} m, north {:.3} m, altitude {:.3} m{}",
+				crate::station(reference.station),
+				units::metres(reference.east as f64),
+				units::metres(reference.north as f64),
+				units::metres(reference.altitude),
+				crate::comment(&reference.comment),
+			)
+		}
+		Item::Outline(index) => element("outline", &document.outline, index),
+		Item::Sideview(index) => element("sideview", &document.sideview, index),
+	}
+}
+
+fn element(view: &str, drawing: &Drawing, index: usize) -> String {
+	match &drawing.elements[index] {
+		Element::Polygon(polygon) => format!(
+			"{view} polygon {index}: {:?}, {} points",
+			polygon.color,
+			polygon.points.len()
+		),
+		Element::CrossSection(cross_section) => {
+			format!("{view} cross-section {index}: {}", cross_section.station)
+		}
+		Element::Unknown { id, .. } => format!("{view} element {index}: {id:#04X}"),
+	}
+}
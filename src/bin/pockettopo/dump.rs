@@ -0,0 +1,29 @@
+use std::{io, path::PathBuf};
+
+use pocket_topo::parser::ParseOptions;
+
+use crate::Result;
+
+#[derive(Debug, clap::Args)]
+pub struct Args {
+	/// The .top file to dump
+	input: PathBuf,
+
+	/// Print JSON instead of Rust's debug formatting
+	#[arg(long)]
+	json: bool,
+}
+
+pub fn run(args: Args, options: &ParseOptions) -> Result<()> {
+	let contents = crate::read(&args.input)?;
+	let document = crate::parse(&args.input, &contents, options)?;
+
+	if args.json {
+		serde_json::to_writer_pretty(io::stdout().lock(), &document)?;
+		println!();
+	} else {
+		println!("{document:#?}");
+	}
+
+	Ok(())
+}
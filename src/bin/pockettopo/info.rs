@@ -0,0 +1,96 @@
+use std::path::PathBuf;
+
+use pocket_topo::{
+	parser::{Document, ParseOptions},
+	units, Drawing, Element,
+};
+
+use crate::Result;
+
+#[derive(Debug, clap::Args)]
+pub struct Args {
+	/// The .top file to summarise
+	input: PathBuf,
+}
+
+pub fn run(args: Args, options: &ParseOptions) -> Result<()> {
+	let contents = crate::read(&args.input)?;
+	let document = crate::parse(&args.input, &contents, options)?;
+
+	print!("{}", summary(&document));
+
+	Ok(())
+}
+
+fn summary(document: &Document) -> String {
+	let mut summary = String::new();
+
+	summary += &format!("Trips: {}\n", document.trips.len());
+	for (index, trip) in document.trips.iter().enumerate() {
+		summary += &format!(
+			"  {index}: {} declination {:.2}°{}\n",
+			trip.time.format("%Y-%m-%d"),
+			units::degrees(trip.declination),
+			crate::comment(&trip.comment),
+		);
+	}
+
+	let legs = document.shots.iter().filter(|shot| shot.to.is_some());
+	let legs = legs.filter(|shot| shot.from.is_some()).count();
+	let splays = document.shots.iter().filter(|shot| shot.to.is_none());
+	let splays = splays.filter(|shot| shot.from.is_some()).count();
+
+	summary += &format!(
+		"Shots: {} ({legs} legs, {splays} splays)\n",
+		document.shots.len()
+	);
+
+	summary += &format!("References: {}\n", document.references.len());
+	if let Some(crs) = document.crs {
+		summary += &format!("  CRS: {crs} (EPSG:{})\n", crs.epsg());
+	}
+	for reference in document.references.iter() {
+		summary += &format!(
+			"  {}: east {:.3} m, north {:.3} m, altitude {:.3} m{}\n",
+			crate::station(reference.station),
+			units::metres(reference.east as f64),
+			units::metres(reference.north as f64),
+			units::metres(reference.altitude),
+			crate::comment(&reference.comment),
+		);
+
+		if let Some(crs) = document.crs {
+			let (position, grid) = crs.reference(reference);
+			summary += &format!(
+				"    latitude {:.7}°, longitude {:.7}°, convergence {:.4}°, scale factor {:.6}\n",
+				position.latitude, position.longitude, grid.convergence, grid.scale_factor,
+			);
+		}
+	}
+
+	summary += &format!("Outline: {}\n", elements(&document.outline));
+	summary += &format!("Sideview: {}\n", elements(&document.sideview));
+
+	summary
+}
+
+fn elements(drawing: &Drawing) -> String {
+	let mut polygons = 0;
+	let mut cross_sections = 0;
+	let mut unknown = 0;
+
+	for element in drawing.elements.iter() {
+		match element {
+			Element::Polygon(_) => polygons += 1,
+			Element::CrossSection(_) => cross_sections += 1,
+			Element::Unknown { .. } => unknown += 1,
+		}
+	}
+
+	let mut elements = format!("{polygons} polygons, {cross_sections} cross-sections");
+	if unknown > 0 {
+		elements += &format!(", {unknown} unknown elements");
+	}
+
+	elements
+}
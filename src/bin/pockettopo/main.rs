@@ -0,0 +1,232 @@
+mod blunders;
+mod browse;
+mod cloud;
+mod convert;
+mod declination;
+mod diff;
+mod dump;
+mod info;
+mod mesh;
+mod pdf;
+mod preview;
+mod render;
+mod scene;
+mod stats;
+mod surface;
+mod validate;
+
+use std::{
+	env,
+	error::Error,
+	fs,
+	io::{self, IsTerminal},
+	path::Path,
+	process::ExitCode,
+};
+
+use clap::{Parser, Subcommand, ValueEnum};
+use pocket_topo::{
+	encoding_rs::Encoding,
+	geodesy::Crs,
+	parser::{self, CommentEncoding, Document, ParseOptions, Record},
+	reduction, StationId,
+};
+
+type Result<T> = std::result::Result<T, Box<dyn Error>>;
+
+/// Inspect and convert PocketTopo .top files
+#[derive(Debug, Parser)]
+#[command(name = "pockettopo", version)]
+struct Cli {
+	/// How comments which aren't valid UTF-8 are decoded: `utf-8` (fail), `utf-8-lossy`, or a
+	/// legacy encoding such as `windows-1252`
+	#[arg(long, global = true, default_value = "utf-8", value_parser = parse_encoding)]
+	encoding: CommentEncoding,
+
+	/// The coordinate system of the references, as a UTM zone such as `33N` or `EPSG:32633`
+	#[arg(long, global = true)]
+	crs: Option<Crs>,
+
+	#[command(subcommand)]
+	command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+	/// Rank shots which are likely to be blunders, for re-survey
+	Blunders(blunders::Args),
+
+	/// Browse the trips, shots and references in the terminal
+	Browse(browse::Args),
+
+	/// Export the stations and splay ends as a point cloud
+	Cloud(cloud::Args),
+
+	/// Convert a file to another format
+	Convert(convert::Args),
+
+	/// Compare or fill in the trips' declinations from the World Magnetic Model, offline
+	Declination(declination::Args),
+
+	/// Print the differences between two files
+	Diff(diff::Args),
+
+	/// Print the full contents of a file
+	Dump(dump::Args),
+
+	/// Print a summary of a file
+	Info(info::Args),
+
+	/// Model the passage walls from the splays as a 3D mesh
+	Mesh(mesh::Args),
+
+	/// Print a map to PDF, with a grid, scale bar, legend and title block
+	Pdf(pdf::Args),
+
+	/// Preview the outline or sideview drawing in the terminal
+	Preview(preview::Args),
+
+	/// Render the outline or sideview drawing to PNG
+	Render(render::Args),
+
+	/// Export a 3D scene of the survey as binary glTF
+	Scene(scene::Args),
+
+	/// Print survey statistics: length, extent and depth
+	Stats(stats::Args),
+
+	/// Compare the stations with a surface elevation model, for the cover above them
+	Surface(surface::Args),
+
+	/// Check a file for problems, failing if there are errors
+	Validate(validate::Args),
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum North {
+	/// As measured, without the trips' declinations
+	Magnetic,
+
+	/// With the trips' declinations
+	True,
+
+	/// With the trips' declinations and the grid convergence at the first surveyed reference
+	Grid,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum When {
+	Auto,
+	Always,
+	Never,
+}
+
+// whether to colour standard output: by default if it's a terminal and NO_COLOR
+// isn't set to a non-empty value, as https://no-color.org asks
+fn color(when: When) -> bool {
+	match when {
+		When::Auto => {
+			io::stdout().is_terminal()
+				&& env::var_os("NO_COLOR").is_none_or(|value| value.is_empty())
+		}
+		When::Always => true,
+		When::Never => false,
+	}
+}
+
+// grid north for a document with a CRS unless asked otherwise, so that
+// exports line up with the references' grid
+fn north(north: Option<North>, document: &Document) -> reduction::North {
+	match north {
+		Some(North::Magnetic) => reduction::North::Magnetic,
+		Some(North::True) => reduction::North::True,
+		Some(North::Grid) => reduction::North::Grid,
+		None if document.crs.is_some() => reduction::North::Grid,
+		None => reduction::North::True,
+	}
+}
+
+fn main() -> ExitCode {
+	let cli = Cli::parse();
+
+	let options = ParseOptions {
+		comment_encoding: cli.encoding,
+		crs: cli.crs,
+	};
+
+	let result = match cli.command {
+		Command::Blunders(args) => blunders::run(args, &options),
+		Command::Browse(args) => browse::run(args, &options),
+		Command::Cloud(args) => cloud::run(args, &options),
+		Command::Convert(args) => convert::run(args, &options),
+		Command::Declination(args) => declination::run(args, &options),
+		Command::Diff(args) => diff::run(args, &options),
+		Command::Dump(args) => dump::run(args, &options),
+		Command::Info(args) => info::run(args, &options),
+		Command::Mesh(args) => mesh::run(args, &options),
+		Command::Pdf(args) => pdf::run(args, &options),
+		Command::Preview(args) => preview::run(args, &options),
+		Command::Render(args) => render::run(args, &options),
+		Command::Scene(args) => scene::run(args, &options),
+		Command::Stats(args) => stats::run(args, &options),
+		Command::Surface(args) => surface::run(args, &options),
+		Command::Validate(args) => validate::run(args, &options),
+	};
+
+	match result {
+		Ok(()) => ExitCode::SUCCESS,
+		Err(error) => {
+			eprintln!("error: {error}");
+			ExitCode::FAILURE
+		}
+	}
+}
+
+fn parse_encoding(label: &str) -> std::result::Result<CommentEncoding, String> {
+	match label {
+		"utf-8" => Ok(CommentEncoding::Utf8),
+		"utf-8-lossy" => Ok(CommentEncoding::Utf8Lossy),
+		label => match Encoding::for_label(label.as_bytes()) {
+			Some(encoding) => Ok(CommentEncoding::Legacy(encoding)),
+			None => Err(format!("unknown encoding: {label}")),
+		},
+	}
+}
+
+// a station, or "-" for none
+fn station(station: Option<StationId>) -> String {
+	station.map_or("-".to_owned(), |station| station.to_string())
+}
+
+// the first line of a comment, quoted after a space, or nothing if it's empty
+fn comment(comment: &str) -> String {
+	match comment.lines().next() {
+		Some(line) if !line.is_empty() => format!(" \"{line}\""),
+		_ => String::new(),
+	}
+}
+
+fn read(path: &Path) -> Result<Vec<u8>> {
+	fs::read(path).map_err(|error| format!("{}: {error}", path.display()).into())
+}
+
+// parses `contents`, warning about any comments which had to be decoded
+fn parse<'a>(path: &Path, contents: &'a [u8], options: &ParseOptions) -> Result<Document<'a>> {
+	let (document, decoded) = parser::parse_with_options(contents, options)
+		.map_err(|error| format!("{}: {error}", path.display()))?;
+
+	for record in decoded.iter() {
+		let (record, index) = match record {
+			Record::Reference(index) => ("reference", index),
+			Record::Shot(index) => ("shot", index),
+			Record::Trip(index) => ("trip", index),
+		};
+
+		eprintln!(
+			"warning: {}: comment of {record} {index} isn't valid UTF-8",
+			path.display()
+		);
+	}
+
+	Ok(document)
+}
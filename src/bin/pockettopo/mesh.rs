@@ -0,0 +1,112 @@
+use std::{
+	fs::File,
+	io::{BufWriter, Write},
+	path::{Path, PathBuf},
+};
+
+use clap::ValueEnum;
+use pocket_topo::{
+	mesh::{self, Mesh, Options},
+	parser::ParseOptions,
+};
+
+use crate::Result;
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum Format {
+	/// Wavefront OBJ
+	Obj,
+
+	/// Binary PLY
+	Ply,
+
+	/// Binary STL, without colours
+	Stl,
+}
+
+impl Format {
+	fn from_extension(path: &Path) -> Option<Self> {
+		let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+
+		match extension.as_str() {
+			"obj" => Some(Format::Obj),
+			"ply" => Some(Format::Ply),
+			"stl" => Some(Format::Stl),
+			_ => None,
+		}
+	}
+
+	fn write<W: Write>(
+		self,
+		mesh: &Mesh,
+		colours: Option<&[[u8; 3]]>,
+		output: &mut W,
+	) -> Result<()> {
+		match self {
+			Format::Obj => mesh::write_obj(mesh, colours, output)?,
+			Format::Ply => mesh::write_ply(mesh, colours, output)?,
+			Format::Stl => mesh::write_stl(mesh, output)?,
+		}
+
+		Ok(())
+	}
+}
+
+#[derive(Debug, clap::Args)]
+pub struct Args {
+	/// The .top file to model
+	input: PathBuf,
+
+	/// The file to write
+	output: PathBuf,
+
+	/// The format to write, instead of choosing it from the output's extension
+	#[arg(long, value_enum)]
+	format: Option<Format>,
+
+	/// The number of vertices around each station
+	#[arg(long, default_value_t = Options::default().segments)]
+	segments: usize,
+
+	/// Leave the passage open at stations with only one leg
+	#[arg(long)]
+	no_caps: bool,
+
+	/// Colour the vertices by depth
+	#[arg(long)]
+	depth_colours: bool,
+
+	/// The north to reduce azimuths to, by default grid north with --crs or else true north
+	#[arg(long, value_enum)]
+	north: Option<crate::North>,
+}
+
+pub fn run(args: Args, options: &ParseOptions) -> Result<()> {
+	let format = match args.format.or_else(|| Format::from_extension(&args.output)) {
+		Some(format) => format,
+		None => {
+			let error = format!("{}: unknown format, use --format", args.output.display());
+			return Err(error.into());
+		}
+	};
+
+	let contents = crate::read(&args.input)?;
+	let document = crate::parse(&args.input, &contents, options)?;
+
+	let options = Options {
+		segments: args.segments,
+		caps: !args.no_caps,
+		north: crate::north(args.north, &document),
+	};
+	let mesh = mesh::mesh(&document, &options);
+	let colours = args.depth_colours.then(|| mesh.depth_colours());
+
+	let file = File::create(&args.output)
+		.map_err(|error| format!("{}: {error}", args.output.display()))?;
+
+	let mut output = BufWriter::new(file);
+	format.write(&mesh, colours.as_deref(), &mut output)?;
+	output.flush()?;
+
+	Ok(())
+}
@@ -0,0 +1,113 @@
+use std::{
+	fs::File,
+	io::{BufWriter, Write},
+	path::PathBuf,
+};
+
+use clap::ValueEnum;
+use pocket_topo::{
+	map::Sheet,
+	parser::ParseOptions,
+	pdf::{self, Options},
+	Color,
+};
+
+use crate::Result;
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum Paper {
+	A4,
+	A3,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct Args {
+	/// The .top file to map
+	input: PathBuf,
+
+	/// The .pdf file to write
+	output: PathBuf,
+
+	/// Only the plan, from the outline drawing
+	#[arg(long, conflicts_with = "profile")]
+	plan: bool,
+
+	/// Only the extended profile, from the sideview drawing
+	#[arg(long)]
+	profile: bool,
+
+	/// The map scale, e.g. 500 for 1:500
+	#[arg(long, default_value_t = Options::default().scale)]
+	scale: f64,
+
+	#[arg(long, value_enum, default_value = "a4")]
+	paper: Paper,
+
+	/// Print in portrait instead of landscape
+	#[arg(long)]
+	portrait: bool,
+
+	/// The margin, in mm
+	#[arg(long, default_value_t = Options::default().margin)]
+	margin: f64,
+
+	/// The title, instead of the first trip's comment
+	#[arg(long)]
+	title: Option<String>,
+
+	/// What a drawing colour means, as `color=meaning`, e.g. `blue=water`
+	#[arg(long, value_parser = parse_legend)]
+	legend: Vec<(Color, String)>,
+
+	/// Leave out the station labels
+	#[arg(long)]
+	no_labels: bool,
+}
+
+pub fn run(args: Args, options: &ParseOptions) -> Result<()> {
+	let contents = crate::read(&args.input)?;
+	let document = crate::parse(&args.input, &contents, options)?;
+
+	let sheets = match (args.plan, args.profile) {
+		(true, _) => vec![Sheet::Outline],
+		(_, true) => vec![Sheet::Sideview],
+		_ => vec![Sheet::Outline, Sheet::Sideview],
+	};
+
+	let (width, height) = match args.paper {
+		Paper::A4 => pdf::A4,
+		Paper::A3 => pdf::A3,
+	};
+
+	let options = Options {
+		sheets,
+		scale: args.scale,
+		page: match args.portrait {
+			true => (height, width),
+			false => (width, height),
+		},
+		margin: args.margin,
+		title: args.title,
+		legend: args.legend,
+		labels: !args.no_labels,
+	};
+
+	let file = File::create(&args.output)
+		.map_err(|error| format!("{}: {error}", args.output.display()))?;
+
+	let mut output = BufWriter::new(file);
+	pdf::write_pdf(&document, &options, &mut output)?;
+	output.flush()?;
+
+	Ok(())
+}
+
+fn parse_legend(legend: &str) -> std::result::Result<(Color, String), String> {
+	let (color, meaning) = legend
+		.split_once('=')
+		.ok_or_else(|| format!("expected `color=meaning`: {legend:?}"))?;
+
+	let color = color.parse().map_err(|error| format!("{error}"))?;
+
+	Ok((color, meaning.to_owned()))
+}
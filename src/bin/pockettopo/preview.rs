@@ -0,0 +1,80 @@
+use std::{
+	env,
+	io::{self, Write},
+	path::PathBuf,
+};
+
+use pocket_topo::{
+	map::Sheet,
+	parser::ParseOptions,
+	preview::{self, Charset, Options},
+};
+
+use crate::Result;
+
+#[derive(Debug, clap::Args)]
+pub struct Args {
+	/// The .top file to preview
+	input: PathBuf,
+
+	/// Preview the sideview instead of the outline
+	#[arg(long)]
+	sideview: bool,
+
+	/// The most characters across, by default the terminal's width
+	#[arg(long)]
+	width: Option<usize>,
+
+	/// The most lines down
+	#[arg(long)]
+	height: Option<usize>,
+
+	/// Draw with half blocks instead of braille, for fonts without braille
+	#[arg(long)]
+	blocks: bool,
+
+	/// When to colour the drawing
+	#[arg(long, value_enum, default_value = "auto")]
+	color: crate::When,
+
+	/// Label the stations
+	#[arg(long)]
+	labels: bool,
+}
+
+pub fn run(args: Args, options: &ParseOptions) -> Result<()> {
+	let contents = crate::read(&args.input)?;
+	let document = crate::parse(&args.input, &contents, options)?;
+
+	let stdout = io::stdout();
+
+	let width = args.width.unwrap_or_else(|| {
+		terminal_size::terminal_size()
+			.map(|(width, _)| usize::from(width.0))
+			.or_else(|| env::var("COLUMNS").ok()?.parse().ok())
+			.unwrap_or(80)
+	});
+
+	let colour = crate::color(args.color);
+
+	let options = Options {
+		sheet: match args.sideview {
+			true => Sheet::Sideview,
+			false => Sheet::Outline,
+		},
+		width,
+		height: args.height,
+		charset: match args.blocks {
+			true => Charset::Blocks,
+			false => Charset::Braille,
+		},
+		colour,
+		labels: args.labels,
+	};
+
+	let mut stdout = stdout.lock();
+	stdout.write_all(preview::preview(&document, &options).as_bytes())?;
+	stdout.flush()?;
+
+	Ok(())
+}
@@ -0,0 +1,67 @@
+use std::{
+	fs::File,
+	io::{BufWriter, Write},
+	path::PathBuf,
+};
+
+use pocket_topo::{
+	map::Sheet,
+	parser::ParseOptions,
+	render::{self, Options},
+};
+
+use crate::Result;
+
+#[derive(Debug, clap::Args)]
+pub struct Args {
+	/// The .top file to render
+	input: PathBuf,
+
+	/// The .png file to write
+	output: PathBuf,
+
+	/// Render the sideview instead of the outline
+	#[arg(long)]
+	sideview: bool,
+
+	/// Pixels per inch
+	#[arg(long, default_value_t = Options::default().dpi)]
+	dpi: f64,
+
+	/// The map scale, e.g. 500 for 1:500
+	#[arg(long, default_value_t = Options::default().scale)]
+	scale: f64,
+
+	/// The space around the survey, in mm
+	#[arg(long, default_value_t = Options::default().margin)]
+	margin: f64,
+
+	/// Leave out the station labels
+	#[arg(long)]
+	no_labels: bool,
+}
+
+pub fn run(args: Args, options: &ParseOptions) -> Result<()> {
+	let contents = crate::read(&args.input)?;
+	let document = crate::parse(&args.input, &contents, options)?;
+
+	let options = Options {
+		sheet: match args.sideview {
+			true => Sheet::Sideview,
+			false => Sheet::Outline,
+		},
+		dpi: args.dpi,
+		scale: args.scale,
+		margin: args.margin,
+		labels: !args.no_labels,
+	};
+
+	let file = File::create(&args.output)
+		.map_err(|error| format!("{}: {error}", args.output.display()))?;
+
+	let mut output = BufWriter::new(file);
+	render::write_png(&document, &options, &mut output)?;
+	output.flush()?;
+
+	Ok(())
+}
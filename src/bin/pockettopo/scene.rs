@@ -0,0 +1,56 @@
+use std::{
+	fs::File,
+	io::{BufWriter, Write},
+	path::PathBuf,
+};
+
+use pocket_topo::{
+	mesh,
+	parser::ParseOptions,
+	scene::{self, Options},
+};
+
+use crate::Result;
+
+#[derive(Debug, clap::Args)]
+pub struct Args {
+	/// The .top file to export
+	input: PathBuf,
+
+	/// The .glb file to write
+	output: PathBuf,
+
+	/// Include the passage walls modelled from the splays
+	#[arg(long)]
+	passage: bool,
+
+	/// The number of vertices around each station in the passage walls
+	#[arg(long, default_value_t = mesh::Options::default().segments)]
+	segments: usize,
+
+	/// The north to reduce azimuths to, by default grid north with --crs or else true north
+	#[arg(long, value_enum)]
+	north: Option<crate::North>,
+}
+
+pub fn run(args: Args, options: &ParseOptions) -> Result<()> {
+	let contents = crate::read(&args.input)?;
+	let document = crate::parse(&args.input, &contents, options)?;
+
+	let options = Options {
+		passage: args.passage.then_some(mesh::Options {
+			segments: args.segments,
+			..mesh::Options::default()
+		}),
+		north: crate::north(args.north, &document),
+	};
+
+	let file = File::create(&args.output)
+		.map_err(|error| format!("{}: {error}", args.output.display()))?;
+
+	let mut output = BufWriter::new(file);
+	scene::write_glb(&document, &options, &mut output)?;
+	output.flush()?;
+
+	Ok(())
+}
@@ -0,0 +1,83 @@
+use std::{io, path::PathBuf};
+
+use pocket_topo::{
+	parser::ParseOptions,
+	stats::{self, Statistics},
+};
+
+use crate::Result;
+
+#[derive(Debug, clap::Args)]
+pub struct Args {
+	/// The .top file to summarise
+	input: PathBuf,
+
+	/// Print JSON instead of text
+	#[arg(long)]
+	json: bool,
+}
+
+pub fn run(args: Args, options: &ParseOptions) -> Result<()> {
+	let contents = crate::read(&args.input)?;
+	let document = crate::parse(&args.input, &contents, options)?;
+
+	let report = stats::statistics(&document);
+
+	if args.json {
+		serde_json::to_writer_pretty(io::stdout().lock(), &report)?;
+		println!();
+	} else {
+		print!("{}", statistics(&report.document, ""));
+
+		for (index, trip) in report.trips.iter().enumerate() {
+			println!("Trip {index}:");
+			print!("{}", statistics(trip, "  "));
+		}
+	}
+
+	Ok(())
+}
+
+fn statistics(statistics: &Statistics, indent: &str) -> String {
+	let mut text = String::new();
+
+	if let (Some(first), Some(last)) = (statistics.first_date, statistics.last_date) {
+		text += &format!("{indent}Dates: {first} to {last}\n");
+	}
+
+	text += &format!("{indent}Length: {:.2} m\n", statistics.length);
+	text += &format!(
+		"{indent}Stations: {}, legs: {}, splays: {}\n",
+		statistics.stations, statistics.legs, statistics.splays
+	);
+
+	if let Some(leg) = statistics.longest_leg {
+		text += &format!(
+			"{indent}Longest leg: {} → {}, {:.2} m\n",
+			leg.from, leg.to, leg.length
+		);
+	}
+
+	text += &format!(
+		"{indent}Horizontal extent: {:.2} m\n",
+		statistics.horizontal_extent
+	);
+
+	if let (Some(highest), Some(deepest)) = (statistics.highest, statistics.deepest) {
+		let datum = match statistics.datum {
+			Some(datum) => format!(" relative to {datum}"),
+			None => String::new(),
+		};
+
+		text += &format!(
+			"{indent}Vertical range: {:.2} m{datum}, highest {} {:+.2} m, deepest {} {:+.2} m\n",
+			statistics.vertical_range,
+			highest.station,
+			highest.height,
+			deepest.station,
+			deepest.height,
+		);
+	}
+
+	text
+}
@@ -0,0 +1,105 @@
+use std::{
+	fs::File,
+	io::{self, BufWriter, Write},
+	path::PathBuf,
+};
+
+use pocket_topo::{
+	parser::ParseOptions,
+	reduction,
+	surface::{self, Dem},
+};
+
+use crate::Result;
+
+#[derive(Debug, clap::Args)]
+pub struct Args {
+	/// The .top file to check
+	input: PathBuf,
+
+	/// The elevation model, as an ESRI ASCII grid or a GeoTIFF in the references' coordinates or
+	/// in WGS84 latitude and longitude
+	dem: PathBuf,
+
+	/// How many of the shallowest stations to print
+	#[arg(long, default_value_t = 10)]
+	shallowest: usize,
+
+	/// Write the surface along the centerline as CSV
+	#[arg(long)]
+	profile: Option<PathBuf>,
+
+	/// The distance between the profile's points along each leg, in metres
+	#[arg(long, default_value_t = 5.0)]
+	step: f64,
+
+	/// The north to reduce azimuths to, by default grid north with --crs or else true north
+	#[arg(long, value_enum)]
+	north: Option<crate::North>,
+
+	/// Print every station's cover as JSON instead of text
+	#[arg(long)]
+	json: bool,
+}
+
+pub fn run(args: Args, options: &ParseOptions) -> Result<()> {
+	let contents = crate::read(&args.input)?;
+	let document = crate::parse(&args.input, &contents, options)?;
+
+	let dem = crate::read(&args.dem)?;
+	let dem = Dem::parse(&dem).map_err(|error| format!("{}: {error}", args.dem.display()))?;
+
+	let north = crate::north(args.north, &document);
+	let options = reduction::Options { north };
+
+	let cover = surface::cover(&document, &dem, &options)
+		.map_err(|error| format!("{}: {error}", args.input.display()))?;
+
+	if let Some(path) = &args.profile {
+		let profile = surface::profile(&document, &dem, &options, args.step)?;
+
+		let file = File::create(path).map_err(|error| format!("{}: {error}", path.display()))?;
+		let mut output = BufWriter::new(file);
+		surface::write_profile_csv(&profile, &mut output)?;
+		output.flush()?;
+	}
+
+	if args.json {
+		serde_json::to_writer_pretty(io::stdout().lock(), &cover)?;
+		println!();
+		return Ok(());
+	}
+
+	let shallowest = surface::shallowest(&cover);
+
+	println!(
+		"Surface: {} × {} cells of {} × {}{}",
+		dem.columns,
+		dem.rows,
+		dem.cell_width,
+		dem.cell_height,
+		dem.epsg
+			.map_or(String::new(), |epsg| format!(" (EPSG:{epsg})")),
+	);
+	println!(
+		"Stations: {}, {} under the surface",
+		cover.len(),
+		shallowest.len()
+	);
+
+	if !shallowest.is_empty() {
+		println!("Shallowest:");
+	}
+
+	for cover in shallowest.iter().take(args.shallowest) {
+		println!(
+			"  {}: cover {:.1} m, altitude {:.1} m, surface {:.1} m",
+			cover.station,
+			cover.thickness().unwrap_or_default(),
+			cover.position.up,
+			cover.surface.unwrap_or_default(),
+		);
+	}
+
+	Ok(())
+}
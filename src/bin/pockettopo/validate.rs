@@ -0,0 +1,80 @@
+use std::{io, path::PathBuf};
+
+use pocket_topo::{
+	diff::Item,
+	parser::ParseOptions,
+	validate::{self, Options, Severity},
+};
+
+use crate::Result;
+
+#[derive(Debug, clap::Args)]
+pub struct Args {
+	/// The .top file to check
+	input: PathBuf,
+
+	/// How far repeated shots of a leg may differ in distance, in metres
+	#[arg(long, default_value_t = Options::default().distance)]
+	distance: f64,
+
+	/// How far repeated shots of a leg may differ in direction, in degrees
+	#[arg(long, default_value_t = Options::default().angle)]
+	angle: f64,
+
+	/// Fail on warnings as well as errors
+	#[arg(long)]
+	strict: bool,
+
+	/// Print JSON instead of text
+	#[arg(long)]
+	json: bool,
+}
+
+pub fn run(args: Args, options: &ParseOptions) -> Result<()> {
+	let contents = crate::read(&args.input)?;
+	let document = crate::parse(&args.input, &contents, options)?;
+
+	let options = Options {
+		distance: args.distance,
+		angle: args.angle,
+	};
+	let findings = validate::validate(&document, &options);
+
+	if args.json {
+		serde_json::to_writer_pretty(io::stdout().lock(), &findings)?;
+		println!();
+	} else {
+		for finding in findings.iter() {
+			let severity = match finding.severity {
+				Severity::Warning => "warning",
+				Severity::Error => "error",
+			};
+
+			println!(
+				"{severity}[{}]: {}: {}",
+				finding.rule.id(),
+				location(finding.location),
+				finding.message
+			);
+		}
+	}
+
+	let failures = findings
+		.iter()
+		.filter(|finding| finding.severity == Severity::Error || args.strict);
+
+	match failures.count() {
+		0 => Ok(()),
+		count => Err(format!("{}: {count} problems found", args.input.display()).into()),
+	}
+}
+
+fn location(item: Item) -> String {
+	match item {
+		Item::Trip(index) => format!("trip {index}"),
+		Item::Shot(index) => format!("shot {index}"),
+		Item::Reference(index) => format!("reference {index}"),
+		Item::Outline(index) => format!("outline element {index}"),
+		Item::Sideview(index) => format!("sideview element {index}"),
+	}
+}
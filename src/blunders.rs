@@ -0,0 +1,683 @@
+use std::collections::{btree_map::Entry, BTreeMap, VecDeque};
+
+use crate::{
+	parser::Document,
+	reduction::{self, Leg, Position},
+	StationId,
+};
+
+// What counts as a disagreement.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Options {
+	pub distance: f64, // m
+	pub angle: f64,    // degrees
+	// the misclosure allowed per metre of loop, on top of `distance`
+	pub misclosure: f64,
+}
+
+impl Default for Options {
+	fn default() -> Self {
+		Self {
+			distance: 0.05,
+			angle: 1.5,
+			misclosure: 0.01,
+		}
+	}
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum Reading {
+	Distance,
+	Azimuth,
+	Inclination,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum Evidence {
+	// The shot disagrees with `other`, a shot of the same leg taken from the
+	// other station, by a distance in metres and an angle in degrees.
+	Backsight {
+		other: usize,
+		distance: f64,
+		angle: f64,
+	},
+	// Leaving the shot's leg out of the adjustment removes `share` of the
+	// misclosure of the loops. The loops fit best if `reading` were off by
+	// `error` (in metres or degrees), which explains `explained` of the
+	// difference.
+	Misclosure {
+		share: f64,
+		reading: Reading,
+		error: f64,
+		explained: f64,
+	},
+	// Shots taken at `station` are rotated by `deviation` degrees compared to
+	// backsights taken at the other ends of their legs, as by iron nearby.
+	MagneticAnomaly {
+		station: StationId,
+		deviation: f64,
+	},
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Suspect {
+	// index into `Document::shots`
+	pub shot: usize,
+	pub score: f64,
+	pub evidence: Vec<Evidence>,
+}
+
+// A loop of legs, and how far it fails to close.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Loop {
+	pub stations: Vec<StationId>,
+	pub length: f64,
+	pub misclosure: Position,
+	// whether the misclosure is more than the options allow
+	pub blunder: bool,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Report {
+	// most suspicious first
+	pub suspects: Vec<Suspect>,
+	pub loops: Vec<Loop>,
+}
+
+// the most legs through loops which don't close to adjust the network without
+const MAX_CANDIDATES: usize = 50;
+
+// Looks for blunders in three ways:
+//
+// - shots of a leg taken from either end are compared. Shots with reversed
+//   stations are backsights. Flipped shots are only drawn to the left in the
+//   extended elevation, so they're compared like any other.
+// - the loops of the centerline are closed, and for each leg in a loop which
+//   doesn't close, the loops are adjusted without the leg to see how much of
+//   the misclosure it accounts for, and which of its readings is most likely
+//   wrong.
+// - the azimuths of shots taken at each station are compared with their
+//   backsights, as a rotation common to every leg at a station points to a
+//   magnetic anomaly there rather than a misread.
+//
+// Each piece of evidence is scored by how far it exceeds the options, and the
+// suspects are ranked by their total score.
+pub fn detect(document: &Document, options: &Options) -> Report {
+	let centerline = reduction::reduce(document);
+
+	let legs: Vec<Vec<Observation>> = centerline
+		.legs
+		.iter()
+		.map(|leg| observations(document, leg))
+		.collect();
+
+	let mut evidence: BTreeMap<usize, Vec<(f64, Evidence)>> = BTreeMap::new();
+
+	for (leg, observations) in centerline.legs.iter().zip(legs.iter()) {
+		backsights(leg, observations, options, &mut evidence);
+	}
+
+	magnetic_anomalies(&centerline.legs, &legs, options, &mut evidence);
+
+	let loops = match centerline.origin {
+		Some(origin) => {
+			let network = Network::new(origin, &centerline.legs, &legs);
+			network.misclosures(&centerline.legs, options, &mut evidence)
+		}
+		None => Vec::new(),
+	};
+
+	let mut suspects: Vec<Suspect> = evidence
+		.into_iter()
+		.map(|(shot, evidence)| Suspect {
+			shot,
+			score: evidence.iter().map(|(score, _)| score).sum(),
+			evidence: evidence.into_iter().map(|(_, evidence)| evidence).collect(),
+		})
+		.collect();
+
+	suspects.sort_by(|a, b| b.score.total_cmp(&a.score).then(a.shot.cmp(&b.shot)));
+
+	Report { suspects, loops }
+}
+
+// a shot of a leg, oriented from the leg's `from` to its `to` station
+#[derive(Clone, Copy)]
+struct Observation {
+	shot: usize,
+	vector: Position,
+	// the station the shot was taken at
+	at: StationId,
+}
+
+fn observations(document: &Document, leg: &Leg) -> Vec<Observation> {
+	leg.shots
+		.iter()
+		.map(|shot| {
+			let vector = reduction::vector(document, &document.shots[*shot]);
+
+			match document.shots[*shot].from == Some(leg.from) {
+				true => Observation {
+					shot: *shot,
+					vector,
+					at: leg.from,
+				},
+				false => Observation {
+					shot: *shot,
+					vector: -vector,
+					at: leg.to,
+				},
+			}
+		})
+		.collect()
+}
+
+fn mean(observations: &[Observation]) -> Position {
+	let sum = observations
+		.iter()
+		.fold(Position::default(), |sum, observation| {
+			sum + observation.vector
+		});
+
+	sum * (1.0 / observations.len().max(1) as f64)
+}
+
+fn backsights(
+	leg: &Leg,
+	observations: &[Observation],
+	options: &Options,
+	evidence: &mut BTreeMap<usize, Vec<(f64, Evidence)>>,
+) {
+	let foresights = observations.iter().filter(|o| o.at == leg.from);
+
+	for foresight in foresights {
+		let backsights = observations.iter().filter(|o| o.at == leg.to);
+
+		for backsight in backsights {
+			let distance = (foresight.vector.length() - backsight.vector.length()).abs();
+			let angle = foresight.vector.angle(backsight.vector);
+			let score = (distance / options.distance).max(angle / options.angle);
+
+			if score <= 1.0 {
+				continue;
+			}
+
+			for (shot, other) in [
+				(foresight.shot, backsight.shot),
+				(backsight.shot, foresight.shot),
+			] {
+				evidence.entry(shot).or_default().push((
+					score,
+					Evidence::Backsight {
+						other,
+						distance,
+						angle,
+					},
+				));
+			}
+		}
+	}
+}
+
+fn magnetic_anomalies(
+	legs: &[Leg],
+	observations: &[Vec<Observation>],
+	options: &Options,
+	evidence: &mut BTreeMap<usize, Vec<(f64, Evidence)>>,
+) {
+	// the rotation of each station's shots compared to their backsights
+	let mut rotations: BTreeMap<StationId, Vec<(f64, Vec<usize>)>> = BTreeMap::new();
+
+	for (leg, observations) in legs.iter().zip(observations.iter()) {
+		let (foresights, backsights): (Vec<_>, Vec<_>) =
+			observations.iter().copied().partition(|o| o.at == leg.from);
+
+		if foresights.is_empty() || backsights.is_empty() {
+			continue;
+		}
+
+		let foresight = mean(&foresights);
+		let backsight = mean(&backsights);
+
+		// azimuths of steep legs are unreliable
+		let steep = |vector: Position| vector.up.abs() > vector.horizontal_length() * 2.75;
+		if steep(foresight) || steep(backsight) {
+			continue;
+		}
+
+		let rotation =
+			(foresight.azimuth() - backsight.azimuth() + 180.0).rem_euclid(360.0) - 180.0;
+
+		let shots = |observations: &[Observation]| observations.iter().map(|o| o.shot).collect();
+		rotations
+			.entry(leg.from)
+			.or_default()
+			.push((rotation, shots(&foresights)));
+		rotations
+			.entry(leg.to)
+			.or_default()
+			.push((-rotation, shots(&backsights)));
+	}
+
+	for (station, rotations) in rotations {
+		if rotations.len() < 2 {
+			continue;
+		}
+
+		let systematic = rotations
+			.iter()
+			.all(|(rotation, _)| rotation.abs() > options.angle)
+			&& (rotations.iter().all(|(rotation, _)| *rotation > 0.0)
+				|| rotations.iter().all(|(rotation, _)| *rotation < 0.0));
+
+		if !systematic {
+			continue;
+		}
+
+		let deviation =
+			rotations.iter().map(|(rotation, _)| rotation).sum::<f64>() / rotations.len() as f64;
+		let score = deviation.abs() / options.angle;
+
+		for shot in rotations.iter().flat_map(|(_, shots)| shots) {
+			evidence
+				.entry(*shot)
+				.or_default()
+				.push((score, Evidence::MagneticAnomaly { station, deviation }));
+		}
+	}
+}
+
+// the legs connected to the origin, as observations between station indices
+struct Network {
+	stations: Vec<StationId>,
+	edges: Vec<Edge>,
+}
+
+struct Edge {
+	// index into the centerline's legs
+	leg: usize,
+	from: usize,
+	to: usize,
+	vector: Position,
+	weight: f64,
+}
+
+impl Network {
+	fn new(origin: StationId, legs: &[Leg], observations: &[Vec<Observation>]) -> Self {
+		let mut adjacent: BTreeMap<StationId, Vec<usize>> = BTreeMap::new();
+		for (index, leg) in legs.iter().enumerate() {
+			adjacent.entry(leg.from).or_default().push(index);
+			adjacent.entry(leg.to).or_default().push(index);
+		}
+
+		// the origin is station 0
+		let mut stations = vec![origin];
+		let mut indices = BTreeMap::from([(origin, 0)]);
+		let mut queue = VecDeque::from([origin]);
+
+		while let Some(station) = queue.pop_front() {
+			for leg in adjacent.get(&station).into_iter().flatten() {
+				let leg = &legs[*leg];
+				for other in [leg.from, leg.to] {
+					if let Entry::Vacant(entry) = indices.entry(other) {
+						entry.insert(stations.len());
+						stations.push(other);
+						queue.push_back(other);
+					}
+				}
+			}
+		}
+
+		let edges = legs
+			.iter()
+			.enumerate()
+			.filter_map(|(index, leg)| {
+				let vector = mean(&observations[index]);
+
+				Some(Edge {
+					leg: index,
+					from: *indices.get(&leg.from)?,
+					to: *indices.get(&leg.to)?,
+					vector,
+					// the variance of a leg grows with its length
+					weight: 1.0 / vector.length().max(0.1),
+				})
+			})
+			.collect();
+
+		Network { stations, edges }
+	}
+
+	// closes each loop of a spanning tree, and looks for the legs which best
+	// explain the loops which don't close
+	fn misclosures(
+		&self,
+		legs: &[Leg],
+		options: &Options,
+		evidence: &mut BTreeMap<usize, Vec<(f64, Evidence)>>,
+	) -> Vec<Loop> {
+		let tree = Tree::new(self);
+		let mut loops = Vec::new();
+		// the worst misclosure, relative to its tolerance, of the loops through each edge
+		let mut worst: BTreeMap<usize, f64> = BTreeMap::new();
+
+		let mut in_tree = vec![false; self.edges.len()];
+		for edge in tree.parents.iter().flatten() {
+			in_tree[*edge] = true;
+		}
+
+		for (index, edge) in self.edges.iter().enumerate() {
+			if in_tree[index] {
+				continue;
+			}
+
+			let path = tree.path(self, edge.from, edge.to);
+			let misclosure = tree.positions[edge.from] + edge.vector - tree.positions[edge.to];
+			let length = path
+				.iter()
+				.chain([&index])
+				.map(|edge| self.edges[*edge].vector.length())
+				.sum::<f64>();
+			let tolerance = options.distance + options.misclosure * length;
+			let blunder = misclosure.length() > tolerance;
+
+			if blunder {
+				for edge in path.iter().chain([&index]) {
+					let ratio = worst.entry(*edge).or_default();
+					*ratio = ratio.max(misclosure.length() / tolerance);
+				}
+			}
+
+			let mut stations = vec![self.stations[edge.from]];
+			let mut station = edge.from;
+			for edge in path.iter() {
+				let edge = &self.edges[*edge];
+				station = match edge.from == station {
+					true => edge.to,
+					false => edge.from,
+				};
+				stations.push(self.stations[station]);
+			}
+
+			loops.push(Loop {
+				stations,
+				length,
+				misclosure,
+				blunder,
+			});
+		}
+
+		if worst.is_empty() {
+			return loops;
+		}
+
+		// each candidate is a whole adjustment, so only the worst are tried
+		let mut worst: Vec<(usize, f64)> = worst.into_iter().collect();
+		worst.sort_by(|a, b| b.1.total_cmp(&a.1));
+		worst.truncate(MAX_CANDIDATES);
+
+		let (_, residual) = self.adjust(&tree.positions, None);
+
+		for (edge, ratio) in worst {
+			let (positions, without) = self.adjust(&tree.positions, Some(edge));
+			let share = match residual > 0.0 {
+				true => ((residual - without) / residual).clamp(0.0, 1.0),
+				false => 0.0,
+			};
+
+			let edge = &self.edges[edge];
+			let expected = positions[edge.to] - positions[edge.from];
+			let (reading, error, explained) = single_reading(edge.vector, expected);
+
+			let score = share * explained * ratio;
+			for shot in legs[edge.leg].shots.iter() {
+				evidence.entry(*shot).or_default().push((
+					score,
+					Evidence::Misclosure {
+						share,
+						reading,
+						error,
+						explained,
+					},
+				));
+			}
+		}
+
+		loops
+	}
+
+	// Least squares positions, with the origin fixed, and the weighted sum of
+	// squared residuals, leaving out the `skip` edge. The weighted Laplacian
+	// of the network is solved by conjugate gradients for each axis.
+	fn adjust(&self, initial: &[Position], skip: Option<usize>) -> (Vec<Position>, f64) {
+		let edges: Vec<&Edge> = self
+			.edges
+			.iter()
+			.enumerate()
+			.filter(|(index, _)| Some(*index) != skip)
+			.map(|(_, edge)| edge)
+			.collect();
+
+		let axes: [fn(&mut Position) -> &mut f64; 3] = [
+			|position| &mut position.east,
+			|position| &mut position.north,
+			|position| &mut position.up,
+		];
+
+		let mut positions = initial.to_vec();
+
+		for axis in axes {
+			let mut x: Vec<f64> = positions.iter_mut().map(|p| *axis(p)).collect();
+
+			let mut rhs = vec![0.0; x.len()];
+			for edge in edges.iter() {
+				let mut vector = edge.vector;
+				let value = *axis(&mut vector);
+				rhs[edge.from] -= edge.weight * value;
+				rhs[edge.to] += edge.weight * value;
+			}
+
+			conjugate_gradients(&edges, &rhs, &mut x);
+
+			for (position, value) in positions.iter_mut().zip(x) {
+				*axis(position) = value;
+			}
+		}
+
+		let residual = edges
+			.iter()
+			.map(|edge| {
+				let residual = positions[edge.to] - positions[edge.from] - edge.vector;
+				edge.weight * residual.dot(residual)
+			})
+			.sum();
+
+		(positions, residual)
+	}
+}
+
+// x ← L⁻¹ rhs, where L is the weighted Laplacian of the edges without the
+// origin's row and column, so the origin stays at 0
+fn conjugate_gradients(edges: &[&Edge], rhs: &[f64], x: &mut [f64]) {
+	let multiply = |x: &[f64]| {
+		let mut product = vec![0.0; x.len()];
+		for edge in edges {
+			let difference = edge.weight * (x[edge.to] - x[edge.from]);
+			product[edge.to] += difference;
+			product[edge.from] -= difference;
+		}
+		product[0] = 0.0;
+		product
+	};
+	let dot = |a: &[f64], b: &[f64]| a.iter().zip(b).map(|(a, b)| a * b).sum::<f64>();
+
+	x[0] = 0.0;
+	let product = multiply(x);
+	let mut r: Vec<f64> = rhs.iter().zip(product).map(|(b, ax)| b - ax).collect();
+	r[0] = 0.0;
+	let mut p = r.clone();
+	let mut rr = dot(&r, &r);
+
+	for _ in 0..x.len() * 2 {
+		if rr < 1e-18 {
+			break;
+		}
+
+		let ap = multiply(&p);
+		let alpha = rr / dot(&p, &ap);
+
+		for i in 1..x.len() {
+			x[i] += alpha * p[i];
+			r[i] -= alpha * ap[i];
+		}
+
+		let next = dot(&r, &r);
+		let beta = next / rr;
+		rr = next;
+
+		for i in 1..x.len() {
+			p[i] = r[i] + beta * p[i];
+		}
+	}
+}
+
+// a spanning tree of the network, rooted at the origin
+struct Tree {
+	// the edge to each station's parent
+	parents: Vec<Option<usize>>,
+	depths: Vec<usize>,
+	positions: Vec<Position>,
+}
+
+impl Tree {
+	fn new(network: &Network) -> Self {
+		let count = network.stations.len();
+		let mut adjacent = vec![Vec::new(); count];
+		for (index, edge) in network.edges.iter().enumerate() {
+			adjacent[edge.from].push(index);
+			adjacent[edge.to].push(index);
+		}
+
+		let mut tree = Tree {
+			parents: vec![None; count],
+			depths: vec![0; count],
+			positions: vec![Position::default(); count],
+		};
+
+		let mut visited = vec![false; count];
+		visited[0] = true;
+		let mut queue = VecDeque::from([0]);
+
+		while let Some(station) = queue.pop_front() {
+			for index in adjacent[station].iter() {
+				let edge = &network.edges[*index];
+				let (other, vector) = match edge.from == station {
+					true => (edge.to, edge.vector),
+					false => (edge.from, -edge.vector),
+				};
+
+				if !visited[other] {
+					visited[other] = true;
+					tree.parents[other] = Some(*index);
+					tree.depths[other] = tree.depths[station] + 1;
+					tree.positions[other] = tree.positions[station] + vector;
+					queue.push_back(other);
+				}
+			}
+		}
+
+		tree
+	}
+
+	// the edges of the tree from `from` to `to`
+	fn path(&self, network: &Network, mut from: usize, mut to: usize) -> Vec<usize> {
+		let parent = |station: usize| {
+			let edge = &network.edges[self.parents[station].expect("not the root")];
+			match edge.from == station {
+				true => edge.to,
+				false => edge.from,
+			}
+		};
+
+		let mut up = Vec::new();
+		let mut down = Vec::new();
+
+		while from != to {
+			if self.depths[from] >= self.depths[to] {
+				up.push(self.parents[from].expect("not the root"));
+				from = parent(from);
+			} else {
+				down.push(self.parents[to].expect("not the root"));
+				to = parent(to);
+			}
+		}
+
+		up.extend(down.into_iter().rev());
+		up
+	}
+}
+
+// which single reading of `measured` best accounts for its difference from
+// `expected`, how far off it is, and what fraction of the difference it explains
+fn single_reading(measured: Position, expected: Position) -> (Reading, f64, f64) {
+	let readings = |vector: Position| {
+		let distance = vector.length();
+		let inclination = match distance > 0.0 {
+			true => (vector.up / distance).clamp(-1.0, 1.0).asin().to_degrees(),
+			false => 0.0,
+		};
+		(distance, vector.azimuth(), inclination)
+	};
+	let vector = |(distance, azimuth, inclination): (f64, f64, f64)| {
+		let (azimuth, inclination): (f64, f64) = (azimuth.to_radians(), inclination.to_radians());
+		let horizontal = distance * inclination.cos();
+		Position {
+			east: horizontal * azimuth.sin(),
+			north: horizontal * azimuth.cos(),
+			up: distance * inclination.sin(),
+		}
+	};
+
+	let (distance, azimuth, inclination) = readings(measured);
+	let (expected_distance, expected_azimuth, expected_inclination) = readings(expected);
+
+	let candidates = [
+		(
+			Reading::Distance,
+			distance - expected_distance,
+			(expected_distance, azimuth, inclination),
+		),
+		(
+			Reading::Azimuth,
+			(azimuth - expected_azimuth + 180.0).rem_euclid(360.0) - 180.0,
+			(distance, expected_azimuth, inclination),
+		),
+		(
+			Reading::Inclination,
+			inclination - expected_inclination,
+			(distance, azimuth, expected_inclination),
+		),
+	];
+
+	let difference = (measured - expected).length();
+
+	candidates
+		.into_iter()
+		.map(|(reading, error, corrected)| {
+			let remaining = (vector(corrected) - expected).length();
+			let explained = match difference > 0.0 {
+				true => (1.0 - remaining / difference).max(0.0),
+				false => 0.0,
+			};
+			(reading, error, explained)
+		})
+		.max_by(|a, b| a.2.total_cmp(&b.2))
+		.expect("three readings")
+}
@@ -0,0 +1,244 @@
+use crate::{parser::Document, Shot, StationId};
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Tab {
+	#[default]
+	Trips,
+	Shots,
+	References,
+}
+
+impl Tab {
+	pub const ALL: [Tab; 3] = [Tab::Trips, Tab::Shots, Tab::References];
+
+	pub fn title(self) -> &'static str {
+		match self {
+			Tab::Trips => "Trips",
+			Tab::Shots => "Shots",
+			Tab::References => "References",
+		}
+	}
+}
+
+// What the rows are limited to. The trip limits the shots, and the trips to
+// that trip; the station limits the shots and references to those at the
+// station, and the trips to those with shots at the station.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Filter {
+	pub trip: Option<i16>,
+	pub station: Option<StationId>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Row {
+	// index into `Document::trips`
+	Trip(usize),
+	// consecutive shots between the same stations, in either direction, as
+	// indices into `Document::shots`
+	Leg {
+		from: StationId,
+		to: StationId,
+		shots: Vec<usize>,
+	},
+	// a shot without a `to` station, or without any station
+	Splay(usize),
+	// index into `Document::references`
+	Reference(usize),
+}
+
+// The state of a browser over a document's trips, shots and references: the
+// open tab, the filter and the selected row.
+#[derive(Debug)]
+pub struct Browser<'a, 'd> {
+	document: &'a Document<'d>,
+	tab: Tab,
+	filter: Filter,
+	rows: Vec<Row>,
+	selected: usize,
+}
+
+impl<'a, 'd> Browser<'a, 'd> {
+	pub fn new(document: &'a Document<'d>) -> Self {
+		let mut browser = Self {
+			document,
+			tab: Tab::default(),
+			filter: Filter::default(),
+			rows: Vec::new(),
+			selected: 0,
+		};
+		browser.update();
+		browser
+	}
+
+	pub fn document(&self) -> &'a Document<'d> {
+		self.document
+	}
+
+	pub fn tab(&self) -> Tab {
+		self.tab
+	}
+
+	pub fn filter(&self) -> Filter {
+		self.filter
+	}
+
+	pub fn rows(&self) -> &[Row] {
+		&self.rows
+	}
+
+	pub fn selected(&self) -> Option<&Row> {
+		self.rows.get(self.selected)
+	}
+
+	pub fn selected_index(&self) -> usize {
+		self.selected
+	}
+
+	pub fn set_tab(&mut self, tab: Tab) {
+		self.tab = tab;
+		self.selected = 0;
+		self.update();
+	}
+
+	// keeps the selected row if it's still shown
+	pub fn set_filter(&mut self, filter: Filter) {
+		let selected = self.selected().cloned();
+		self.filter = filter;
+		self.update();
+
+		self.selected = selected
+			.and_then(|selected| self.rows.iter().position(|row| *row == selected))
+			.unwrap_or(0);
+	}
+
+	pub fn select(&mut self, index: usize) {
+		self.selected = index.min(self.rows.len().saturating_sub(1));
+	}
+
+	// moves the selection by a number of rows, stopping at either end
+	pub fn move_by(&mut self, rows: isize) {
+		self.select(self.selected.saturating_add_signed(rows));
+	}
+
+	// Opens the selected trip or reference, as the shots of that trip or at
+	// that reference's station.
+	pub fn open(&mut self) {
+		let filter = match self.selected() {
+			Some(Row::Trip(index)) => Filter {
+				trip: i16::try_from(*index).ok(),
+				..self.filter
+			},
+			Some(Row::Reference(index)) => match self.document.references[*index].station {
+				Some(station) => Filter {
+					station: Some(station),
+					..self.filter
+				},
+				None => return,
+			},
+			_ => return,
+		};
+
+		self.filter = filter;
+		self.set_tab(Tab::Shots);
+	}
+
+	// Jumps from the selected leg or splay to the legs connected to one of its
+	// stations: the `to` station if `to`, or else the `from` station.
+	pub fn follow(&mut self, to: bool) {
+		let station = match self.selected() {
+			Some(Row::Leg { from, to: end, .. }) => Some(if to { *end } else { *from }),
+			Some(Row::Splay(index)) if !to => self.document.shots[*index].from,
+			_ => None,
+		};
+
+		if let Some(station) = station {
+			self.set_filter(Filter {
+				station: Some(station),
+				..self.filter
+			});
+		}
+	}
+
+	fn update(&mut self) {
+		self.rows = match self.tab {
+			Tab::Trips => self.trips(),
+			Tab::Shots => self.shots(),
+			Tab::References => self.references(),
+		};
+		self.select(self.selected);
+	}
+
+	fn trips(&self) -> Vec<Row> {
+		(0..self.document.trips.len())
+			.filter(|index| match self.filter.trip {
+				Some(trip) => usize::try_from(trip) == Ok(*index),
+				None => true,
+			})
+			.filter(|index| match self.filter.station {
+				Some(station) => self.document.shots.iter().any(|shot| {
+					usize::try_from(shot.trip_index) == Ok(*index) && at(shot, station)
+				}),
+				None => true,
+			})
+			.map(Row::Trip)
+			.collect()
+	}
+
+	fn shots(&self) -> Vec<Row> {
+		let mut rows = Vec::new();
+
+		for (index, shot) in self.document.shots.iter().enumerate() {
+			let shown = self.filter.trip.is_none_or(|trip| shot.trip_index == trip)
+				&& self.filter.station.is_none_or(|station| at(shot, station));
+
+			if !shown {
+				continue;
+			}
+
+			let (Some(from), Some(to)) = (shot.from, shot.to) else {
+				rows.push(Row::Splay(index));
+				continue;
+			};
+
+			// a repeat of the shot before, in either direction
+			if let Some(Row::Leg {
+				from: leg_from,
+				to: leg_to,
+				shots,
+			}) = rows.last_mut()
+			{
+				let same = (*leg_from, *leg_to) == (from, to) || (*leg_from, *leg_to) == (to, from);
+				if same && shots.last() == Some(&(index.wrapping_sub(1))) {
+					shots.push(index);
+					continue;
+				}
+			}
+
+			rows.push(Row::Leg {
+				from,
+				to,
+				shots: vec![index],
+			});
+		}
+
+		rows
+	}
+
+	fn references(&self) -> Vec<Row> {
+		self.document
+			.references
+			.iter()
+			.enumerate()
+			.filter(|(_, reference)| {
+				self.filter
+					.station
+					.is_none_or(|station| reference.station == Some(station))
+			})
+			.map(|(index, _)| Row::Reference(index))
+			.collect()
+	}
+}
+
+fn at(shot: &Shot, station: StationId) -> bool {
+	shot.from == Some(station) || shot.to == Some(station)
+}
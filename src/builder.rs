@@ -0,0 +1,337 @@
+use std::borrow::Cow;
+
+use chrono::NaiveDateTime;
+use thiserror::Error;
+
+use crate::{
+	geodesy::Crs, parser::Document, units, Color, CrossSection, Drawing, Element, InvalidColor,
+	InvalidStationId, Mapping, Point, Polygon, Reference, Shot, ShotFlags, StationId, Trip,
+};
+
+#[derive(Debug, Error, PartialEq)]
+pub enum BuildError {
+	#[error(transparent)]
+	InvalidColor(#[from] InvalidColor),
+
+	#[error(transparent)]
+	InvalidStationId(#[from] InvalidStationId),
+
+	#[error("invalid trip index: {0}")]
+	InvalidTripIndex(i16),
+
+	#[error("no shot")]
+	NoShot,
+
+	#[error("{0} out of range: {1}")]
+	OutOfRange(&'static str, f64),
+}
+
+#[derive(Clone, Copy, Debug)]
+enum View {
+	Outline,
+	Sideview,
+}
+
+// Builds a `Document` from values in metres and degrees, checking each value
+// fits the file format. Shots belong to the most recently added trip, and
+// `comment`, `flipped` and `trip_index` modify the most recently added shot.
+// The first invalid value is reported by `build`.
+#[derive(Debug, Default)]
+pub struct DocumentBuilder<'a> {
+	trips: Vec<Trip<'a>>,
+	shots: Vec<Shot<'a>>,
+	references: Vec<Reference<'a>>,
+	outline: Vec<Element>,
+	sideview: Vec<Element>,
+	crs: Option<Crs>,
+	error: Option<BuildError>,
+}
+
+impl<'a> DocumentBuilder<'a> {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	pub fn trip(
+		self,
+		time: NaiveDateTime,
+		declination: f64,
+		comment: impl Into<Cow<'a, str>>,
+	) -> Self {
+		self.try_with(|builder| {
+			let trip = Trip {
+				time,
+				comment: comment.into(),
+				declination: angle("declination", declination)?,
+			};
+
+			builder.trips.push(trip);
+
+			Ok(())
+		})
+	}
+
+	pub fn shot(self, from: &str, to: &str, distance: f64, azimuth: f64, inclination: f64) -> Self {
+		self.try_with(|builder| {
+			let to = to.parse()?;
+			builder.push_shot(from, Some(to), distance, azimuth, inclination)
+		})
+	}
+
+	pub fn splay(self, from: &str, distance: f64, azimuth: f64, inclination: f64) -> Self {
+		self.try_with(|builder| builder.push_shot(from, None, distance, azimuth, inclination))
+	}
+
+	pub fn comment(self, comment: impl Into<Cow<'a, str>>) -> Self {
+		self.try_with(|builder| {
+			let shot = builder.shots.last_mut().ok_or(BuildError::NoShot)?;
+			shot.flags.insert(ShotFlags::HAS_COMMENT);
+			shot.comment = Some(comment.into());
+
+			Ok(())
+		})
+	}
+
+	pub fn flipped(self) -> Self {
+		self.try_with(|builder| {
+			let shot = builder.shots.last_mut().ok_or(BuildError::NoShot)?;
+			shot.flags.insert(ShotFlags::FLIPPED);
+
+			Ok(())
+		})
+	}
+
+	// -1 for no trip; checked against the trips in `build`
+	pub fn trip_index(self, index: i16) -> Self {
+		self.try_with(|builder| {
+			let shot = builder.shots.last_mut().ok_or(BuildError::NoShot)?;
+			shot.trip_index = index;
+
+			Ok(())
+		})
+	}
+
+	pub fn reference(
+		self,
+		station: &str,
+		east: f64,
+		north: f64,
+		altitude: f64,
+		comment: impl Into<Cow<'a, str>>,
+	) -> Self {
+		self.try_with(|builder| {
+			let reference = Reference {
+				station: Some(station.parse()?),
+				east: millimetres("east", east)?,
+				north: millimetres("north", north)?,
+				altitude: millimetres("altitude", altitude)?,
+				comment: comment.into(),
+			};
+
+			builder.references.push(reference);
+
+			Ok(())
+		})
+	}
+
+	pub fn outline_polygon(self, color: &str, points: &[(f64, f64)]) -> Self {
+		self.try_with(|builder| builder.push_polygon(View::Outline, color, points))
+	}
+
+	pub fn sideview_polygon(self, color: &str, points: &[(f64, f64)]) -> Self {
+		self.try_with(|builder| builder.push_polygon(View::Sideview, color, points))
+	}
+
+	// `direction` is the projection azimuth in degrees, or `None` for a horizontal section
+	pub fn outline_cross_section(
+		self,
+		station: &str,
+		position: (f64, f64),
+		direction: Option<f64>,
+	) -> Self {
+		self.try_with(|builder| {
+			builder.push_cross_section(View::Outline, station, position, direction)
+		})
+	}
+
+	pub fn sideview_cross_section(
+		self,
+		station: &str,
+		position: (f64, f64),
+		direction: Option<f64>,
+	) -> Self {
+		self.try_with(|builder| {
+			builder.push_cross_section(View::Sideview, station, position, direction)
+		})
+	}
+
+	pub fn crs(mut self, crs: Crs) -> Self {
+		self.crs = Some(crs);
+		self
+	}
+
+	pub fn build(self) -> Result<Document<'a>, BuildError> {
+		if let Some(error) = self.error {
+			return Err(error);
+		}
+
+		let trips = self.trips.len();
+		for shot in self.shots.iter() {
+			if shot.trip_index < -1 || shot.trip_index as isize >= trips as isize {
+				return Err(BuildError::InvalidTripIndex(shot.trip_index));
+			}
+		}
+
+		let drawing = |elements: Vec<Element>| Drawing {
+			mapping: mapping(),
+			elements: elements.into_boxed_slice(),
+		};
+
+		Ok(Document {
+			references: self.references.into_boxed_slice(),
+			shots: self.shots.into_boxed_slice(),
+			trips: self.trips.into_boxed_slice(),
+			mapping: mapping(),
+			outline: drawing(self.outline),
+			sideview: drawing(self.sideview),
+			crs: self.crs,
+		})
+	}
+
+	fn try_with(mut self, f: impl FnOnce(&mut Self) -> Result<(), BuildError>) -> Self {
+		if self.error.is_none() {
+			if let Err(error) = f(&mut self) {
+				self.error = Some(error);
+			}
+		}
+
+		self
+	}
+
+	fn push_shot(
+		&mut self,
+		from: &str,
+		to: Option<StationId>,
+		distance: f64,
+		azimuth: f64,
+		inclination: f64,
+	) -> Result<(), BuildError> {
+		if !(-90.0..=90.0).contains(&inclination) {
+			return Err(BuildError::OutOfRange("inclination", inclination));
+		}
+
+		if distance < 0.0 {
+			return Err(BuildError::OutOfRange("distance", distance));
+		}
+
+		// the last trip, or -1 before the first
+		let trip_index = match self.trips.len().checked_sub(1) {
+			Some(index) => i16::try_from(index)
+				.map_err(|_| BuildError::OutOfRange("trip index", index as f64))?,
+			None => -1,
+		};
+
+		let shot = Shot {
+			from: Some(from.parse()?),
+			to,
+			azimuth: angle("azimuth", azimuth)?,
+			distance: millimetres("distance", distance)?,
+			inclination: angle("inclination", inclination)?,
+			flags: ShotFlags::empty(),
+			roll: 0,
+			trip_index,
+			comment: None,
+		};
+
+		self.shots.push(shot);
+
+		Ok(())
+	}
+
+	fn push_polygon(
+		&mut self,
+		view: View,
+		color: &str,
+		points: &[(f64, f64)],
+	) -> Result<(), BuildError> {
+		let polygon = Polygon {
+			points: points
+				.iter()
+				.map(|point| self::point(*point))
+				.collect::<Result<_, _>>()?,
+			color: color.parse::<Color>()?,
+		};
+
+		self.elements(view).push(Element::Polygon(polygon));
+
+		Ok(())
+	}
+
+	fn push_cross_section(
+		&mut self,
+		view: View,
+		station: &str,
+		position: (f64, f64),
+		direction: Option<f64>,
+	) -> Result<(), BuildError> {
+		let direction = match direction {
+			// the file stores the direction as an unsigned angle
+			Some(direction) => i32::from(angle("direction", direction)? as u16),
+			None => -1,
+		};
+
+		let cross_section = CrossSection {
+			position: point(position)?,
+			station: station.parse()?,
+			direction,
+		};
+
+		self.elements(view)
+			.push(Element::CrossSection(cross_section));
+
+		Ok(())
+	}
+
+	fn elements(&mut self, view: View) -> &mut Vec<Element> {
+		match view {
+			View::Outline => &mut self.outline,
+			View::Sideview => &mut self.sideview,
+		}
+	}
+}
+
+// degrees to internal angle units (full circle = 2^16), wrapping around the circle
+fn angle(name: &'static str, degrees: f64) -> Result<i16, BuildError> {
+	if !degrees.is_finite() {
+		return Err(BuildError::OutOfRange(name, degrees));
+	}
+
+	let units = units::units(degrees).round().rem_euclid(65536.0);
+
+	Ok(units as u16 as i16)
+}
+
+fn mapping() -> Mapping {
+	Mapping {
+		origin: Point { x: 0, y: 0 },
+		scale: 500,
+	}
+}
+
+// metres to millimetres, checked against the range of the field
+fn millimetres<T: TryFrom<i64>>(name: &'static str, metres: f64) -> Result<T, BuildError> {
+	let millimetres = (metres * 1000.0).round();
+
+	if !(i64::MIN as f64..=i64::MAX as f64).contains(&millimetres) {
+		return Err(BuildError::OutOfRange(name, metres));
+	}
+
+	T::try_from(millimetres as i64).map_err(|_| BuildError::OutOfRange(name, metres))
+}
+
+fn point((x, y): (f64, f64)) -> Result<Point, BuildError> {
+	Ok(Point {
+		x: millimetres("x", x)?,
+		y: millimetres("y", y)?,
+	})
+}
@@ -0,0 +1,254 @@
+use std::io::{self, Write};
+
+use crate::{
+	geodesy::Crs,
+	parser::Document,
+	reduction::{self, Position},
+	StationId,
+};
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum Kind {
+	Station,
+	Splay,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct CloudPoint {
+	pub position: Position,
+	pub kind: Kind,
+	// the station, or the station the splay was measured from
+	pub station: StationId,
+	// index into `Document::shots`: the splay, or the first shot to the station
+	pub shot: usize,
+	pub trip_index: i16,
+}
+
+// Stations and splay ends, in metres. If the centerline has an anchor the
+// points are georeferenced, in the references' coordinates; otherwise they're
+// relative to the first station. For a document with a CRS, the coordinates
+// are only aligned with the grid if reduced to grid north.
+pub fn points(document: &Document, options: &reduction::Options) -> Vec<CloudPoint> {
+	let centerline = reduction::reduce_with_options(document, options);
+
+	let offset = centerline
+		.anchor(document)
+		.map(|anchor| anchor.offset)
+		.unwrap_or_default();
+
+	let mut points = Vec::new();
+
+	for (station, position) in centerline.stations.iter() {
+		let shot = document
+			.shots
+			.iter()
+			.position(|shot| shot.from == Some(*station) || shot.to == Some(*station));
+
+		let Some(shot) = shot else {
+			continue;
+		};
+
+		points.push(CloudPoint {
+			position: *position + offset,
+			kind: Kind::Station,
+			station: *station,
+			shot,
+			trip_index: document.shots[shot].trip_index,
+		});
+	}
+
+	for splay in centerline.splays.iter() {
+		let Some(from) = centerline.position(&splay.from) else {
+			continue;
+		};
+
+		points.push(CloudPoint {
+			position: from + splay.vector + offset,
+			kind: Kind::Splay,
+			station: splay.from,
+			shot: splay.shot,
+			trip_index: document.shots[splay.shot].trip_index,
+		});
+	}
+
+	points
+}
+
+// Binary PLY, with double precision coordinates for georeferenced points.
+pub fn write_ply<W: Write>(points: &[CloudPoint], output: &mut W) -> io::Result<()> {
+	writeln!(output, "ply")?;
+	writeln!(output, "format binary_little_endian 1.0")?;
+	writeln!(output, "comment kind: 0 station, 1 splay")?;
+	writeln!(output, "element vertex {}", points.len())?;
+	writeln!(output, "property double x")?;
+	writeln!(output, "property double y")?;
+	writeln!(output, "property double z")?;
+	writeln!(output, "property short trip")?;
+	writeln!(output, "property uint shot")?;
+	writeln!(output, "property uchar kind")?;
+	writeln!(output, "end_header")?;
+
+	for point in points {
+		let position = point.position;
+		for coordinate in [position.east, position.north, position.up] {
+			output.write_all(&coordinate.to_le_bytes())?;
+		}
+
+		output.write_all(&point.trip_index.to_le_bytes())?;
+		output.write_all(&(point.shot as u32).to_le_bytes())?;
+		output.write_all(&[kind(point.kind)])?;
+	}
+
+	Ok(())
+}
+
+// the classifications of stations and splays, from LAS's user-definable range
+pub const LAS_STATION: u8 = 64;
+pub const LAS_SPLAY: u8 = 65;
+
+const LAS_HEADER: u16 = 375;
+const LAS_VLR_HEADER: u32 = 54;
+const LAS_EXTRA_BYTES: u32 = 192;
+// point data record format 6, and the trip and shot as extra bytes
+const LAS_RECORD: u16 = 30 + 2 + 4;
+
+// LAS 1.4 with point data record format 6 and millimetre precision. Points are
+// classified as `LAS_STATION` or `LAS_SPLAY`, and have the trip index and shot
+// index as extra bytes attributes named "trip" and "shot". The CRS, if any, is
+// recorded as WKT.
+pub fn write_las<W: Write>(
+	points: &[CloudPoint],
+	crs: Option<Crs>,
+	output: &mut W,
+) -> io::Result<()> {
+	let mut min = [f64::INFINITY; 3];
+	let mut max = [f64::NEG_INFINITY; 3];
+	for point in points {
+		let position = point.position;
+		for (axis, coordinate) in [position.east, position.north, position.up]
+			.into_iter()
+			.enumerate()
+		{
+			min[axis] = min[axis].min(coordinate);
+			max[axis] = max[axis].max(coordinate);
+		}
+	}
+	if points.is_empty() {
+		(min, max) = ([0.0; 3], [0.0; 3]);
+	}
+
+	let offset = min.map(f64::floor);
+
+	// null-terminated
+	let wkt = crs.map(|crs| {
+		let mut wkt = crs.wkt().into_bytes();
+		wkt.push(0);
+		wkt
+	});
+	let records = match &wkt {
+		Some(wkt) => 2 * LAS_VLR_HEADER + 2 * LAS_EXTRA_BYTES + wkt.len() as u32,
+		None => LAS_VLR_HEADER + 2 * LAS_EXTRA_BYTES,
+	};
+
+	let mut header = Vec::with_capacity(LAS_HEADER as usize);
+	header.extend_from_slice(b"LASF");
+	header.extend_from_slice(&0_u16.to_le_bytes()); // file source ID
+	header.extend_from_slice(&16_u16.to_le_bytes()); // WKT, required for format 6
+	header.extend_from_slice(&[0; 16]); // project ID
+	header.extend_from_slice(&[1, 4]);
+	header.extend_from_slice(&text::<32>("pocket-topo"));
+	header.extend_from_slice(&text::<32>("pocket-topo"));
+	header.extend_from_slice(&[0; 4]); // creation date
+	header.extend_from_slice(&LAS_HEADER.to_le_bytes());
+	header.extend_from_slice(&(u32::from(LAS_HEADER) + records).to_le_bytes());
+	header.extend_from_slice(&(1 + u32::from(wkt.is_some())).to_le_bytes()); // variable length records
+	header.push(6);
+	header.extend_from_slice(&LAS_RECORD.to_le_bytes());
+	header.extend_from_slice(&[0; 4 + 5 * 4]); // legacy point counts
+	for _ in 0..3 {
+		header.extend_from_slice(&0.001_f64.to_le_bytes());
+	}
+	for coordinate in offset {
+		header.extend_from_slice(&coordinate.to_le_bytes());
+	}
+	for (max, min) in max.into_iter().zip(min) {
+		header.extend_from_slice(&max.to_le_bytes());
+		header.extend_from_slice(&min.to_le_bytes());
+	}
+	header.extend_from_slice(&[0; 8 + 8 + 4]); // waveforms and extended records
+	header.extend_from_slice(&(points.len() as u64).to_le_bytes());
+	header.extend_from_slice(&(points.len() as u64).to_le_bytes()); // all first returns
+	header.extend_from_slice(&[0; 14 * 8]);
+	output.write_all(&header)?;
+
+	// the extra bytes record, describing the trip and shot attributes
+	output.write_all(&[0, 0])?;
+	output.write_all(&text::<16>("LASF_Spec"))?;
+	output.write_all(&4_u16.to_le_bytes())?;
+	output.write_all(&(2 * LAS_EXTRA_BYTES as u16).to_le_bytes())?;
+	output.write_all(&text::<32>("extra bytes"))?;
+	output.write_all(&extra_bytes(4, "trip", "trip index, -1 for none"))?;
+	output.write_all(&extra_bytes(5, "shot", "shot index"))?;
+
+	if let Some(wkt) = wkt {
+		output.write_all(&[0, 0])?;
+		output.write_all(&text::<16>("LASF_Projection"))?;
+		output.write_all(&2112_u16.to_le_bytes())?;
+		output.write_all(&(wkt.len() as u16).to_le_bytes())?;
+		output.write_all(&text::<32>("OGC WKT"))?;
+		output.write_all(&wkt)?;
+	}
+
+	for point in points {
+		let position = point.position;
+		for (coordinate, offset) in [position.east, position.north, position.up]
+			.into_iter()
+			.zip(offset)
+		{
+			let coordinate = ((coordinate - offset) * 1000.0).round() as i32;
+			output.write_all(&coordinate.to_le_bytes())?;
+		}
+
+		let classification = match point.kind {
+			Kind::Station => LAS_STATION,
+			Kind::Splay => LAS_SPLAY,
+		};
+
+		output.write_all(&0_u16.to_le_bytes())?; // intensity
+		output.write_all(&[0x11, 0, classification, 0])?; // the first of one return
+		output.write_all(&0_i16.to_le_bytes())?; // scan angle
+		output.write_all(&0_u16.to_le_bytes())?; // point source ID
+		output.write_all(&0_f64.to_le_bytes())?; // GPS time
+		output.write_all(&point.trip_index.to_le_bytes())?;
+		output.write_all(&(point.shot as u32).to_le_bytes())?;
+	}
+
+	Ok(())
+}
+
+fn kind(kind: Kind) -> u8 {
+	match kind {
+		Kind::Station => 0,
+		Kind::Splay => 1,
+	}
+}
+
+// an extra bytes descriptor of a data type, without scale, offset or limits
+fn extra_bytes(data_type: u8, name: &str, description: &str) -> [u8; LAS_EXTRA_BYTES as usize] {
+	let mut descriptor = [0; LAS_EXTRA_BYTES as usize];
+	descriptor[2] = data_type;
+	descriptor[4..36].copy_from_slice(&text::<32>(name));
+	descriptor[160..192].copy_from_slice(&text::<32>(description));
+	descriptor
+}
+
+// a null-padded string
+fn text<const N: usize>(value: &str) -> [u8; N] {
+	let mut text = [0; N];
+	let length = value.len().min(N);
+	text[..length].copy_from_slice(&value.as_bytes()[..length]);
+	text
+}
@@ -0,0 +1,363 @@
+use std::collections::HashMap;
+
+use crate::{parser::Document, units, Drawing, Element, Polygon, Reference, Shot, StationId, Trip};
+
+// An item of a `Document`, by its index. Drawing items are indices into the
+// drawing's elements.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum Item {
+	Trip(usize),
+	Shot(usize),
+	Reference(usize),
+	Outline(usize),
+	Sideview(usize),
+}
+
+// `Added` items are in the new document, `Removed` items in the old document,
+// and `Modified` items are the old and new versions of an item.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Change {
+	Added(Item),
+	Removed(Item),
+	Modified(Item, Item),
+}
+
+// Differences which are treated as noise rather than changes.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Tolerances {
+	pub distance: f64, // m
+	pub angle: f64,    // degrees
+}
+
+impl Default for Tolerances {
+	fn default() -> Self {
+		Self {
+			distance: 0.005,
+			angle: 0.1,
+		}
+	}
+}
+
+// Compares two documents. Trips are matched by index, as shots refer to them
+// by index. Shots are matched by their stations and trip, and references by
+// their station; where several share a key, the closest readings are matched
+// first. Polygons are matched by color and points, and polygons which overlap
+// another of the same color are reported as modified.
+pub fn diff(old: &Document, new: &Document, tolerances: &Tolerances) -> Vec<Change> {
+	let tolerances = Thresholds {
+		distance: (tolerances.distance * 1000.0).round() as i64,
+		angle: units::units(tolerances.angle).round() as u16,
+	};
+
+	let mut changes = Vec::new();
+
+	changes.extend(diff_trips(&old.trips, &new.trips, &tolerances));
+
+	changes.extend(
+		diff_keyed(&old.shots, &new.shots, shot_key, |old, new| {
+			compare_shots(old, new, &tolerances)
+		})
+		.map(|change| change.map(Item::Shot)),
+	);
+
+	changes.extend(
+		diff_keyed(
+			&old.references,
+			&new.references,
+			|reference| reference.station,
+			|old, new| compare_references(old, new, &tolerances),
+		)
+		.map(|change| change.map(Item::Reference)),
+	);
+
+	changes.extend(
+		diff_drawings(&old.outline, &new.outline, &tolerances)
+			.map(|change| change.map(Item::Outline)),
+	);
+
+	changes.extend(
+		diff_drawings(&old.sideview, &new.sideview, &tolerances)
+			.map(|change| change.map(Item::Sideview)),
+	);
+
+	changes
+}
+
+// tolerances in the file's units
+struct Thresholds {
+	distance: i64,
+	angle: u16,
+}
+
+impl Thresholds {
+	fn distance(&self, old: i64, new: i64) -> bool {
+		(old - new).abs() <= self.distance
+	}
+
+	fn angle(&self, old: i16, new: i16) -> bool {
+		units::angle_difference(old, new) <= self.angle
+	}
+}
+
+// a change to the index of an item
+enum IndexChange {
+	Added(usize),
+	Removed(usize),
+	Modified(usize, usize),
+}
+
+impl IndexChange {
+	fn map(self, item: fn(usize) -> Item) -> Change {
+		match self {
+			IndexChange::Added(new) => Change::Added(item(new)),
+			IndexChange::Removed(old) => Change::Removed(item(old)),
+			IndexChange::Modified(old, new) => Change::Modified(item(old), item(new)),
+		}
+	}
+}
+
+fn diff_trips(old: &[Trip], new: &[Trip], tolerances: &Thresholds) -> Vec<Change> {
+	let mut changes = Vec::new();
+
+	for (index, (old, new)) in old.iter().zip(new.iter()).enumerate() {
+		let unchanged = old.time == new.time
+			&& old.comment == new.comment
+			&& tolerances.angle(old.declination, new.declination);
+
+		if !unchanged {
+			changes.push(Change::Modified(Item::Trip(index), Item::Trip(index)));
+		}
+	}
+
+	changes.extend((new.len()..old.len()).map(|index| Change::Removed(Item::Trip(index))));
+	changes.extend((old.len()..new.len()).map(|index| Change::Added(Item::Trip(index))));
+
+	changes
+}
+
+// how two items with the same key compare
+enum Comparison {
+	Unchanged,
+	// the score is lower for more similar items
+	Modified(u64),
+	Unrelated,
+}
+
+// Matches items with equal keys: unchanged pairs are matched first, then the
+// most similar remaining pairs are reported as modified.
+fn diff_keyed<'a, T, K, C>(
+	old: &'a [T],
+	new: &'a [T],
+	key: impl Fn(&T) -> K,
+	compare: C,
+) -> impl Iterator<Item = IndexChange> + 'a
+where
+	K: Eq + std::hash::Hash,
+	C: Fn(&T, &T) -> Comparison,
+{
+	let mut groups: HashMap<K, (Vec<usize>, Vec<usize>)> = HashMap::new();
+	for (index, item) in old.iter().enumerate() {
+		groups.entry(key(item)).or_default().0.push(index);
+	}
+	for (index, item) in new.iter().enumerate() {
+		groups.entry(key(item)).or_default().1.push(index);
+	}
+
+	let mut changes = Vec::new();
+
+	for (_, (mut olds, mut news)) in groups {
+		// unchanged items
+		olds.retain(|old_index| {
+			let unchanged = news.iter().position(|new_index| {
+				let comparison = compare(&old[*old_index], &new[*new_index]);
+				matches!(comparison, Comparison::Unchanged)
+			});
+
+			match unchanged {
+				Some(position) => {
+					news.remove(position);
+					false
+				}
+				None => true,
+			}
+		});
+
+		// modified items, closest first
+		let mut pairs = Vec::new();
+		for old_index in olds.iter() {
+			for new_index in news.iter() {
+				if let Comparison::Modified(score) = compare(&old[*old_index], &new[*new_index]) {
+					pairs.push((score, *old_index, *new_index));
+				}
+			}
+		}
+		pairs.sort();
+
+		for (_, old_index, new_index) in pairs {
+			if olds.contains(&old_index) && news.contains(&new_index) {
+				olds.retain(|index| *index != old_index);
+				news.retain(|index| *index != new_index);
+				changes.push(IndexChange::Modified(old_index, new_index));
+			}
+		}
+
+		changes.extend(olds.into_iter().map(IndexChange::Removed));
+		changes.extend(news.into_iter().map(IndexChange::Added));
+	}
+
+	changes.sort_by_key(|change| match change {
+		IndexChange::Removed(old) | IndexChange::Modified(old, _) => (*old, 0),
+		IndexChange::Added(new) => (*new, 1),
+	});
+
+	changes.into_iter()
+}
+
+fn shot_key(shot: &Shot) -> (Option<StationId>, Option<StationId>, i16) {
+	(shot.from, shot.to, shot.trip_index)
+}
+
+fn compare_shots(old: &Shot, new: &Shot, tolerances: &Thresholds) -> Comparison {
+	let unchanged = tolerances.distance(old.distance.into(), new.distance.into())
+		&& tolerances.angle(old.azimuth, new.azimuth)
+		&& tolerances.angle(old.inclination, new.inclination)
+		&& old.flags == new.flags
+		&& old.comment == new.comment;
+
+	if unchanged {
+		return Comparison::Unchanged;
+	}
+
+	let distance = (i64::from(old.distance) - i64::from(new.distance)).unsigned_abs();
+	let azimuth = units::angle_difference(old.azimuth, new.azimuth);
+	let inclination = units::angle_difference(old.inclination, new.inclination);
+
+	Comparison::Modified(distance + u64::from(azimuth) + u64::from(inclination))
+}
+
+fn compare_references(old: &Reference, new: &Reference, tolerances: &Thresholds) -> Comparison {
+	let unchanged = tolerances.distance(old.east, new.east)
+		&& tolerances.distance(old.north, new.north)
+		&& tolerances.distance(old.altitude.into(), new.altitude.into())
+		&& old.comment == new.comment;
+
+	if unchanged {
+		return Comparison::Unchanged;
+	}
+
+	let east = (old.east - new.east).unsigned_abs();
+	let north = (old.north - new.north).unsigned_abs();
+	let altitude = (i64::from(old.altitude) - i64::from(new.altitude)).unsigned_abs();
+
+	Comparison::Modified(east + north + altitude)
+}
+
+fn diff_drawings(
+	old: &Drawing,
+	new: &Drawing,
+	tolerances: &Thresholds,
+) -> impl Iterator<Item = IndexChange> {
+	let old = polygons(old);
+	let new = polygons(new);
+
+	let changes = diff_keyed(
+		&old,
+		&new,
+		|(_, polygon)| polygon.color,
+		|(_, old), (_, new)| compare_polygons(old, new, tolerances),
+	);
+
+	// map from the indices of the polygons to those of the elements
+	changes
+		.map(|change| match change {
+			IndexChange::Added(index) => IndexChange::Added(new[index].0),
+			IndexChange::Removed(index) => IndexChange::Removed(old[index].0),
+			IndexChange::Modified(old_index, new_index) => {
+				IndexChange::Modified(old[old_index].0, new[new_index].0)
+			}
+		})
+		.collect::<Vec<_>>()
+		.into_iter()
+}
+
+// the drawing's polygons, with their indices in the elements
+fn polygons(drawing: &Drawing) -> Vec<(usize, &Polygon)> {
+	let elements = drawing.elements.iter().enumerate();
+
+	elements
+		.filter_map(|(index, element)| match element {
+			Element::Polygon(polygon) => Some((index, polygon)),
+			_ => None,
+		})
+		.collect()
+}
+
+// polygons which overlap are modified versions of each other, and more similar
+// the closer their centres
+fn compare_polygons(old: &Polygon, new: &Polygon, tolerances: &Thresholds) -> Comparison {
+	let unchanged = old.points.len() == new.points.len()
+		&& old.points.iter().zip(new.points.iter()).all(|(old, new)| {
+			tolerances.distance(old.x.into(), new.x.into())
+				&& tolerances.distance(old.y.into(), new.y.into())
+		});
+
+	if unchanged {
+		return Comparison::Unchanged;
+	}
+
+	let (Some(old), Some(new)) = (bounds(old), bounds(new)) else {
+		return Comparison::Unrelated;
+	};
+
+	let overlaps = old.min_x <= new.max_x
+		&& new.min_x <= old.max_x
+		&& old.min_y <= new.max_y
+		&& new.min_y <= old.max_y;
+
+	if !overlaps {
+		return Comparison::Unrelated;
+	}
+
+	let x = (old.centre_x() - new.centre_x()).unsigned_abs();
+	let y = (old.centre_y() - new.centre_y()).unsigned_abs();
+
+	Comparison::Modified(x + y)
+}
+
+struct Bounds {
+	min_x: i64,
+	min_y: i64,
+	max_x: i64,
+	max_y: i64,
+}
+
+impl Bounds {
+	fn centre_x(&self) -> i64 {
+		(self.min_x + self.max_x) / 2
+	}
+
+	fn centre_y(&self) -> i64 {
+		(self.min_y + self.max_y) / 2
+	}
+}
+
+fn bounds(polygon: &Polygon) -> Option<Bounds> {
+	let first = polygon.points.first()?;
+
+	let mut bounds = Bounds {
+		min_x: first.x.into(),
+		min_y: first.y.into(),
+		max_x: first.x.into(),
+		max_y: first.y.into(),
+	};
+
+	for point in polygon.points.iter() {
+		bounds.min_x = bounds.min_x.min(point.x.into());
+		bounds.min_y = bounds.min_y.min(point.y.into());
+		bounds.max_x = bounds.max_x.max(point.x.into());
+		bounds.max_y = bounds.max_y.max(point.y.into());
+	}
+
+	Some(bounds)
+}
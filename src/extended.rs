@@ -0,0 +1,201 @@
+use std::collections::BTreeMap;
+
+use crate::{
+	parser::Document,
+	reduction::{self, Leg, Position},
+	Point, ShotFlags, StationId,
+};
+
+// Which way a leg is drawn in an extended elevation, looking from its `from`
+// station to its `to` station.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum Direction {
+	Left,
+	Right,
+	Vertical,
+}
+
+impl Direction {
+	fn reversed(self) -> Self {
+		match self {
+			Direction::Left => Direction::Right,
+			Direction::Right => Direction::Left,
+			Direction::Vertical => Direction::Vertical,
+		}
+	}
+
+	fn sign(self) -> f64 {
+		match self {
+			Direction::Left => -1.0,
+			Direction::Right => 1.0,
+			Direction::Vertical => 0.0,
+		}
+	}
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ExtendedLeg {
+	pub from: StationId,
+	pub to: StationId,
+	pub direction: Direction,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ExtendedSplay {
+	// index into `Document::shots`
+	pub shot: usize,
+	pub from: Point,
+	pub to: Point,
+}
+
+// An extended elevation in the same coordinates as the sideview drawing: in
+// mm, x along the unrolled passage and y increasing downwards, with the first
+// station at the origin.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ExtendedElevation {
+	pub stations: BTreeMap<StationId, Point>,
+	pub legs: Vec<ExtendedLeg>,
+	pub splays: Vec<ExtendedSplay>,
+}
+
+// The direction PocketTopo draws a leg in its sideview: to the right, unless
+// the leg's first shot, which it takes its `from` station from, is flipped.
+// The sideview drawing itself isn't consulted: it only stores polygons and
+// cross-section markers, not where PocketTopo placed the stations, so the
+// flags are all there is to go on.
+pub fn direction(document: &Document, leg: &Leg) -> Direction {
+	let shot = &document.shots[leg.shots[0]];
+
+	match shot.flags.contains(ShotFlags::FLIPPED) {
+		true => Direction::Left,
+		false => Direction::Right,
+	}
+}
+
+// Unrolls the centerline, outwards from the first station. Each leg's
+// direction is taken from `directions`, by its stations in either order, or
+// else from the document as by `direction`. A leg which closes a loop is drawn
+// between stations already placed, so it may not have its measured length.
+// Splays are projected onto the direction of the leg which reached their
+// station.
+pub fn extended_elevation(
+	document: &Document,
+	directions: &BTreeMap<(StationId, StationId), Direction>,
+) -> ExtendedElevation {
+	let centerline = reduction::reduce(document);
+
+	let legs: Vec<ExtendedLeg> = centerline
+		.legs
+		.iter()
+		.map(|leg| {
+			let forwards = directions.get(&(leg.from, leg.to)).copied();
+			let backwards = directions.get(&(leg.to, leg.from)).copied();
+
+			let direction = forwards
+				.or(backwards.map(Direction::reversed))
+				.unwrap_or_else(|| direction(document, leg));
+
+			ExtendedLeg {
+				from: leg.from,
+				to: leg.to,
+				direction,
+			}
+		})
+		.collect();
+
+	// positions along the profile in metres, and the leg which reached each station
+	let mut stations: BTreeMap<StationId, (f64, f64)> = BTreeMap::new();
+	let mut reached_by: BTreeMap<StationId, usize> = BTreeMap::new();
+
+	if let Some(origin) = centerline.origin {
+		stations.insert(origin, (0.0, 0.0));
+	}
+
+	let mut progress = true;
+	while progress {
+		progress = false;
+
+		for (index, (leg, extended)) in centerline.legs.iter().zip(legs.iter()).enumerate() {
+			let along = leg.vector.horizontal_length() * extended.direction.sign();
+
+			let (station, (x, up)) = match (stations.get(&leg.from), stations.get(&leg.to)) {
+				(Some((x, up)), None) => (leg.to, (x + along, up + leg.vector.up)),
+				(None, Some((x, up))) => (leg.from, (x - along, up - leg.vector.up)),
+				_ => continue,
+			};
+
+			stations.insert(station, (x, up));
+			reached_by.insert(station, index);
+			progress = true;
+		}
+	}
+
+	// the origin's splays follow its first leg
+	if let Some(origin) = centerline.origin {
+		let first = centerline
+			.legs
+			.iter()
+			.position(|leg| leg.from == origin || leg.to == origin);
+
+		if let Some(first) = first {
+			reached_by.insert(origin, first);
+		}
+	}
+
+	let splays = centerline
+		.splays
+		.iter()
+		.filter_map(|splay| {
+			let (x, up) = *stations.get(&splay.from)?;
+
+			let along = match reached_by.get(&splay.from) {
+				Some(leg) => {
+					let direction = horizontal_direction(centerline.legs[*leg].vector);
+					splay.vector.dot(direction) * legs[*leg].direction.sign()
+				}
+				None => 0.0,
+			};
+
+			Some(ExtendedSplay {
+				shot: splay.shot,
+				from: point(x, up),
+				to: point(x + along, up + splay.vector.up),
+			})
+		})
+		.collect();
+
+	ExtendedElevation {
+		stations: stations
+			.into_iter()
+			.map(|(station, (x, up))| (station, point(x, up)))
+			.collect(),
+		legs,
+		splays,
+	}
+}
+
+// the horizontal unit vector along a leg, or zero for a vertical leg
+fn horizontal_direction(vector: Position) -> Position {
+	let length = vector.horizontal_length();
+
+	match length > 0.0 {
+		true => Position {
+			east: vector.east / length,
+			north: vector.north / length,
+			up: 0.0,
+		},
+		false => Position::default(),
+	}
+}
+
+fn point(x: f64, up: f64) -> Point {
+	Point {
+		x: (x * 1000.0).round() as i32,
+		y: (-up * 1000.0).round() as i32,
+	}
+}
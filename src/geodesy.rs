@@ -0,0 +1,374 @@
+use std::{fmt, str::FromStr};
+
+use thiserror::Error;
+
+use crate::{units, Reference};
+
+// WGS84
+const A: f64 = 6_378_137.0;
+const F: f64 = 1.0 / 298.257_223_563;
+
+// UTM
+const K0: f64 = 0.9996;
+const FALSE_EASTING: f64 = 500_000.0;
+const FALSE_NORTHING: f64 = 10_000_000.0;
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum Hemisphere {
+	North,
+	South,
+}
+
+// A UTM zone on WGS84, e.g. 33N.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Utm {
+	pub zone: u8,
+	pub hemisphere: Hemisphere,
+}
+
+// The coordinate reference system of a document's references, which .top
+// files don't record.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum Crs {
+	Utm(Utm),
+}
+
+// In degrees, positive to the north and east.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct LatLon {
+	pub latitude: f64,
+	pub longitude: f64,
+}
+
+// A position on the grid, in metres, with the grid's distortion there.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct GridPoint {
+	pub east: f64,
+	pub north: f64,
+	// the angle from true north to grid north, in degrees clockwise, so a
+	// true azimuth is the grid azimuth plus the convergence
+	pub convergence: f64,
+	// grid distance over ellipsoid distance
+	pub scale_factor: f64,
+}
+
+#[derive(Debug, Error, Eq, PartialEq)]
+#[error("invalid UTM zone: {0:?}")]
+pub struct InvalidCrs(pub String);
+
+impl Utm {
+	pub fn new(zone: u8, hemisphere: Hemisphere) -> Option<Self> {
+		(1..=60)
+			.contains(&zone)
+			.then_some(Self { zone, hemisphere })
+	}
+
+	// The zone containing a position, including the exceptions for Norway and
+	// Svalbard.
+	pub fn zone_of(position: LatLon) -> Self {
+		let LatLon {
+			latitude,
+			longitude,
+		} = position;
+
+		let longitude = (longitude + 180.0).rem_euclid(360.0) - 180.0;
+		let mut zone = (((longitude + 180.0) / 6.0).floor() as u8 + 1).min(60);
+
+		if (56.0..64.0).contains(&latitude) && (3.0..12.0).contains(&longitude) {
+			zone = 32;
+		}
+
+		if (72.0..=84.0).contains(&latitude) && (0.0..42.0).contains(&longitude) {
+			zone = match longitude {
+				longitude if longitude < 9.0 => 31,
+				longitude if longitude < 21.0 => 33,
+				longitude if longitude < 33.0 => 35,
+				_ => 37,
+			};
+		}
+
+		let hemisphere = match latitude < 0.0 {
+			true => Hemisphere::South,
+			false => Hemisphere::North,
+		};
+
+		Self { zone, hemisphere }
+	}
+
+	pub fn epsg(self) -> u16 {
+		match self.hemisphere {
+			Hemisphere::North => 32600 + u16::from(self.zone),
+			Hemisphere::South => 32700 + u16::from(self.zone),
+		}
+	}
+
+	// in degrees
+	pub fn central_meridian(self) -> f64 {
+		f64::from(self.zone) * 6.0 - 183.0
+	}
+
+	// Projects a position onto the zone's grid, with Krüger's series to the
+	// sixth order, which is accurate to well under a millimetre within the
+	// zone and usable some way beyond it.
+	pub fn from_wgs84(self, position: LatLon) -> GridPoint {
+		let (n, a) = series();
+		let alpha = alpha(n);
+		let e = (F * (2.0 - F)).sqrt();
+
+		let phi = position.latitude.to_radians();
+		let lambda = (position.longitude - self.central_meridian()).to_radians();
+		let lambda = (lambda + std::f64::consts::PI).rem_euclid(std::f64::consts::TAU)
+			- std::f64::consts::PI;
+
+		let tau = phi.tan();
+		let sigma = (e * (e * tau / (1.0 + tau * tau).sqrt()).atanh()).sinh();
+		let tau_prime = tau * (1.0 + sigma * sigma).sqrt() - sigma * (1.0 + tau * tau).sqrt();
+
+		let xi_prime = tau_prime.atan2(lambda.cos());
+		let eta_prime =
+			(lambda.sin() / (tau_prime * tau_prime + lambda.cos().powi(2)).sqrt()).asinh();
+
+		let mut xi = xi_prime;
+		let mut eta = eta_prime;
+		let mut p = 1.0;
+		let mut q = 0.0;
+
+		for (j, alpha) in (1..).zip(alpha) {
+			let j = f64::from(j) * 2.0;
+			xi += alpha * (j * xi_prime).sin() * (j * eta_prime).cosh();
+			eta += alpha * (j * xi_prime).cos() * (j * eta_prime).sinh();
+			p += j * alpha * (j * xi_prime).cos() * (j * eta_prime).cosh();
+			q += j * alpha * (j * xi_prime).sin() * (j * eta_prime).sinh();
+		}
+
+		let convergence =
+			(tau_prime / (1.0 + tau_prime * tau_prime).sqrt() * lambda.tan()).atan() + q.atan2(p);
+
+		let scale_factor = K0
+			* ((1.0 - e * e * phi.sin().powi(2)).sqrt() * (1.0 + tau * tau).sqrt()
+				/ (tau_prime * tau_prime + lambda.cos().powi(2)).sqrt())
+			* (a / A * (p * p + q * q).sqrt());
+
+		let north = match self.hemisphere {
+			Hemisphere::North => K0 * a * xi,
+			Hemisphere::South => K0 * a * xi + FALSE_NORTHING,
+		};
+
+		GridPoint {
+			east: K0 * a * eta + FALSE_EASTING,
+			north,
+			convergence: convergence.to_degrees(),
+			scale_factor,
+		}
+	}
+
+	// The inverse of `from_wgs84`, with coordinates in metres.
+	pub fn to_wgs84(self, east: f64, north: f64) -> LatLon {
+		let (n, a) = series();
+		let beta = beta(n);
+		let e = (F * (2.0 - F)).sqrt();
+
+		let north = match self.hemisphere {
+			Hemisphere::North => north,
+			Hemisphere::South => north - FALSE_NORTHING,
+		};
+
+		let eta = (east - FALSE_EASTING) / (K0 * a);
+		let xi = north / (K0 * a);
+
+		let mut xi_prime = xi;
+		let mut eta_prime = eta;
+		for (j, beta) in (1..).zip(beta) {
+			let j = f64::from(j) * 2.0;
+			xi_prime -= beta * (j * xi).sin() * (j * eta).cosh();
+			eta_prime -= beta * (j * xi).cos() * (j * eta).sinh();
+		}
+
+		let tau_prime = xi_prime.sin() / (eta_prime.sinh().powi(2) + xi_prime.cos().powi(2)).sqrt();
+
+		// Newton-Raphson for the conformal latitude's inverse
+		let mut tau = tau_prime;
+		for _ in 0..16 {
+			let sigma = (e * (e * tau / (1.0 + tau * tau).sqrt()).atanh()).sinh();
+			let tau_i = tau * (1.0 + sigma * sigma).sqrt() - sigma * (1.0 + tau * tau).sqrt();
+			let delta = (tau_prime - tau_i) / (1.0 + tau_i * tau_i).sqrt()
+				* (1.0 + (1.0 - e * e) * tau * tau)
+				/ ((1.0 - e * e) * (1.0 + tau * tau).sqrt());
+			tau += delta;
+
+			if delta.abs() < 1e-12 {
+				break;
+			}
+		}
+
+		let longitude =
+			eta_prime.sinh().atan2(xi_prime.cos()).to_degrees() + self.central_meridian();
+
+		LatLon {
+			latitude: tau.atan().to_degrees(),
+			longitude: (longitude + 180.0).rem_euclid(360.0) - 180.0,
+		}
+	}
+}
+
+impl Crs {
+	pub fn epsg(self) -> u16 {
+		match self {
+			Crs::Utm(utm) => utm.epsg(),
+		}
+	}
+
+	// coordinates in metres
+	pub fn to_wgs84(self, east: f64, north: f64) -> LatLon {
+		match self {
+			Crs::Utm(utm) => utm.to_wgs84(east, north),
+		}
+	}
+
+	pub fn from_wgs84(self, position: LatLon) -> GridPoint {
+		match self {
+			Crs::Utm(utm) => utm.from_wgs84(position),
+		}
+	}
+
+	// OGC WKT, as used by LAS
+	pub fn wkt(self) -> String {
+		match self {
+			Crs::Utm(utm) => {
+				let false_northing = match utm.hemisphere {
+					Hemisphere::North => 0.0,
+					Hemisphere::South => FALSE_NORTHING,
+				};
+
+				format!(
+					concat!(
+						r#"PROJCS["WGS 84 / UTM zone {utm}","#,
+						r#"GEOGCS["WGS 84",DATUM["WGS_1984",SPHEROID["WGS 84",6378137,298.257223563,AUTHORITY["EPSG","7030"]],AUTHORITY["EPSG","6326"]],"#,
+						r#"PRIMEM["Greenwich",0,AUTHORITY["EPSG","8901"]],UNIT["degree",0.0174532925199433,AUTHORITY["EPSG","9122"]],AUTHORITY["EPSG","4326"]],"#,
+						r#"PROJECTION["Transverse_Mercator"],PARAMETER["latitude_of_origin",0],PARAMETER["central_meridian",{meridian}],"#,
+						r#"PARAMETER["scale_factor",0.9996],PARAMETER["false_easting",500000],PARAMETER["false_northing",{false_northing}],"#,
+						r#"UNIT["metre",1,AUTHORITY["EPSG","9001"]],AXIS["Easting",EAST],AXIS["Northing",NORTH],AUTHORITY["EPSG","{epsg}"]]"#,
+					),
+					utm = utm,
+					meridian = utm.central_meridian(),
+					false_northing = false_northing,
+					epsg = utm.epsg(),
+				)
+			}
+		}
+	}
+
+	// The reference's position, and the grid's convergence and scale factor
+	// there.
+	pub fn reference(self, reference: &Reference) -> (LatLon, GridPoint) {
+		let position = self.to_wgs84(
+			units::metres(reference.east as f64),
+			units::metres(reference.north as f64),
+		);
+		(position, self.from_wgs84(position))
+	}
+}
+
+// the third flattening and the rectifying radius
+fn series() -> (f64, f64) {
+	let n = F / (2.0 - F);
+	let n2 = n * n;
+	let a = A / (1.0 + n) * (1.0 + n2 / 4.0 + n2 * n2 / 64.0 + n2 * n2 * n2 / 256.0);
+
+	(n, a)
+}
+
+fn alpha(n: f64) -> [f64; 6] {
+	let [n2, n3, n4, n5, n6] = [n.powi(2), n.powi(3), n.powi(4), n.powi(5), n.powi(6)];
+
+	[
+		n / 2.0 - 2.0 / 3.0 * n2 + 5.0 / 16.0 * n3 + 41.0 / 180.0 * n4 - 127.0 / 288.0 * n5
+			+ 7891.0 / 37800.0 * n6,
+		13.0 / 48.0 * n2 - 3.0 / 5.0 * n3 + 557.0 / 1440.0 * n4 + 281.0 / 630.0 * n5
+			- 1983433.0 / 1935360.0 * n6,
+		61.0 / 240.0 * n3 - 103.0 / 140.0 * n4 + 15061.0 / 26880.0 * n5 + 167603.0 / 181440.0 * n6,
+		49561.0 / 161280.0 * n4 - 179.0 / 168.0 * n5 + 6601661.0 / 7257600.0 * n6,
+		34729.0 / 80640.0 * n5 - 3418889.0 / 1995840.0 * n6,
+		212378941.0 / 319334400.0 * n6,
+	]
+}
+
+fn beta(n: f64) -> [f64; 6] {
+	let [n2, n3, n4, n5, n6] = [n.powi(2), n.powi(3), n.powi(4), n.powi(5), n.powi(6)];
+
+	[
+		n / 2.0 - 2.0 / 3.0 * n2 + 37.0 / 96.0 * n3 - 1.0 / 360.0 * n4 - 81.0 / 512.0 * n5
+			+ 96199.0 / 604800.0 * n6,
+		1.0 / 48.0 * n2 + 1.0 / 15.0 * n3 - 437.0 / 1440.0 * n4 + 46.0 / 105.0 * n5
+			- 1118711.0 / 3870720.0 * n6,
+		17.0 / 480.0 * n3 - 37.0 / 840.0 * n4 - 209.0 / 4480.0 * n5 + 5569.0 / 90720.0 * n6,
+		4397.0 / 161280.0 * n4 - 11.0 / 504.0 * n5 - 830251.0 / 7257600.0 * n6,
+		4583.0 / 161280.0 * n5 - 108847.0 / 3991680.0 * n6,
+		20648693.0 / 638668800.0 * n6,
+	]
+}
+
+// `33N`, `33S` or `EPSG:32633`, case-insensitively
+impl FromStr for Utm {
+	type Err = InvalidCrs;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let invalid = || InvalidCrs(s.to_owned());
+		let upper = s.trim().to_ascii_uppercase();
+
+		if let Some(code) = upper.strip_prefix("EPSG:") {
+			let code: u16 = code.parse().map_err(|_| invalid())?;
+			let hemisphere = match code / 100 {
+				326 => Hemisphere::North,
+				327 => Hemisphere::South,
+				_ => return Err(invalid()),
+			};
+
+			return Utm::new((code % 100) as u8, hemisphere).ok_or_else(invalid);
+		}
+
+		let (zone, hemisphere) = match upper.strip_suffix('N') {
+			Some(zone) => (zone, Hemisphere::North),
+			None => match upper.strip_suffix('S') {
+				Some(zone) => (zone, Hemisphere::South),
+				None => return Err(invalid()),
+			},
+		};
+
+		let zone = zone.parse().map_err(|_| invalid())?;
+		Utm::new(zone, hemisphere).ok_or_else(invalid)
+	}
+}
+
+impl FromStr for Crs {
+	type Err = InvalidCrs;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		s.parse().map(Crs::Utm)
+	}
+}
+
+impl fmt::Display for Utm {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let hemisphere = match self.hemisphere {
+			Hemisphere::North => 'N',
+			Hemisphere::South => 'S',
+		};
+
+		write!(f, "{}{hemisphere}", self.zone)
+	}
+}
+
+impl fmt::Display for Crs {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Crs::Utm(utm) => write!(f, "UTM {utm}"),
+		}
+	}
+}
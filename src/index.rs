@@ -0,0 +1,212 @@
+use std::ops::Range;
+
+use nom::{
+	bytes::complete::{tag, take},
+	multi::{length_count, many_till},
+	number::complete::{le_u32, le_u8},
+	Finish, IResult,
+};
+
+use crate::{
+	parser::{
+		is_outline_trailer, is_sideview_trailer, parse_drawing, parse_element, parse_header,
+		parse_mapping, parse_references, parse_shots, parse_trips,
+		parse_variable_length_little_endian_int, parse_version, ParseError, ParseOptions,
+	},
+	Drawing, Mapping, Reference, Shot, Trip,
+};
+
+// Byte ranges of each section of a file, including any leading count.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Sections {
+	pub trips: Range<usize>,
+	pub shots: Range<usize>,
+	pub references: Range<usize>,
+	pub mapping: Range<usize>,
+	pub outline: Range<usize>,
+	pub sideview: Range<usize>,
+}
+
+// A file whose sections have been located, but not decoded. Each section is
+// decoded on demand, so reading e.g. only the trips doesn't pay for the shots
+// and drawings.
+#[derive(Debug)]
+pub struct Index<'a> {
+	input: &'a [u8],
+	options: ParseOptions,
+	sections: Sections,
+}
+
+impl<'a> Index<'a> {
+	pub fn sections(&self) -> &Sections {
+		&self.sections
+	}
+
+	pub fn trips(&self) -> Result<Box<[Trip<'a>]>, ParseError<'a>> {
+		let input = &self.input[self.sections.trips.clone()];
+		parse_trips(input, &self.options)
+			.finish()
+			.map(|(_, trips)| trips)
+	}
+
+	pub fn shots(&self) -> Result<Box<[Shot<'a>]>, ParseError<'a>> {
+		let input = &self.input[self.sections.shots.clone()];
+		parse_shots(input, &self.options)
+			.finish()
+			.map(|(_, shots)| shots)
+	}
+
+	pub fn references(&self) -> Result<Box<[Reference<'a>]>, ParseError<'a>> {
+		let input = &self.input[self.sections.references.clone()];
+		parse_references(input, &self.options)
+			.finish()
+			.map(|(_, references)| references)
+	}
+
+	pub fn mapping(&self) -> Result<Mapping, ParseError<'a>> {
+		let input = &self.input[self.sections.mapping.clone()];
+		parse_mapping(input).finish().map(|(_, mapping)| mapping)
+	}
+
+	// the trailer is checked against the rest of the file, as in a full parse
+	pub fn outline(&self) -> Result<Drawing, ParseError<'a>> {
+		let input = &self.input[self.sections.outline.start..];
+		parse_drawing(input, is_outline_trailer)
+			.finish()
+			.map(|(_, drawing)| drawing)
+	}
+
+	pub fn sideview(&self) -> Result<Drawing, ParseError<'a>> {
+		let input = &self.input[self.sections.sideview.start..];
+		parse_drawing(input, is_sideview_trailer)
+			.finish()
+			.map(|(_, drawing)| drawing)
+	}
+}
+
+pub fn index(input: &[u8]) -> Result<Index<'_>, ParseError<'_>> {
+	index_with_options(input, &ParseOptions::default())
+}
+
+// The options are used when sections are decoded, as comments aren't decoded
+// while indexing.
+pub fn index_with_options<'a>(
+	input: &'a [u8],
+	options: &ParseOptions,
+) -> Result<Index<'a>, ParseError<'a>> {
+	let (_, sections) = index_internal(input).finish()?;
+
+	Ok(Index {
+		input,
+		options: *options,
+		sections,
+	})
+}
+
+fn index_internal(input: &[u8]) -> IResult<&[u8], Sections, ParseError<'_>> {
+	let offset = |rest: &[u8]| input.len() - rest.len();
+
+	let (rest, _) = parse_header(input)?;
+	let (rest, _) = parse_version(rest)?;
+
+	let start = offset(rest);
+	let (rest, _) = length_count(le_u32, skip_trip)(rest)?;
+	let trips = start..offset(rest);
+
+	let start = offset(rest);
+	let (rest, _) = length_count(le_u32, skip_shot)(rest)?;
+	let shots = start..offset(rest);
+
+	let start = offset(rest);
+	let (rest, _) = length_count(le_u32, skip_reference)(rest)?;
+	let references = start..offset(rest);
+
+	let start = offset(rest);
+	let (rest, _) = parse_mapping(rest)?;
+	let mapping = start..offset(rest);
+
+	let start = offset(rest);
+	let (rest, _) = skip_drawing(rest, is_outline_trailer)?;
+	let outline = start..offset(rest);
+
+	let start = offset(rest);
+	let (rest, _) = skip_drawing(rest, is_sideview_trailer)?;
+	let sideview = start..offset(rest);
+
+	let sections = Sections {
+		trips,
+		shots,
+		references,
+		mapping,
+		outline,
+		sideview,
+	};
+
+	Ok((rest, sections))
+}
+
+fn skip_drawing(input: &[u8], trailer: fn(&[u8]) -> bool) -> IResult<&[u8], (), ParseError<'_>> {
+	let (input, _) = parse_mapping(input)?;
+	let (input, _) = many_till(|input| skip_element(input, trailer), tag([0x0_u8]))(input)?;
+
+	Ok((input, ()))
+}
+
+// unknown elements need to be resynchronised, so they're parsed in full
+fn skip_element(input: &[u8], trailer: fn(&[u8]) -> bool) -> IResult<&[u8], (), ParseError<'_>> {
+	match input.first() {
+		Some(0x1_u8) => skip_polygon(input),
+		Some(0x3_u8) => skip(input, 17),
+		_ => parse_element(input, trailer).map(|(input, _)| (input, ())),
+	}
+}
+
+// id, point count, points and color
+fn skip_polygon(input: &[u8]) -> IResult<&[u8], (), ParseError<'_>> {
+	let (input, _) = le_u8(input)?;
+	let (input, count) = le_u32(input)?;
+	let (input, _) = take(count as usize * 8)(input)?;
+
+	skip(input, 1)
+}
+
+// stations, distance, azimuth and inclination, followed by the flags, roll,
+// trip index and an optional comment
+fn skip_shot(input: &[u8]) -> IResult<&[u8], (), ParseError<'_>> {
+	const HAS_COMMENT: u8 = 0x2;
+
+	let (input, _) = take(16_usize)(input)?;
+	let (input, flags) = le_u8(input)?;
+	let (input, _) = take(3_usize)(input)?;
+
+	if flags & HAS_COMMENT == HAS_COMMENT {
+		skip_string(input)
+	} else {
+		Ok((input, ()))
+	}
+}
+
+// station, east, north and altitude, followed by a comment
+fn skip_reference(input: &[u8]) -> IResult<&[u8], (), ParseError<'_>> {
+	let (input, _) = take(24_usize)(input)?;
+
+	skip_string(input)
+}
+
+fn skip_string(input: &[u8]) -> IResult<&[u8], (), ParseError<'_>> {
+	let (input, length) = parse_variable_length_little_endian_int(input)?;
+
+	skip(input, length)
+}
+
+// time, comment and declination
+fn skip_trip(input: &[u8]) -> IResult<&[u8], (), ParseError<'_>> {
+	let (input, _) = take(8_usize)(input)?;
+	let (input, _) = skip_string(input)?;
+
+	skip(input, 2)
+}
+
+fn skip(input: &[u8], length: usize) -> IResult<&[u8], (), ParseError<'_>> {
+	take(length)(input).map(|(input, _)| (input, ()))
+}
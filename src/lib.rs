@@ -1,9 +1,42 @@
+pub mod blunders;
+pub mod browser;
+pub mod builder;
+pub mod cloud;
+pub mod diff;
+pub mod extended;
+pub mod geodesy;
+pub mod index;
+pub mod lrud;
+pub mod magnetic;
+pub mod map;
+pub mod merge;
+pub mod mesh;
 pub mod parser;
+#[cfg(feature = "pdf")]
+pub mod pdf;
+pub mod preview;
+pub mod projection;
+pub mod reduction;
+#[cfg(feature = "render")]
+pub mod render;
+pub mod renumber;
+pub mod scene;
+pub mod stats;
+pub mod surface;
+pub mod units;
+pub mod validate;
+pub mod writer;
+
+pub use encoding_rs;
+
+use std::{borrow::Cow, fmt, str::FromStr};
 
 use bitflags::bitflags;
 use chrono::NaiveDateTime;
+use thiserror::Error;
 
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum Color {
 	Black,
 	Blue,
@@ -14,53 +47,62 @@ pub enum Color {
 	Red,
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct CrossSection {
 	pub position: Point,
 	pub station: StationId,
 	pub direction: i32,
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Drawing {
 	pub mapping: Mapping,
 	pub elements: Box<[Element]>,
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum Element {
 	Polygon(Polygon),
 	CrossSection(CrossSection),
+	Unknown { id: u8, bytes: Box<[u8]> },
 }
 
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Mapping {
 	pub origin: Point,
 	pub scale: i32,
 }
 
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Point {
 	pub x: i32,
 	pub y: i32,
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Polygon {
 	pub points: Box<[Point]>,
 	pub color: Color,
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Reference<'a> {
 	pub station: Option<StationId>,
 	pub east: i64,     // mm
 	pub north: i64,    // mm
 	pub altitude: i32, // mm above sea level
-	pub comment: &'a str,
+	pub comment: Cow<'a, str>,
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Shot<'a> {
 	pub from: Option<StationId>,
 	pub to: Option<StationId>,
@@ -70,25 +112,114 @@ pub struct Shot<'a> {
 	pub flags: ShotFlags,
 	pub roll: u8,
 	pub trip_index: i16,
-	pub comment: Option<&'a str>,
+	pub comment: Option<Cow<'a, str>>,
 }
 
 bitflags! {
+	#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 	pub struct ShotFlags: u8 {
 		const FLIPPED = (1 << 0);
 		const HAS_COMMENT = (1 << 1);
 	}
 }
 
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum StationId {
 	MajorMinor(u16, u16),
 	Plain(u32),
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Trip<'a> {
 	pub time: NaiveDateTime,
-	pub comment: &'a str,
+	pub comment: Cow<'a, str>,
 	pub declination: i16,
 }
+
+#[derive(Debug, Error, Eq, PartialEq)]
+#[error("invalid color: {0:?}")]
+pub struct InvalidColor(pub String);
+
+impl FromStr for Color {
+	type Err = InvalidColor;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s {
+			"black" => Ok(Color::Black),
+			"blue" => Ok(Color::Blue),
+			"brown" => Ok(Color::Brown),
+			"gray" => Ok(Color::Gray),
+			"green" => Ok(Color::Green),
+			"orange" => Ok(Color::Orange),
+			"red" => Ok(Color::Red),
+			invalid => Err(InvalidColor(invalid.to_owned())),
+		}
+	}
+}
+
+impl Color {
+	// red, green and blue, as PocketTopo draws them
+	pub fn rgb(self) -> [u8; 3] {
+		match self {
+			Color::Black => [0, 0, 0],
+			Color::Blue => [0, 0, 255],
+			Color::Brown => [153, 102, 51],
+			Color::Gray => [128, 128, 128],
+			Color::Green => [0, 160, 0],
+			Color::Orange => [255, 128, 0],
+			Color::Red => [255, 0, 0],
+		}
+	}
+}
+
+impl StationId {
+	// the largest ids which fit in the file's 31 bits
+	pub const MAX_MAJOR: u16 = 0x7FFF;
+	pub const MAX_PLAIN: u32 = 0x7FFFFFFE;
+}
+
+#[derive(Debug, Error, Eq, PartialEq)]
+#[error("invalid station id: {0:?}")]
+pub struct InvalidStationId(pub String);
+
+// PocketTopo's notation: `major.minor` or a plain number, limited to the values
+// which fit in the file's 31 bits
+impl FromStr for StationId {
+	type Err = InvalidStationId;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let invalid = || InvalidStationId(s.to_owned());
+
+		let is_number = |s: &str| !s.is_empty() && s.bytes().all(|byte| byte.is_ascii_digit());
+
+		match s.split_once('.') {
+			Some((major, minor)) if is_number(major) && is_number(minor) => {
+				let major = major.parse().map_err(|_| invalid())?;
+				let minor = minor.parse().map_err(|_| invalid())?;
+
+				match major {
+					major if major <= StationId::MAX_MAJOR => {
+						Ok(StationId::MajorMinor(major, minor))
+					}
+					_ => Err(invalid()),
+				}
+			}
+			None if is_number(s) => match s.parse().map_err(|_| invalid())? {
+				x if x <= StationId::MAX_PLAIN => Ok(StationId::Plain(x)),
+				_ => Err(invalid()),
+			},
+			_ => Err(invalid()),
+		}
+	}
+}
+
+impl fmt::Display for StationId {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			StationId::MajorMinor(major, minor) => write!(f, "{major}.{minor}"),
+			StationId::Plain(x) => write!(f, "{x}"),
+		}
+	}
+}
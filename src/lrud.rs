@@ -0,0 +1,127 @@
+use std::collections::BTreeMap;
+
+use crate::{
+	parser::Document,
+	reduction::{self, Position},
+	StationId,
+};
+
+// Distances in metres from a station to the passage walls, with left and right
+// perpendicular to `direction`, the azimuth in degrees the passage is facing.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Lrud {
+	pub left: f64,
+	pub right: f64,
+	pub up: f64,
+	pub down: f64,
+	pub direction: f64,
+}
+
+// Which of the splays towards a wall gives its distance.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Selection {
+	// the furthest, projected onto the direction of the wall
+	#[default]
+	Max,
+	// the closest in angle to the direction of the wall, projected onto it
+	Perpendicular,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Options {
+	pub selection: Selection,
+	// how far in degrees a splay may be from the direction of a wall
+	pub tolerance: f64,
+}
+
+impl Default for Options {
+	fn default() -> Self {
+		Self {
+			selection: Selection::default(),
+			tolerance: 45.0,
+		}
+	}
+}
+
+// Derives passage dimensions for each station with splays. The passage faces
+// along the bisector of the station's first incoming and first outgoing legs,
+// in the direction they were surveyed, or along its only leg. Each splay
+// counts towards the wall it's closest to in angle, if it's within the
+// tolerance; walls without splays are 0.
+pub fn lruds(document: &Document, options: &Options) -> BTreeMap<StationId, Lrud> {
+	let centerline = reduction::reduce(document);
+
+	let mut splays: BTreeMap<StationId, Vec<Position>> = BTreeMap::new();
+	for splay in centerline.splays.iter() {
+		splays.entry(splay.from).or_default().push(splay.vector);
+	}
+
+	splays
+		.into_iter()
+		.map(|(station, splays)| {
+			let direction = centerline.bisector(station, true);
+			let direction = match direction.horizontal_length() > 0.0 {
+				true => direction.azimuth(),
+				false => 0.0,
+			};
+
+			(station, lrud(direction, &splays, options))
+		})
+		.collect()
+}
+
+fn lrud(direction: f64, splays: &[Position], options: &Options) -> Lrud {
+	let (sin, cos) = direction.to_radians().sin_cos();
+	let left = Position {
+		east: -cos,
+		north: sin,
+		up: 0.0,
+	};
+	let up = Position {
+		east: 0.0,
+		north: 0.0,
+		up: 1.0,
+	};
+	let axes = [left, -left, up, -up];
+
+	// the (distance, angle) of the chosen splay towards each wall
+	let mut walls: [Option<(f64, f64)>; 4] = [None; 4];
+
+	for splay in splays {
+		let closest = axes
+			.iter()
+			.enumerate()
+			.map(|(index, axis)| (index, splay.angle(*axis)))
+			.min_by(|a, b| a.1.total_cmp(&b.1));
+
+		let Some((index, angle)) = closest else {
+			continue;
+		};
+
+		if angle > options.tolerance || splay.length() == 0.0 {
+			continue;
+		}
+
+		let distance = splay.dot(axes[index]);
+		let better = match (walls[index], options.selection) {
+			(None, _) => true,
+			(Some((best, _)), Selection::Max) => distance > best,
+			(Some((_, best)), Selection::Perpendicular) => angle < best,
+		};
+
+		if better {
+			walls[index] = Some((distance, angle));
+		}
+	}
+
+	let [left, right, up, down] = walls.map(|wall| wall.map_or(0.0, |(distance, _)| distance));
+
+	Lrud {
+		left,
+		right,
+		up,
+		down,
+		direction,
+	}
+}
@@ -0,0 +1,355 @@
+use chrono::{Datelike, NaiveDate, NaiveDateTime, Timelike};
+use thiserror::Error;
+
+use crate::{geodesy::LatLon, parser::Document, reduction, units};
+
+// the model's reference radius, in km
+const RADIUS: f64 = 6371.2;
+
+// WGS84, in km
+const A: f64 = 6378.137;
+const F: f64 = 1.0 / 298.257_223_563;
+
+// NOAA's World Magnetic Models for 2020 to 2025 and 2025 to 2030, from their
+// WMM.COF
+const WMM2020: &str = include_str!("../data/WMM2020.COF");
+const WMM2025: &str = include_str!("../data/WMM2025.COF");
+
+// A spherical harmonic model of the main field, such as the WMM, with
+// coefficients in nT at `epoch` and their secular variation in nT a year.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Model {
+	pub name: String,
+	// decimal year
+	pub epoch: f64,
+	// years after `epoch` the model is valid for
+	pub lifetime: f64,
+	// by degree n and order m: `g[n][m]`
+	g: Vec<Vec<f64>>,
+	h: Vec<Vec<f64>>,
+	g_rate: Vec<Vec<f64>>,
+	h_rate: Vec<Vec<f64>>,
+}
+
+// The field in nT, in the geodetic frame, with angles in degrees.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Field {
+	pub north: f64,
+	pub east: f64,
+	pub down: f64,
+	// east of true north
+	pub declination: f64,
+	// below horizontal
+	pub inclination: f64,
+}
+
+#[derive(Debug, Error, PartialEq)]
+pub enum ModelError {
+	#[error("invalid coefficients header: {0:?}")]
+	InvalidHeader(String),
+
+	#[error("invalid coefficients on line {0}")]
+	InvalidLine(usize),
+
+	#[error("no coefficients")]
+	Empty,
+}
+
+impl Model {
+	// The embedded models, oldest first, so that declinations can be computed
+	// offline.
+	pub fn embedded() -> Vec<Self> {
+		vec![Model::wmm2020(), Model::wmm2025()]
+	}
+
+	pub fn wmm2020() -> Self {
+		Model::parse(WMM2020).expect("invalid WMM2020 coefficients")
+	}
+
+	pub fn wmm2025() -> Self {
+		Model::parse(WMM2025).expect("invalid WMM2025 coefficients")
+	}
+
+	// Parses coefficients in the format of NOAA's WMM.COF, which WMM and IGRF
+	// tables are also distributed in: a header with the epoch and name, then
+	// lines of n, m, g, h and their rates, ending at a line of 9s.
+	pub fn parse(coefficients: &str) -> Result<Self, ModelError> {
+		let mut lines = coefficients.lines().enumerate();
+
+		let (_, header) = lines.next().ok_or(ModelError::Empty)?;
+		let mut fields = header.split_whitespace();
+		let epoch = fields.next().and_then(|epoch| epoch.parse().ok());
+		let name = fields.next();
+		let (Some(epoch), Some(name)) = (epoch, name) else {
+			return Err(ModelError::InvalidHeader(header.to_owned()));
+		};
+
+		let mut rows = Vec::new();
+		for (index, line) in lines {
+			if line.trim().is_empty() {
+				continue;
+			}
+			if line.trim_start().starts_with("9999") {
+				break;
+			}
+
+			let invalid = || ModelError::InvalidLine(index + 1);
+			let fields: Vec<&str> = line.split_whitespace().collect();
+			let [n, m, g, h, g_rate, h_rate] = fields[..] else {
+				return Err(invalid());
+			};
+
+			let n: usize = n.parse().map_err(|_| invalid())?;
+			let m: usize = m.parse().map_err(|_| invalid())?;
+			if n == 0 || m > n {
+				return Err(invalid());
+			}
+
+			let values = [g, h, g_rate, h_rate].map(|value| value.parse::<f64>());
+			let [Ok(g), Ok(h), Ok(g_rate), Ok(h_rate)] = values else {
+				return Err(invalid());
+			};
+
+			rows.push((n, m, [g, h, g_rate, h_rate]));
+		}
+
+		let degree = rows
+			.iter()
+			.map(|(n, ..)| *n)
+			.max()
+			.ok_or(ModelError::Empty)?;
+		let table = || -> Vec<Vec<f64>> { (0..=degree).map(|n| vec![0.0; n + 1]).collect() };
+
+		let mut model = Model {
+			name: name.to_owned(),
+			epoch,
+			lifetime: 5.0,
+			g: table(),
+			h: table(),
+			g_rate: table(),
+			h_rate: table(),
+		};
+
+		for (n, m, [g, h, g_rate, h_rate]) in rows {
+			model.g[n][m] = g;
+			model.h[n][m] = h;
+			model.g_rate[n][m] = g_rate;
+			model.h_rate[n][m] = h_rate;
+		}
+
+		Ok(model)
+	}
+
+	pub fn is_valid(&self, year: f64) -> bool {
+		(self.epoch..self.epoch + self.lifetime).contains(&year)
+	}
+
+	// The field at a position and height above the ellipsoid in metres, at a
+	// decimal year. Outside the model's lifetime the secular variation is
+	// extrapolated, which gets less accurate with every year.
+	pub fn field(&self, position: LatLon, height: f64, year: f64) -> Field {
+		let degree = self.g.len() - 1;
+		let dt = year - self.epoch;
+
+		// geodetic to geocentric spherical
+		let latitude = position.latitude.to_radians();
+		let longitude = position.longitude.to_radians();
+		let height = height / 1000.0;
+
+		let e2 = F * (2.0 - F);
+		let radius = A / (1.0 - e2 * latitude.sin().powi(2)).sqrt();
+		let p = (radius + height) * latitude.cos();
+		let z = (radius * (1.0 - e2) + height) * latitude.sin();
+		let r = p.hypot(z);
+		let geocentric = (z / r).asin();
+
+		// Schmidt semi-normalised associated Legendre functions of the
+		// colatitude, and their derivatives by it
+		let x = geocentric.sin();
+		let s = geocentric.cos().max(1e-12);
+
+		let mut legendre = vec![vec![0.0; degree + 1]; degree + 1];
+		let mut derivative = vec![vec![0.0; degree + 1]; degree + 1];
+		legendre[0][0] = 1.0;
+
+		for n in 1..=degree {
+			for m in 0..=n {
+				let (value, slope) = if n == m {
+					let factor = match n {
+						1 => 1.0,
+						n => ((2 * n - 1) as f64 / (2 * n) as f64).sqrt(),
+					};
+
+					let previous = legendre[n - 1][n - 1];
+					let previous_slope = derivative[n - 1][n - 1];
+					(
+						factor * s * previous,
+						factor * (s * previous_slope + x * previous),
+					)
+				} else {
+					let (n_f, m_f) = (n as f64, m as f64);
+					let k = (2.0 * n_f - 1.0) / (n_f * n_f - m_f * m_f).sqrt();
+					let j = match n {
+						1 => 0.0,
+						_ => {
+							((n_f - 1.0).powi(2) - m_f * m_f).sqrt()
+								/ (n_f * n_f - m_f * m_f).sqrt()
+						}
+					};
+
+					let (before, before_slope) = match n {
+						1 => (0.0, 0.0),
+						_ => (legendre[n - 2][m], derivative[n - 2][m]),
+					};
+
+					(
+						k * x * legendre[n - 1][m] - j * before,
+						k * (x * derivative[n - 1][m] - s * legendre[n - 1][m]) - j * before_slope,
+					)
+				};
+
+				legendre[n][m] = value;
+				derivative[n][m] = slope;
+			}
+		}
+
+		let (mut north, mut east, mut down) = (0.0, 0.0, 0.0);
+
+		for n in 1..=degree {
+			let scale = (RADIUS / r).powi(n as i32 + 2);
+
+			for m in 0..=n {
+				let g = self.g[n][m] + dt * self.g_rate[n][m];
+				let h = self.h[n][m] + dt * self.h_rate[n][m];
+				let (sin, cos) = (m as f64 * longitude).sin_cos();
+
+				let term = g * cos + h * sin;
+				north += scale * term * derivative[n][m];
+				east += scale * m as f64 * (g * sin - h * cos) * legendre[n][m] / s;
+				down -= scale * (n as f64 + 1.0) * term * legendre[n][m];
+			}
+		}
+
+		// geocentric to geodetic
+		let (sin, cos) = (geocentric - latitude).sin_cos();
+		let (north, down) = (north * cos - down * sin, north * sin + down * cos);
+
+		Field {
+			north,
+			east,
+			down,
+			declination: east.atan2(north).to_degrees(),
+			inclination: down.atan2(north.hypot(east)).to_degrees(),
+		}
+	}
+}
+
+// The year as a decimal, e.g. 2024.5 in the middle of 2024.
+pub fn decimal_year(time: NaiveDateTime) -> f64 {
+	let year = time.year();
+	let start = NaiveDate::from_ymd_opt(year, 1, 1).map(|date| date.and_time(Default::default()));
+	let end = NaiveDate::from_ymd_opt(year + 1, 1, 1).map(|date| date.and_time(Default::default()));
+
+	match (start, end) {
+		(Some(start), Some(end)) => {
+			let elapsed = (time - start).num_seconds() as f64 + f64::from(time.nanosecond()) / 1e9;
+			f64::from(year) + elapsed / (end - start).num_seconds() as f64
+		}
+		_ => f64::from(year),
+	}
+}
+
+// A trip's declination, as recorded and from the model, in degrees.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct TripDeclination {
+	// index into `Document::trips`
+	pub trip: usize,
+	pub recorded: f64,
+	pub model: f64,
+	// index into the models, of the one the trip's declination is from
+	pub source: usize,
+	// whether the trip's date is within the model's lifetime
+	pub valid: bool,
+}
+
+impl TripDeclination {
+	// the smallest angle from the recorded declination to the model's
+	pub fn difference(&self) -> f64 {
+		(self.model - self.recorded + 180.0).rem_euclid(360.0) - 180.0
+	}
+}
+
+// The index of the newest model whose epoch is no later than `year`, or of
+// the oldest model for earlier years. `models` must be oldest first.
+pub fn select(models: &[Model], year: f64) -> Option<usize> {
+	match models.iter().rposition(|model| model.epoch <= year) {
+		Some(index) => Some(index),
+		None => (!models.is_empty()).then_some(0),
+	}
+}
+
+// Each trip's declination at a position and height in metres, such as the
+// centerline's anchor, from the model for the trip's date.
+pub fn declinations(
+	document: &Document,
+	models: &[Model],
+	position: LatLon,
+	height: f64,
+) -> Vec<TripDeclination> {
+	document
+		.trips
+		.iter()
+		.enumerate()
+		.filter_map(|(trip, recorded)| {
+			let year = decimal_year(recorded.time);
+			let source = select(models, year)?;
+			let model = &models[source];
+
+			Some(TripDeclination {
+				trip,
+				recorded: units::degrees(recorded.declination),
+				model: model.field(position, height, year).declination,
+				source,
+				valid: model.is_valid(year),
+			})
+		})
+		.collect()
+}
+
+// The position and altitude of the centerline's anchor, for a document with a
+// CRS.
+pub fn reference_location(document: &Document) -> Option<(LatLon, f64)> {
+	let crs = document.crs?;
+	let reference = reduction::reduce(document).anchor(document)?.reference;
+	let (position, _) = crs.reference(reference);
+
+	Some((position, units::metres(reference.altitude)))
+}
+
+// Sets each trip's declination to the model's, or only those which are zero
+// unless `all`, to the file's precision. Returns the indices of the trips which
+// were changed.
+pub fn fill(document: &mut Document, declinations: &[TripDeclination], all: bool) -> Vec<usize> {
+	let mut changed = Vec::new();
+
+	for declination in declinations {
+		let Some(trip) = document.trips.get_mut(declination.trip) else {
+			continue;
+		};
+
+		if trip.declination != 0 && !all {
+			continue;
+		}
+
+		let value = units::units(declination.model).round().rem_euclid(65536.0) as u16 as i16;
+
+		if trip.declination != value {
+			trip.declination = value;
+			changed.push(declination.trip);
+		}
+	}
+
+	changed
+}
@@ -0,0 +1,98 @@
+use std::collections::BTreeMap;
+
+use crate::{
+	extended,
+	parser::Document,
+	projection::{self, View},
+	Drawing, Element, Point, StationId,
+};
+
+// Which of the document's drawings to show.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Sheet {
+	// the plan, with north up
+	#[default]
+	Outline,
+	// the extended elevation
+	Sideview,
+}
+
+// A drawing with the centerline it's drawn over, in the drawing's coordinates.
+// The centerline is the plan for the outline and the extended elevation for
+// the sideview, so that it lines up with the drawing.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Map<'a> {
+	pub drawing: &'a Drawing,
+	pub stations: BTreeMap<StationId, Point>,
+	pub legs: Vec<(Point, Point)>,
+}
+
+pub fn map<'a>(document: &'a Document, sheet: Sheet) -> Map<'a> {
+	match sheet {
+		Sheet::Outline => {
+			let projection = projection::project(document, View::Plan, &BTreeMap::new());
+			let legs = projection
+				.centerline
+				.iter()
+				.map(|line| (line[0], line[1]))
+				.collect();
+
+			Map {
+				drawing: &document.outline,
+				stations: projection.stations,
+				legs,
+			}
+		}
+		Sheet::Sideview => {
+			let elevation = extended::extended_elevation(document, &BTreeMap::new());
+			let legs = elevation
+				.legs
+				.iter()
+				.filter_map(|leg| {
+					Some((
+						*elevation.stations.get(&leg.from)?,
+						*elevation.stations.get(&leg.to)?,
+					))
+				})
+				.collect();
+
+			Map {
+				drawing: &document.sideview,
+				stations: elevation.stations,
+				legs,
+			}
+		}
+	}
+}
+
+impl Map<'_> {
+	// the extent of the drawing and the stations, if there's anything to show
+	pub fn bounds(&self) -> Option<(Point, Point)> {
+		let points = self
+			.drawing
+			.elements
+			.iter()
+			.flat_map(|element| match element {
+				Element::Polygon(polygon) => polygon.points.to_vec(),
+				Element::CrossSection(section) => vec![section.position],
+				Element::Unknown { .. } => Vec::new(),
+			})
+			.chain(self.stations.values().copied());
+
+		let mut bounds: Option<(Point, Point)> = None;
+
+		for point in points {
+			let (min, max) = bounds.get_or_insert((point, point));
+			*min = Point {
+				x: min.x.min(point.x),
+				y: min.y.min(point.y),
+			};
+			*max = Point {
+				x: max.x.max(point.x),
+				y: max.y.max(point.y),
+			};
+		}
+
+		bounds
+	}
+}
@@ -0,0 +1,333 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use thiserror::Error;
+
+use crate::{
+	parser::Document,
+	reduction::{self, Position},
+	renumber::stations,
+	Drawing, Element, Point, StationId,
+};
+
+#[derive(Debug, Error, Eq, PartialEq)]
+pub enum MergeError {
+	#[error("no documents to merge")]
+	Empty,
+
+	#[error("equate refers to document {0}, which isn't being merged")]
+	InvalidDocument(usize),
+
+	#[error("document {0} isn't connected to the merged survey, so its drawings can't be placed")]
+	Disconnected(usize),
+
+	#[error("too many trips: {0}")]
+	TooManyTrips(usize),
+
+	#[error("no free station ids to renumber station {0}")]
+	NoFreeStationIds(StationId),
+}
+
+// How to treat a station which appears in a document and in an earlier one.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Collisions {
+	// The later document's station is given a new id. Series are renumbered
+	// as a whole, so 1.0, 1.1 and 1.2 become e.g. 4.0, 4.1 and 4.2, and plain
+	// numbers are all shifted past the highest number in use.
+	#[default]
+	Renumber,
+	// Stations with the same id are the same station.
+	Equate,
+}
+
+// `station` in the document with index `document` is the same station as `to`
+// in the merged survey, e.g. where a later survey ties into an earlier one.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Equate {
+	pub document: usize,
+	pub station: StationId,
+	pub to: StationId,
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct MergeOptions {
+	pub collisions: Collisions,
+	pub equates: Vec<Equate>,
+}
+
+// Merges documents into one, in order. Trips are concatenated, references are
+// unioned, and stations are renumbered or equated according to the options.
+//
+// Drawings are placed relative to the first document's first station: each
+// outline is translated by the position of its document's first station in the
+// merged survey, so documents with drawings must be connected to it. Sideviews
+// are aligned vertically the same way, and placed to the right of the earlier
+// sideviews, as the extended elevations can't otherwise be lined up. Unknown
+// elements can't be translated, so they're copied as they are. The mapping and
+// CRS are taken from the first document.
+pub fn merge<'a>(
+	documents: &[Document<'a>],
+	options: &MergeOptions,
+) -> Result<Document<'a>, MergeError> {
+	let first = documents.first().ok_or(MergeError::Empty)?;
+
+	if let Some(equate) = options
+		.equates
+		.iter()
+		.find(|equate| equate.document >= documents.len())
+	{
+		return Err(MergeError::InvalidDocument(equate.document));
+	}
+
+	let mut merged = Document {
+		references: Box::new([]),
+		shots: Box::new([]),
+		trips: Box::new([]),
+		mapping: first.mapping,
+		outline: Drawing {
+			mapping: first.outline.mapping,
+			elements: Box::new([]),
+		},
+		sideview: Drawing {
+			mapping: first.sideview.mapping,
+			elements: Box::new([]),
+		},
+		crs: first.crs,
+	};
+
+	let mut references = Vec::new();
+	let mut shots = Vec::new();
+	let mut trips = Vec::new();
+	let mut used = BTreeSet::new();
+	let mut renumberings = Vec::new();
+
+	for (index, document) in documents.iter().enumerate() {
+		let renumbering = renumber(index, document, &used, options)?;
+		let station = |station: StationId| renumbering.get(&station).copied().unwrap_or(station);
+
+		let trip_offset = trips.len();
+		trips.extend(document.trips.iter().cloned());
+		if trips.len() > i16::MAX as usize + 1 {
+			return Err(MergeError::TooManyTrips(trips.len()));
+		}
+
+		for shot in document.shots.iter() {
+			let mut shot = shot.clone();
+			shot.from = shot.from.map(station);
+			shot.to = shot.to.map(station);
+			if let Ok(index) = usize::try_from(shot.trip_index) {
+				shot.trip_index = i16::try_from(trip_offset + index)
+					.map_err(|_| MergeError::TooManyTrips(trip_offset + index + 1))?;
+			}
+			shots.push(shot);
+		}
+
+		for reference in document.references.iter() {
+			let mut reference = reference.clone();
+			reference.station = reference.station.map(station);
+			if !references.contains(&reference) {
+				references.push(reference);
+			}
+		}
+
+		used.extend(stations(document).into_iter().map(station));
+		renumberings.push(renumbering);
+	}
+
+	merged.references = references.into_boxed_slice();
+	merged.shots = shots.into_boxed_slice();
+	merged.trips = trips.into_boxed_slice();
+
+	// place the drawings using the merged centerline
+	let centerline = reduction::reduce(&merged);
+	let mut outline = Vec::new();
+	let mut sideview: Vec<Element> = Vec::new();
+
+	for (index, (document, renumbering)) in documents.iter().zip(renumberings).enumerate() {
+		if document.outline.elements.is_empty() && document.sideview.elements.is_empty() {
+			continue;
+		}
+
+		let origin = document
+			.shots
+			.iter()
+			.find_map(|shot| shot.from)
+			.map(|station| renumbering.get(&station).copied().unwrap_or(station))
+			.and_then(|station| centerline.position(&station))
+			.ok_or(MergeError::Disconnected(index))?;
+
+		let station = |station: StationId| renumbering.get(&station).copied().unwrap_or(station);
+
+		let offset = outline_offset(origin);
+		outline.extend(
+			document
+				.outline
+				.elements
+				.iter()
+				.map(|element| translate(element, offset, station)),
+		);
+
+		// right of the earlier sideviews, and level with the outline
+		let right = sideview.iter().filter_map(max_x).max();
+		let left = document.sideview.elements.iter().filter_map(min_x).min();
+		let x = match (right, left) {
+			(Some(right), Some(left)) => right - left,
+			_ => 0,
+		};
+		let offset = Point {
+			x,
+			y: millimetres(-origin.up),
+		};
+		sideview.extend(
+			document
+				.sideview
+				.elements
+				.iter()
+				.map(|element| translate(element, offset, station)),
+		);
+	}
+
+	merged.outline.elements = outline.into_boxed_slice();
+	merged.sideview.elements = sideview.into_boxed_slice();
+
+	Ok(merged)
+}
+
+// the new ids of a document's stations, given the stations already in use
+fn renumber(
+	index: usize,
+	document: &Document,
+	used: &BTreeSet<StationId>,
+	options: &MergeOptions,
+) -> Result<BTreeMap<StationId, StationId>, MergeError> {
+	let mut renumbering: BTreeMap<StationId, StationId> = options
+		.equates
+		.iter()
+		.filter(|equate| equate.document == index)
+		.map(|equate| (equate.station, equate.to))
+		.collect();
+
+	if options.collisions == Collisions::Equate {
+		return Ok(renumbering);
+	}
+
+	let stations: BTreeSet<StationId> = stations(document)
+		.into_iter()
+		.filter(|station| !renumbering.contains_key(station))
+		.collect();
+
+	// series which collide are moved to majors beyond any in use
+	let majors = |station: &StationId| match station {
+		StationId::MajorMinor(major, _) => Some(*major),
+		StationId::Plain(_) => None,
+	};
+	let colliding: BTreeSet<u16> = stations
+		.iter()
+		.filter(|station| used.contains(station))
+		.filter_map(majors)
+		.collect();
+	let next = used
+		.iter()
+		.chain(stations.iter())
+		.chain(renumbering.values())
+		.filter_map(majors)
+		.max()
+		.map_or(0, |major| major + 1);
+	let series: BTreeMap<u16, u16> = colliding.into_iter().zip(next..).collect();
+
+	// plain numbers are shifted together, if any collide
+	let plains = |station: &StationId| match station {
+		StationId::Plain(number) => Some(*number),
+		StationId::MajorMinor(..) => None,
+	};
+	let collides = stations
+		.iter()
+		.any(|station| plains(station).is_some() && used.contains(station));
+	let offset = match collides {
+		true => used
+			.iter()
+			.chain(renumbering.values())
+			.filter_map(plains)
+			.max()
+			.map_or(0, |number| number + 1),
+		false => 0,
+	};
+	let lowest = stations.iter().filter_map(plains).min().unwrap_or(0);
+
+	for station in stations {
+		let renumbered = match station {
+			StationId::MajorMinor(major, minor) => match series.get(&major) {
+				Some(major) if *major <= StationId::MAX_MAJOR => {
+					StationId::MajorMinor(*major, minor)
+				}
+				Some(_) => return Err(MergeError::NoFreeStationIds(station)),
+				None => continue,
+			},
+			StationId::Plain(_) if !collides => continue,
+			StationId::Plain(number) => (number - lowest)
+				.checked_add(offset)
+				.filter(|number| *number <= StationId::MAX_PLAIN)
+				.map(StationId::Plain)
+				.ok_or(MergeError::NoFreeStationIds(station))?,
+		};
+
+		renumbering.insert(station, renumbered);
+	}
+
+	Ok(renumbering)
+}
+
+// outline points are in mm, with y increasing southwards
+fn outline_offset(position: Position) -> Point {
+	Point {
+		x: millimetres(position.east),
+		y: millimetres(-position.north),
+	}
+}
+
+fn millimetres(metres: f64) -> i32 {
+	(metres * 1000.0).round() as i32
+}
+
+fn translate(
+	element: &Element,
+	offset: Point,
+	station: impl Fn(StationId) -> StationId,
+) -> Element {
+	let translate = |point: &Point| Point {
+		x: point.x.saturating_add(offset.x),
+		y: point.y.saturating_add(offset.y),
+	};
+
+	let mut element = element.clone();
+
+	match &mut element {
+		Element::Polygon(polygon) => {
+			polygon.points = polygon.points.iter().map(translate).collect();
+		}
+		Element::CrossSection(cross_section) => {
+			cross_section.position = translate(&cross_section.position);
+			cross_section.station = station(cross_section.station);
+		}
+		Element::Unknown { .. } => {}
+	}
+
+	element
+}
+
+fn points(element: &Element) -> impl Iterator<Item = &Point> {
+	let points: &[Point] = match element {
+		Element::Polygon(polygon) => &polygon.points,
+		Element::CrossSection(cross_section) => std::slice::from_ref(&cross_section.position),
+		Element::Unknown { .. } => &[],
+	};
+
+	points.iter()
+}
+
+fn min_x(element: &Element) -> Option<i32> {
+	points(element).map(|point| point.x).min()
+}
+
+fn max_x(element: &Element) -> Option<i32> {
+	points(element).map(|point| point.x).max()
+}
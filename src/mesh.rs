@@ -0,0 +1,394 @@
+use std::{
+	collections::BTreeMap,
+	f64::consts::PI,
+	io::{self, Write},
+};
+
+use crate::{
+	parser::Document,
+	reduction::{self, Centerline, North, Position},
+	StationId,
+};
+
+// A triangulated surface, in metres east, north and up from the first station.
+// Triangles wind anticlockwise seen from outside the passage.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Mesh {
+	pub vertices: Vec<Position>,
+	pub triangles: Vec<[u32; 3]>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Options {
+	// the number of vertices around each station
+	pub segments: usize,
+	// whether to close the passage at stations with only one leg
+	pub caps: bool,
+	pub north: North,
+}
+
+impl Default for Options {
+	fn default() -> Self {
+		Self {
+			segments: 16,
+			caps: true,
+			north: North::default(),
+		}
+	}
+}
+
+// Models the passage walls as a tube. Around each station with at least three
+// splays, the splays' ends are projected onto the plane across the passage and
+// resampled into a ring; the rings at each end of a leg are stitched together.
+// Junctions are modelled as overlapping tubes, so the mesh isn't watertight.
+pub fn mesh(document: &Document, options: &Options) -> Mesh {
+	let centerline = reduction::reduce_with_options(
+		document,
+		&reduction::Options {
+			north: options.north,
+		},
+	);
+	let segments = options.segments.max(3);
+
+	let mut splays: BTreeMap<StationId, Vec<Position>> = BTreeMap::new();
+	for splay in centerline.splays.iter() {
+		splays.entry(splay.from).or_default().push(splay.vector);
+	}
+
+	let mut mesh = Mesh::default();
+	let mut rings: BTreeMap<StationId, Ring> = BTreeMap::new();
+
+	for (station, splays) in splays {
+		let Some(position) = centerline.position(&station) else {
+			continue;
+		};
+
+		let direction = direction(&centerline, station);
+		if let Some(ring) = Ring::new(&mut mesh, position, direction, &splays, segments) {
+			rings.insert(station, ring);
+		}
+	}
+
+	let mut legs: BTreeMap<StationId, Vec<StationId>> = BTreeMap::new();
+
+	for leg in centerline.legs.iter() {
+		let (Some(from), Some(to)) = (rings.get(&leg.from), rings.get(&leg.to)) else {
+			continue;
+		};
+
+		legs.entry(leg.from).or_default().push(leg.to);
+		legs.entry(leg.to).or_default().push(leg.from);
+
+		// the rings are mirrored about their tops if the stations face
+		// opposite ways
+		let mirrored = from.direction.dot(to.direction) < 0.0;
+		let centre = (from.centre + to.centre) * 0.5;
+
+		for k in 0..segments {
+			let next = (k + 1) % segments;
+			let (to_k, to_next) = match mirrored {
+				true => (
+					to.vertex((segments - k) % segments),
+					to.vertex((segments - next) % segments),
+				),
+				false => (to.vertex(k), to.vertex(next)),
+			};
+
+			mesh.triangle([from.vertex(k), from.vertex(next), to_next], centre);
+			mesh.triangle([from.vertex(k), to_next, to_k], centre);
+		}
+	}
+
+	if options.caps {
+		for (station, ring) in rings.iter() {
+			let [other] = legs.get(station).map_or(&[][..], |legs| legs.as_slice()) else {
+				continue;
+			};
+
+			// the cap faces away from the rest of the passage
+			let centre = mesh.vertex(ring.centre);
+			let inside = rings[other].centre;
+
+			for k in 0..segments {
+				let next = (k + 1) % segments;
+				mesh.triangle([centre, ring.vertex(k), ring.vertex(next)], inside);
+			}
+		}
+	}
+
+	mesh
+}
+
+// the direction the passage faces at a station, or north if it has none
+fn direction(centerline: &Centerline, station: StationId) -> Position {
+	let direction = centerline.bisector(station, false);
+
+	match direction.length() > 0.0 {
+		true => direction,
+		false => NORTH,
+	}
+}
+
+const NORTH: Position = Position {
+	east: 0.0,
+	north: 1.0,
+	up: 0.0,
+};
+
+const UP: Position = Position {
+	east: 0.0,
+	north: 0.0,
+	up: 1.0,
+};
+
+// the vertices around a station, starting above it and turning to its right
+struct Ring {
+	centre: Position,
+	direction: Position,
+	first: u32,
+}
+
+impl Ring {
+	fn new(
+		mesh: &mut Mesh,
+		centre: Position,
+		direction: Position,
+		splays: &[Position],
+		segments: usize,
+	) -> Option<Self> {
+		// axes across the passage: left, and up for a horizontal passage
+		let left = match UP.cross(direction).length() > 1e-6 {
+			true => UP.cross(direction).unit(),
+			false => NORTH.cross(direction).unit(),
+		};
+		let up = direction.cross(left);
+
+		let mut points: Vec<(f64, f64)> = splays
+			.iter()
+			.map(|splay| {
+				let (x, y) = (splay.dot(left), splay.dot(up));
+				(y.atan2(x), (x * x + y * y).sqrt())
+			})
+			.collect();
+
+		if points.len() < 3 {
+			return None;
+		}
+
+		points.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+		let first = mesh.vertices.len() as u32;
+
+		for k in 0..segments {
+			let angle = PI / 2.0 + 2.0 * PI * k as f64 / segments as f64;
+			let angle = match angle > PI {
+				true => angle - 2.0 * PI,
+				false => angle,
+			};
+			let radius = interpolate(&points, angle);
+			let (sin, cos) = angle.sin_cos();
+
+			mesh.vertices
+				.push(centre + left * (radius * cos) + up * (radius * sin));
+		}
+
+		Some(Ring {
+			centre,
+			direction,
+			first,
+		})
+	}
+
+	fn vertex(&self, k: usize) -> u32 {
+		self.first + k as u32
+	}
+}
+
+// the radius at an angle in (-π, π], between the sorted (angle, radius)
+// points either side of it
+fn interpolate(points: &[(f64, f64)], angle: f64) -> f64 {
+	let (first, last) = (points[0], points[points.len() - 1]);
+
+	// wrapped around the circle at either end
+	let mut wrapped = vec![(last.0 - 2.0 * PI, last.1)];
+	wrapped.extend_from_slice(points);
+	wrapped.push((first.0 + 2.0 * PI, first.1));
+
+	let (before, after) = wrapped
+		.windows(2)
+		.map(|pair| (pair[0], pair[1]))
+		.find(|(before, after)| before.0 <= angle && angle <= after.0)
+		.expect("the points wrap around the circle");
+
+	let span = after.0 - before.0;
+	match span > 0.0 {
+		true => before.1 + (after.1 - before.1) * (angle - before.0) / span,
+		false => before.1,
+	}
+}
+
+impl Mesh {
+	fn vertex(&mut self, position: Position) -> u32 {
+		self.vertices.push(position);
+		self.vertices.len() as u32 - 1
+	}
+
+	// adds a triangle, wound to face away from `inside`
+	fn triangle(&mut self, mut triangle: [u32; 3], inside: Position) {
+		let [a, b, c] = triangle.map(|vertex| self.vertices[vertex as usize]);
+		let centroid = (a + b + c) * (1.0 / 3.0);
+
+		if (b - a).cross(c - a).dot(centroid - inside) < 0.0 {
+			triangle.swap(1, 2);
+		}
+
+		self.triangles.push(triangle);
+	}
+
+	fn normal(&self, triangle: &[u32; 3]) -> Position {
+		let [a, b, c] = triangle.map(|vertex| self.vertices[vertex as usize]);
+		(b - a).cross(c - a).unit()
+	}
+
+	// a colour for each vertex, from blue at the deepest to red at the highest
+	pub fn depth_colours(&self) -> Vec<[u8; 3]> {
+		let ups = self.vertices.iter().map(|vertex| vertex.up);
+		let low = ups.clone().fold(f64::INFINITY, f64::min);
+		let high = ups.fold(f64::NEG_INFINITY, f64::max);
+
+		self.vertices
+			.iter()
+			.map(|vertex| match high > low {
+				true => depth_colour((vertex.up - low) / (high - low)),
+				false => depth_colour(0.5),
+			})
+			.collect()
+	}
+}
+
+// blue through green to red, for 0 to 1
+pub fn depth_colour(t: f64) -> [u8; 3] {
+	let t = t.clamp(0.0, 1.0);
+	let channel = |value: f64| (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+
+	[
+		channel(2.0 * t - 1.0),
+		channel(1.0 - (2.0 * t - 1.0).abs()),
+		channel(1.0 - 2.0 * t),
+	]
+}
+
+// Wavefront OBJ, with vertex colours after the coordinates if given.
+pub fn write_obj<W: Write>(
+	mesh: &Mesh,
+	colours: Option<&[[u8; 3]]>,
+	output: &mut W,
+) -> io::Result<()> {
+	check_colours(mesh, colours)?;
+
+	writeln!(output, "# east north up (m)")?;
+
+	for (index, vertex) in mesh.vertices.iter().enumerate() {
+		write!(
+			output,
+			"v {:.3} {:.3} {:.3}",
+			vertex.east, vertex.north, vertex.up
+		)?;
+
+		if let Some(colours) = colours {
+			let [r, g, b] = colours[index].map(|channel| f64::from(channel) / 255.0);
+			write!(output, " {r:.3} {g:.3} {b:.3}")?;
+		}
+
+		writeln!(output)?;
+	}
+
+	for [a, b, c] in mesh.triangles.iter() {
+		writeln!(output, "f {} {} {}", a + 1, b + 1, c + 1)?;
+	}
+
+	Ok(())
+}
+
+// Binary PLY, with vertex colours if given.
+pub fn write_ply<W: Write>(
+	mesh: &Mesh,
+	colours: Option<&[[u8; 3]]>,
+	output: &mut W,
+) -> io::Result<()> {
+	check_colours(mesh, colours)?;
+
+	writeln!(output, "ply")?;
+	writeln!(output, "format binary_little_endian 1.0")?;
+	writeln!(output, "element vertex {}", mesh.vertices.len())?;
+	writeln!(output, "property float x")?;
+	writeln!(output, "property float y")?;
+	writeln!(output, "property float z")?;
+	if colours.is_some() {
+		writeln!(output, "property uchar red")?;
+		writeln!(output, "property uchar green")?;
+		writeln!(output, "property uchar blue")?;
+	}
+	writeln!(output, "element face {}", mesh.triangles.len())?;
+	writeln!(output, "property list uchar uint vertex_indices")?;
+	writeln!(output, "end_header")?;
+
+	for (index, vertex) in mesh.vertices.iter().enumerate() {
+		for coordinate in [vertex.east, vertex.north, vertex.up] {
+			output.write_all(&(coordinate as f32).to_le_bytes())?;
+		}
+
+		if let Some(colours) = colours {
+			output.write_all(&colours[index])?;
+		}
+	}
+
+	for triangle in mesh.triangles.iter() {
+		output.write_all(&[3])?;
+		for vertex in triangle {
+			output.write_all(&vertex.to_le_bytes())?;
+		}
+	}
+
+	Ok(())
+}
+
+// there must be a colour for each vertex, if any
+fn check_colours(mesh: &Mesh, colours: Option<&[[u8; 3]]>) -> io::Result<()> {
+	match colours {
+		Some(colours) if colours.len() != mesh.vertices.len() => Err(io::Error::new(
+			io::ErrorKind::InvalidInput,
+			format!(
+				"{} colours for {} vertices",
+				colours.len(),
+				mesh.vertices.len()
+			),
+		)),
+		_ => Ok(()),
+	}
+}
+
+// Binary STL, which has no colours.
+pub fn write_stl<W: Write>(mesh: &Mesh, output: &mut W) -> io::Result<()> {
+	let mut header = [0_u8; 80];
+	let title = b"pockettopo passage mesh";
+	header[..title.len()].copy_from_slice(title);
+
+	output.write_all(&header)?;
+	output.write_all(&(mesh.triangles.len() as u32).to_le_bytes())?;
+
+	for triangle in mesh.triangles.iter() {
+		let normal = mesh.normal(triangle);
+		let vertices = triangle.map(|vertex| mesh.vertices[vertex as usize]);
+
+		for position in [normal].iter().chain(vertices.iter()) {
+			for coordinate in [position.east, position.north, position.up] {
+				output.write_all(&(coordinate as f32).to_le_bytes())?;
+			}
+		}
+
+		output.write_all(&[0, 0])?;
+	}
+
+	Ok(())
+}
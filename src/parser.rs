@@ -1,19 +1,23 @@
-use chrono::NaiveDateTime;
+use std::{borrow::Cow, collections::HashMap};
+
+use chrono::{DateTime, NaiveDateTime};
+use encoding_rs::Encoding;
 use nom::{
 	branch::alt,
 	bytes::complete::{tag, take, take_while},
-	multi::{length_count, many_till},
+	multi::{count, length_count, many_till},
 	number::complete::{le_i16, le_i32, le_i64, le_u32, le_u8},
 	Finish, IResult,
 };
 use thiserror::Error;
 
 use crate::{
-	Color, CrossSection, Drawing, Element, Mapping, Point, Polygon, Reference, Shot, ShotFlags,
-	StationId, Trip,
+	geodesy::Crs, Color, CrossSection, Drawing, Element, Mapping, Point, Polygon, Reference, Shot,
+	ShotFlags, StationId, Trip,
 };
 
-#[derive(Debug)]
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Document<'a> {
 	pub references: Box<[Reference<'a>]>,
 	pub shots: Box<[Shot<'a>]>,
@@ -21,6 +25,8 @@ pub struct Document<'a> {
 	pub mapping: Mapping,
 	pub outline: Drawing,
 	pub sideview: Drawing,
+	// not stored in .top files, so taken from `ParseOptions::crs`
+	pub crs: Option<Crs>,
 }
 
 #[derive(Debug, Error, Eq, PartialEq)]
@@ -34,6 +40,9 @@ pub enum ParseError<'a> {
 	#[error("undefined station")]
 	UndefinedStation,
 
+	#[error("unknown element: {0:#04X?}")]
+	UnknownElement(u8),
+
 	#[error("unknown error")]
 	UnknownError,
 
@@ -54,11 +63,67 @@ impl<'a, I> nom::error::ParseError<I> for ParseError<'a> {
 	}
 }
 
+#[derive(Clone, Copy, Debug, Default)]
+pub enum CommentEncoding {
+	// fail with `ParseError::Utf8Error`
+	#[default]
+	Utf8,
+	// replace invalid sequences with U+FFFD
+	Utf8Lossy,
+	// decode the whole comment with a legacy code page, e.g. `encoding_rs::WINDOWS_1252`
+	Legacy(&'static Encoding),
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ParseOptions {
+	// how comments which aren't valid UTF-8 are decoded
+	pub comment_encoding: CommentEncoding,
+	// the references' coordinate system, which files don't record
+	pub crs: Option<Crs>,
+}
+
+// a record in a `Document`, by its index
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Record {
+	Reference(usize),
+	Shot(usize),
+	Trip(usize),
+}
+
 const HEADER: &[u8; 3] = b"Top";
 const VERSION: u8 = 0x3;
 
-pub fn parse(input: &[u8]) -> Result<Document, ParseError> {
-	parse_internal(input).finish().map(|(_, document)| document)
+pub fn parse(input: &[u8]) -> Result<Document<'_>, ParseError<'_>> {
+	parse_with_options(input, &ParseOptions::default()).map(|(document, _)| document)
+}
+
+// Parses with the given options, and also returns the records whose comments
+// weren't valid UTF-8 and were decoded using `ParseOptions::comment_encoding`.
+pub fn parse_with_options<'a>(
+	input: &'a [u8],
+	options: &ParseOptions,
+) -> Result<(Document<'a>, Box<[Record]>), ParseError<'a>> {
+	let (_, document) = parse_internal(input, options).finish()?;
+
+	// comments are only copied when they had to be decoded
+	let is_decoded = |comment: &Cow<str>| matches!(comment, Cow::Owned(_));
+
+	let trips = document.trips.iter().enumerate();
+	let trips = trips.filter(|(_, trip)| is_decoded(&trip.comment));
+
+	let shots = document.shots.iter().enumerate();
+	let shots = shots.filter(|(_, shot)| shot.comment.as_ref().is_some_and(is_decoded));
+
+	let references = document.references.iter().enumerate();
+	let references = references.filter(|(_, reference)| is_decoded(&reference.comment));
+
+	let decoded = trips
+		.map(|(index, _)| Record::Trip(index))
+		.chain(shots.map(|(index, _)| Record::Shot(index)))
+		.chain(references.map(|(index, _)| Record::Reference(index)))
+		.collect();
+
+	Ok((document, decoded))
 }
 
 // File = {
@@ -76,16 +141,19 @@ pub fn parse(input: &[u8]) -> Result<Document, ParseError> {
 // 	 Drawing outline
 // 	 Drawing sideview
 // }
-fn parse_internal(input: &[u8]) -> IResult<&[u8], Document, ParseError> {
+fn parse_internal<'a>(
+	input: &'a [u8],
+	options: &ParseOptions,
+) -> IResult<&'a [u8], Document<'a>, ParseError<'a>> {
 	let (input, _) = parse_header(input)?;
 	let (input, _) = parse_version(input)?;
-	let (input, trips) = parse_trips(input)?;
-	let (input, shots) = parse_shots(input)?;
-	let (input, references) = parse_references(input)?;
+	let (input, trips) = parse_trips(input, options)?;
+	let (input, shots) = parse_shots(input, options)?;
+	let (input, references) = parse_references(input, options)?;
 
 	let (input, mapping) = parse_mapping(input)?;
-	let (input, outline) = parse_drawing(input)?;
-	let (input, sideview) = parse_drawing(input)?;
+	let (input, outline) = parse_drawing(input, is_outline_trailer)?;
+	let (input, sideview) = parse_drawing(input, is_sideview_trailer)?;
 
 	Ok((
 		input,
@@ -96,18 +164,19 @@ fn parse_internal(input: &[u8]) -> IResult<&[u8], Document, ParseError> {
 			mapping,
 			outline,
 			sideview,
+			crs: options.crs,
 		},
 	))
 }
 
-fn parse_header(input: &[u8]) -> IResult<&[u8], &[u8], ParseError> {
+pub(crate) fn parse_header(input: &[u8]) -> IResult<&[u8], &[u8], ParseError<'_>> {
 	tag(HEADER)(input).map_err(|_: nom::Err<ParseError>| {
 		let found = input.chunks(HEADER.len()).next().unwrap_or(b"");
 		nom::Err::Failure(ParseError::InvalidHeader(found))
 	})
 }
 
-fn parse_version(input: &[u8]) -> IResult<&[u8], u8, ParseError> {
+pub(crate) fn parse_version(input: &[u8]) -> IResult<&[u8], u8, ParseError<'_>> {
 	let (input, version) = le_u8(input)?;
 
 	if version != VERSION {
@@ -123,7 +192,7 @@ fn parse_version(input: &[u8]) -> IResult<&[u8], u8, ParseError> {
 // 	 Id station
 // 	 Int32 direction // -1: horizontal, >=0; projection azimuth (internal angle units)
 // }
-fn parse_cross_section(input: &[u8]) -> IResult<&[u8], Element, ParseError> {
+fn parse_cross_section(input: &[u8]) -> IResult<&[u8], Element, ParseError<'_>> {
 	let (input, _) = tag([0x3_u8])(input)?;
 
 	let (input, position) = parse_point(input)?;
@@ -144,16 +213,20 @@ fn parse_cross_section(input: &[u8]) -> IResult<&[u8], Element, ParseError> {
 	Ok((input, cross_section))
 }
 
-fn parse_datetime(input: &[u8]) -> IResult<&[u8], NaiveDateTime, ParseError> {
-	const NANOSECONDS: i64 = 10000000;
+fn parse_datetime(input: &[u8]) -> IResult<&[u8], NaiveDateTime, ParseError<'_>> {
+	const TICKS_PER_SECOND: i64 = 10000000;
+	const NANOSECONDS_PER_TICK: i64 = 100;
 	const SECONDS_FROM_DOT_NET_EPOCH_TO_UNIX_EPOCH: i64 = 62135596800;
 
 	let (input, ticks) = le_i64(input)?;
 
-	let seconds = (ticks / NANOSECONDS) - SECONDS_FROM_DOT_NET_EPOCH_TO_UNIX_EPOCH;
-	let nsecs = (ticks % NANOSECONDS) as u32;
+	let seconds = ticks.div_euclid(TICKS_PER_SECOND) - SECONDS_FROM_DOT_NET_EPOCH_TO_UNIX_EPOCH;
+	let nsecs = (ticks.rem_euclid(TICKS_PER_SECOND) * NANOSECONDS_PER_TICK) as u32;
 
-	let time = NaiveDateTime::from_timestamp(seconds, nsecs);
+	// every `Int64` tick count is well within the range supported by chrono
+	let time = DateTime::from_timestamp(seconds, nsecs)
+		.expect("timestamp out of range")
+		.naive_utc();
 
 	Ok((input, time))
 }
@@ -163,9 +236,16 @@ fn parse_datetime(input: &[u8]) -> IResult<&[u8], NaiveDateTime, ParseError> {
 //   Element[] elements
 //   Byte 0  // end of element list
 // }
-fn parse_drawing(input: &[u8]) -> IResult<&[u8], Drawing, ParseError> {
+//
+// `trailer` recognises whatever follows the drawing in the file, and is used to
+// find the end of any element with an unknown id.
+pub(crate) fn parse_drawing(
+	input: &[u8],
+	trailer: fn(&[u8]) -> bool,
+) -> IResult<&[u8], Drawing, ParseError<'_>> {
 	let (input, mapping) = parse_mapping(input)?;
-	let (input, (elements, _)) = many_till(parse_element, tag([0x0_u8]))(input)?;
+	let (input, (elements, _)) =
+		many_till(|input| parse_element(input, trailer), tag([0x0_u8]))(input)?;
 
 	let drawing = Drawing {
 		mapping,
@@ -179,15 +259,124 @@ fn parse_drawing(input: &[u8]) -> IResult<&[u8], Drawing, ParseError> {
 //   Byte id  // element type
 //   ...
 // }
-fn parse_element(input: &[u8]) -> IResult<&[u8], Element, ParseError> {
-	alt((parse_polygon, parse_cross_section))(input)
+pub(crate) fn parse_element(
+	input: &[u8],
+	trailer: fn(&[u8]) -> bool,
+) -> IResult<&[u8], Element, ParseError<'_>> {
+	match input.first() {
+		Some(0x1_u8) => parse_polygon(input),
+		Some(0x3_u8) => parse_cross_section(input),
+		_ => parse_unknown_element(input, trailer),
+	}
+}
+
+// the most bytes an unknown element may take, which bounds resynchronising in a
+// damaged file
+const MAX_UNKNOWN_ELEMENT: usize = 4096;
+
+// The length of an unknown element isn't recorded in the file, so it's found by
+// resynchronising: the element takes the fewest bytes, up to
+// `MAX_UNKNOWN_ELEMENT`, after which the rest of the drawing parses as known
+// elements, followed by a valid `trailer`. Only lengths followed by a known
+// element or the end of the drawing are tried.
+//
+// Several unknown elements in the same drawing are read as a single element
+// spanning all of them, and trailing zero bytes of an unknown element at the
+// end of the sideview can't be told apart from the padding after it. If no
+// length works, the document fails with `ParseError::UnknownElement`.
+fn parse_unknown_element(
+	input: &[u8],
+	trailer: fn(&[u8]) -> bool,
+) -> IResult<&[u8], Element, ParseError<'_>> {
+	let (input, id) = le_u8(input)?;
+
+	// by the number of bytes left, where the known elements from there end and
+	// whether the trailer follows that end, since the candidates soon fall in
+	// step with the same elements
+	let mut ends = HashMap::new();
+	let mut trailers = HashMap::new();
+
+	for length in 0..=input.len().min(MAX_UNKNOWN_ELEMENT) {
+		let (bytes, rest) = input.split_at(length);
+
+		if !matches!(rest.first(), Some(0x0_u8 | 0x1_u8 | 0x3_u8)) {
+			continue;
+		}
+
+		let resynchronised = match known_elements_end(rest, &mut ends) {
+			Some(end) => *trailers
+				.entry(end)
+				.or_insert_with(|| trailer(&input[input.len() - end..])),
+			None => false,
+		};
+
+		if resynchronised {
+			let element = Element::Unknown {
+				id,
+				bytes: bytes.into(),
+			};
+
+			return Ok((rest, element));
+		}
+	}
+
+	Err(nom::Err::Failure(ParseError::UnknownElement(id)))
+}
+
+// The number of bytes left after the known elements at the start of `input`
+// and the drawing's terminator, or `None` if they don't parse. `ends` caches
+// this by the number of bytes left at each element.
+fn known_elements_end(input: &[u8], ends: &mut HashMap<usize, Option<usize>>) -> Option<usize> {
+	let mut visited = Vec::new();
+	let mut rest = input;
+
+	let end = loop {
+		if let Some(end) = ends.get(&rest.len()) {
+			break *end;
+		}
+		visited.push(rest.len());
+
+		if rest.first() == Some(&0x0_u8) {
+			break Some(rest.len() - 1);
+		}
+
+		match alt((parse_polygon, parse_cross_section))(rest) {
+			Ok((after, _)) => rest = after,
+			Err(_) => break None,
+		}
+	};
+
+	for position in visited {
+		ends.insert(position, end);
+	}
+
+	end
+}
+
+// the outline is followed by the sideview drawing, whose scale is checked
+// first to rule out misaligned candidates without parsing the rest of the file
+pub(crate) fn is_outline_trailer(input: &[u8]) -> bool {
+	match parse_mapping(input) {
+		Ok((_, mapping)) if (10..=50000).contains(&mapping.scale) => {}
+		_ => return false,
+	}
+
+	match parse_drawing(input, is_sideview_trailer) {
+		Ok((input, _)) => is_sideview_trailer(input),
+		Err(_) => false,
+	}
+}
+
+// the sideview is followed by the end of the file, which PocketTopo pads with zeros
+pub(crate) fn is_sideview_trailer(input: &[u8]) -> bool {
+	input.iter().all(|byte| *byte == 0x0_u8)
 }
 
 // Mapping = {  // least recently used scroll position and scale
 //   Point origin // middle of screen relative to first reference
 // 	 Int32 scale  // 10..50000
 // }
-fn parse_mapping(input: &[u8]) -> IResult<&[u8], Mapping, ParseError> {
+pub(crate) fn parse_mapping(input: &[u8]) -> IResult<&[u8], Mapping, ParseError<'_>> {
 	let (input, origin) = parse_point(input)?;
 	let (input, scale) = le_i32(input)?;
 
@@ -200,7 +389,7 @@ fn parse_mapping(input: &[u8]) -> IResult<&[u8], Mapping, ParseError> {
 //   Int32 x  // mm
 //   Int32 y  // mm
 // }
-fn parse_point(input: &[u8]) -> IResult<&[u8], Point, ParseError> {
+fn parse_point(input: &[u8]) -> IResult<&[u8], Point, ParseError<'_>> {
 	let (input, x) = le_i32(input)?;
 	let (input, y) = le_i32(input)?;
 
@@ -215,10 +404,14 @@ fn parse_point(input: &[u8]) -> IResult<&[u8], Point, ParseError> {
 // 	 Point[pointCount] points // open polygon
 // 	 Byte color // black = 1, gray = 2, brown = 3, blue = 4; red = 5, green = 6, orange = 7
 // }
-fn parse_polygon(input: &[u8]) -> IResult<&[u8], Element, ParseError> {
+fn parse_polygon(input: &[u8]) -> IResult<&[u8], Element, ParseError<'_>> {
 	let (input, _) = tag([0x1_u8])(input)?;
 
-	let (input, points) = length_count(le_u32, parse_point)(input)?;
+	// a count the rest of the input can't hold fails before parsing any points
+	let (input, length) = le_u32(input)?;
+	take((length as usize).saturating_mul(8).saturating_add(1))(input)?;
+
+	let (input, points) = count(parse_point, length as usize)(input)?;
 	let (input, color) = le_u8(input)?;
 
 	let color = match color {
@@ -240,8 +433,11 @@ fn parse_polygon(input: &[u8]) -> IResult<&[u8], Element, ParseError> {
 	Ok((input, polygon))
 }
 
-fn parse_shots(input: &[u8]) -> IResult<&[u8], Box<[Shot]>, ParseError> {
-	length_count(le_u32, parse_shot)(input)
+pub(crate) fn parse_shots<'a>(
+	input: &'a [u8],
+	options: &ParseOptions,
+) -> IResult<&'a [u8], Box<[Shot<'a>]>, ParseError<'a>> {
+	length_count(le_u32, |input| parse_shot(input, options))(input)
 		.map(|(input, collection)| (input, collection.into_boxed_slice()))
 }
 
@@ -257,7 +453,10 @@ fn parse_shots(input: &[u8]) -> IResult<&[u8], Box<[Shot]>, ParseError> {
 // 	 if (flags & 2)
 // 	   String comment
 // }
-fn parse_shot(input: &[u8]) -> IResult<&[u8], Shot, ParseError> {
+fn parse_shot<'a>(
+	input: &'a [u8],
+	options: &ParseOptions,
+) -> IResult<&'a [u8], Shot<'a>, ParseError<'a>> {
 	let (input, from) = parse_station_id(input)?;
 	let (input, to) = parse_station_id(input)?;
 	let (input, distance) = le_i32(input)?;
@@ -270,7 +469,7 @@ fn parse_shot(input: &[u8]) -> IResult<&[u8], Shot, ParseError> {
 	let flags = ShotFlags { bits: flags };
 
 	let (input, comment) = if flags.contains(ShotFlags::HAS_COMMENT) {
-		let (input, string) = parse_string(input)?;
+		let (input, string) = parse_string(input, options)?;
 		(input, Some(string))
 	} else {
 		(input, None)
@@ -294,7 +493,7 @@ fn parse_shot(input: &[u8]) -> IResult<&[u8], Shot, ParseError> {
 // Id = { // station identification
 //   Int32 value  // 0x80000000: undefined, <0: plain numbers + 0x80000001, >=0: major<<16|minor
 // }
-fn parse_station_id(input: &[u8]) -> IResult<&[u8], Option<StationId>, ParseError> {
+fn parse_station_id(input: &[u8]) -> IResult<&[u8], Option<StationId>, ParseError<'_>> {
 	const UNDEFINED: u32 = 0b10000000000000000000000000000000;
 
 	let (input, station_id) = le_u32(input)?;
@@ -319,20 +518,33 @@ fn parse_station_id(input: &[u8]) -> IResult<&[u8], Option<StationId>, ParseErro
 //   Byte[] length // unsigned, encoded in 7 bit chunks, little endian, bit7 set in all but the last byte
 //   Byte[length]  // UTF8 encoded, 1 to 3 bytes per character, not 0 terminated
 // }
-fn parse_string(input: &[u8]) -> IResult<&[u8], &str, ParseError> {
+fn parse_string<'a>(
+	input: &'a [u8],
+	options: &ParseOptions,
+) -> IResult<&'a [u8], Cow<'a, str>, ParseError<'a>> {
 	let (input, length) = parse_variable_length_little_endian_int(input)?;
 	let (input, bytes) = take(length)(input)?;
 
-	let str = match std::str::from_utf8(bytes) {
-		Ok(str) => str,
-		Err(err) => return Err(nom::Err::Error(ParseError::from(err))),
+	let string = match (std::str::from_utf8(bytes), options.comment_encoding) {
+		(Ok(str), _) => Cow::Borrowed(str),
+		(Err(err), CommentEncoding::Utf8) => return Err(nom::Err::Error(ParseError::from(err))),
+		(Err(_), CommentEncoding::Utf8Lossy) => {
+			Cow::Owned(String::from_utf8_lossy(bytes).into_owned())
+		}
+		(Err(_), CommentEncoding::Legacy(encoding)) => {
+			let (string, _) = encoding.decode_without_bom_handling(bytes);
+			Cow::Owned(string.into_owned())
+		}
 	};
 
-	Ok((input, str))
+	Ok((input, string))
 }
 
-fn parse_references(input: &[u8]) -> IResult<&[u8], Box<[Reference]>, ParseError> {
-	length_count(le_u32, parse_reference)(input)
+pub(crate) fn parse_references<'a>(
+	input: &'a [u8],
+	options: &ParseOptions,
+) -> IResult<&'a [u8], Box<[Reference<'a>]>, ParseError<'a>> {
+	length_count(le_u32, |input| parse_reference(input, options))(input)
 		.map(|(input, collection)| (input, collection.into_boxed_slice()))
 }
 
@@ -343,12 +555,15 @@ fn parse_references(input: &[u8]) -> IResult<&[u8], Box<[Reference]>, ParseError
 // 	 Int32 altitude // mm above sea level
 // 	 String comment
 // }
-fn parse_reference(input: &[u8]) -> IResult<&[u8], Reference, ParseError> {
+fn parse_reference<'a>(
+	input: &'a [u8],
+	options: &ParseOptions,
+) -> IResult<&'a [u8], Reference<'a>, ParseError<'a>> {
 	let (input, station) = parse_station_id(input)?;
 	let (input, east) = le_i64(input)?;
 	let (input, north) = le_i64(input)?;
 	let (input, altitude) = le_i32(input)?;
-	let (input, comment) = parse_string(input)?;
+	let (input, comment) = parse_string(input, options)?;
 
 	let reference = Reference {
 		station,
@@ -361,8 +576,11 @@ fn parse_reference(input: &[u8]) -> IResult<&[u8], Reference, ParseError> {
 	Ok((input, reference))
 }
 
-fn parse_trips(input: &[u8]) -> IResult<&[u8], Box<[Trip]>, ParseError> {
-	length_count(le_u32, parse_trip)(input)
+pub(crate) fn parse_trips<'a>(
+	input: &'a [u8],
+	options: &ParseOptions,
+) -> IResult<&'a [u8], Box<[Trip<'a>]>, ParseError<'a>> {
+	length_count(le_u32, |input| parse_trip(input, options))(input)
 		.map(|(input, collection)| (input, collection.into_boxed_slice()))
 }
 
@@ -371,9 +589,12 @@ fn parse_trips(input: &[u8]) -> IResult<&[u8], Box<[Trip]>, ParseError> {
 // 	 String comment
 // 	 Int16 declination  // internal angle units (full circle = 2^16)
 // }
-fn parse_trip(input: &[u8]) -> IResult<&[u8], Trip, ParseError> {
+fn parse_trip<'a>(
+	input: &'a [u8],
+	options: &ParseOptions,
+) -> IResult<&'a [u8], Trip<'a>, ParseError<'a>> {
 	let (input, time) = parse_datetime(input)?;
-	let (input, comment) = parse_string(input)?;
+	let (input, comment) = parse_string(input, options)?;
 	let (input, declination) = le_i16(input)?;
 
 	let trip = Trip {
@@ -386,7 +607,9 @@ fn parse_trip(input: &[u8]) -> IResult<&[u8], Trip, ParseError> {
 }
 
 // unsigned, encoded in 7 bit chunks, little endian, bit7 set in all but the last byte
-fn parse_variable_length_little_endian_int(input: &[u8]) -> IResult<&[u8], usize, ParseError> {
+pub(crate) fn parse_variable_length_little_endian_int(
+	input: &[u8],
+) -> IResult<&[u8], usize, ParseError<'_>> {
 	const BIT_7_SET: u8 = 0b10000000;
 
 	let (input, bytes) = take_while(|byte| byte & BIT_7_SET == BIT_7_SET)(input)?;
@@ -427,7 +650,7 @@ mod test {
 		let result = parse(&contents);
 
 		let error = result.expect_err("expected `ParserError`");
-		assert_eq!(error, ParseError::InvalidHeader(&[b'T', b'O', b'P']));
+		assert_eq!(error, ParseError::InvalidHeader(b"TOP"));
 
 		assert_eq!(error.to_string(), "invalid header: [84, 79, 80]");
 	}
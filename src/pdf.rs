@@ -0,0 +1,1146 @@
+use std::io::{self, Write};
+
+use encoding_rs::WINDOWS_1252;
+use pdf_writer::{
+	types::{LineCapStyle, LineJoinStyle},
+	Content, Finish, Name, Pdf, Rect, Ref, Str, TextStr,
+};
+use thiserror::Error;
+
+use crate::{
+	map::{self, Map, Sheet},
+	parser::Document,
+	reduction::{self, Centerline},
+	units, Color, Element, Point,
+};
+
+// paper sizes in mm, landscape
+pub const A4: (f64, f64) = (297.0, 210.0);
+pub const A3: (f64, f64) = (420.0, 297.0);
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Options {
+	// the drawings to include, each on pages of its own
+	pub sheets: Vec<Sheet>,
+	// the map scale's denominator, e.g. 500 for 1:500
+	pub scale: f64,
+	// the paper's width and height, in mm
+	pub page: (f64, f64),
+	// in mm
+	pub margin: f64,
+	// instead of the first trip's comment
+	pub title: Option<String>,
+	// what the drawings' colours mean, instead of their names
+	pub legend: Vec<(Color, String)>,
+	pub labels: bool,
+}
+
+impl Default for Options {
+	fn default() -> Self {
+		Self {
+			sheets: vec![Sheet::Outline, Sheet::Sideview],
+			scale: 500.0,
+			page: A4,
+			margin: 10.0,
+			title: None,
+			legend: Vec::new(),
+			labels: true,
+		}
+	}
+}
+
+#[derive(Debug, Error)]
+pub enum PdfError {
+	#[error("invalid scale, page size or margin")]
+	InvalidOptions,
+
+	#[error("too many pages: {0}")]
+	TooManyPages(usize),
+
+	#[error(transparent)]
+	Io(#[from] io::Error),
+}
+
+const MAX_PAGES: usize = 500;
+
+// the band below the map for the scale bar, north arrow, legend and title
+// block, and the gap above it, in mm
+const BAND: f64 = 32.0;
+const GAP: f64 = 4.0;
+const TITLE_WIDTH: f64 = 80.0;
+const LEGEND_WIDTH: f64 = 38.0;
+// the space around the survey, and to the right of it for labels, in mm
+const PADDING: f64 = 5.0;
+const LABEL_ROOM: f64 = 8.0;
+// the smallest distance between grid lines, in mm
+const GRID: f64 = 20.0;
+
+// line widths and sizes, in mm, and font sizes in points
+const POLYGON_WIDTH: f64 = 0.25;
+const CENTERLINE_WIDTH: f64 = 0.18;
+const FRAME_WIDTH: f64 = 0.3;
+const GRID_WIDTH: f64 = 0.1;
+const STATION_RADIUS: f64 = 0.35;
+const SECTION_RADIUS: f64 = 1.2;
+const LABEL_SIZE: f64 = 5.0;
+
+const BLACK: [u8; 3] = [0, 0, 0];
+const WHITE: [u8; 3] = [255, 255, 255];
+const GRID_COLOUR: [u8; 3] = [170, 170, 170];
+const CENTERLINE: [u8; 3] = [204, 0, 0];
+
+// Writes a map of each of `options.sheets` at a true scale, tiled across as
+// many pages as it needs. Each page has a coordinate grid, which is in the
+// anchor's coordinates if its station is on the map and turned to grid north
+// for a document with a CRS; a scale bar; in plan, a north arrow with grid
+// north and magnetic north for the most recent trip; a legend; and a title
+// block with the trips' dates and comments.
+pub fn write_pdf<W: Write>(
+	document: &Document,
+	options: &Options,
+	output: &mut W,
+) -> Result<(), PdfError> {
+	let (width, height) = options.page;
+	let area = (
+		width - 2.0 * options.margin,
+		height - 2.0 * options.margin - BAND - GAP,
+	);
+
+	let valid = options.scale.is_finite() && options.scale > 0.0 && options.margin >= 0.0;
+	if !(valid && area.0 >= 50.0 && area.1 >= 50.0) {
+		return Err(PdfError::InvalidOptions);
+	}
+
+	let mut pages = Vec::new();
+
+	// for the grid's convergence and anchor, the same on every sheet
+	let north = reduction::Options {
+		north: reduction::North::Grid,
+	};
+	let centerline = reduction::reduce_with_options(document, &north);
+
+	for sheet in options.sheets.iter().copied() {
+		let map = map::map(document, sheet);
+		let tiles = tiles(&map, options, area, MAX_PAGES - pages.len())?;
+		let grid = Grid::new(document, &centerline, &map, sheet, options.scale);
+
+		let count = tiles.len();
+		for (index, tile) in tiles.into_iter().enumerate() {
+			let mut page = Page::new(height);
+			page.map(&map, &grid, &tile, options);
+			page.band(
+				document,
+				&map,
+				sheet,
+				centerline.corrections.convergence,
+				options,
+				(index, count, tile.position),
+			);
+			pages.push(page.content.finish());
+		}
+	}
+
+	let catalog = Ref::new(1);
+	let tree = Ref::new(2);
+	let regular = Ref::new(3);
+	let bold = Ref::new(4);
+	let info = Ref::new(5);
+
+	let mut pdf = Pdf::new();
+	pdf.catalog(catalog).pages(tree);
+
+	let ids: Vec<(Ref, Ref)> = (0..pages.len() as i32)
+		.map(|index| (Ref::new(6 + 2 * index), Ref::new(7 + 2 * index)))
+		.collect();
+
+	pdf.pages(tree)
+		.kids(ids.iter().map(|(page, _)| *page))
+		.count(ids.len() as i32);
+
+	for ((page, contents), data) in ids.iter().zip(pages.iter()) {
+		let mut writer = pdf.page(*page);
+		writer
+			.media_box(Rect::new(0.0, 0.0, pt(width), pt(height)))
+			.parent(tree)
+			.contents(*contents);
+
+		let mut resources = writer.resources();
+		let mut fonts = resources.fonts();
+		fonts.pair(Name(Font::Regular.name()), regular);
+		fonts.pair(Name(Font::Bold.name()), bold);
+		fonts.finish();
+		resources.finish();
+		writer.finish();
+
+		pdf.stream(*contents, data);
+	}
+
+	for (id, font) in [
+		(regular, b"Helvetica".as_slice()),
+		(bold, b"Helvetica-Bold"),
+	] {
+		pdf.type1_font(id)
+			.base_font(Name(font))
+			.encoding_predefined(Name(b"WinAnsiEncoding"));
+	}
+
+	pdf.document_info(info)
+		.title(TextStr(&title(document, options)))
+		.creator(TextStr("pocket-topo"));
+
+	output.write_all(&pdf.finish())?;
+
+	Ok(())
+}
+
+// A page's window onto a map.
+struct Tile {
+	// the drawing's point at the map area's top left, in mm
+	origin: (f64, f64),
+	// the map area on the page, in mm
+	area: (f64, f64, f64, f64),
+	scale: f64,
+	// the row and column, if there's more than one page
+	position: Option<(usize, usize)>,
+}
+
+impl Tile {
+	// from the drawing's mm to the page's
+	fn paper(&self, point: Point) -> (f64, f64) {
+		let (left, top, _, _) = self.area;
+
+		(
+			left + (f64::from(point.x) - self.origin.0) / self.scale,
+			top + (f64::from(point.y) - self.origin.1) / self.scale,
+		)
+	}
+
+	// the drawing's visible extent, in mm
+	fn visible(&self) -> ((f64, f64), (f64, f64)) {
+		let (_, _, width, height) = self.area;
+		let (x, y) = self.origin;
+
+		((x, y), (x + width * self.scale, y + height * self.scale))
+	}
+
+	fn overlaps(&self, points: &[Point]) -> bool {
+		let ((left, top), (right, bottom)) = self.visible();
+		let xs = points.iter().map(|point| f64::from(point.x));
+		let ys = points.iter().map(|point| f64::from(point.y));
+
+		xs.clone().fold(f64::INFINITY, f64::min) <= right
+			&& xs.fold(f64::NEG_INFINITY, f64::max) >= left
+			&& ys.clone().fold(f64::INFINITY, f64::min) <= bottom
+			&& ys.fold(f64::NEG_INFINITY, f64::max) >= top
+	}
+}
+
+// the pages a map needs at its scale, with the survey centred across them
+fn tiles(
+	map: &Map,
+	options: &Options,
+	area: (f64, f64),
+	limit: usize,
+) -> Result<Vec<Tile>, PdfError> {
+	let origin = Point { x: 0, y: 0 };
+	let (min, max) = map.bounds().unwrap_or((origin, origin));
+
+	let labels = match options.labels {
+		true => LABEL_ROOM,
+		false => 0.0,
+	};
+	let width = (f64::from(max.x) - f64::from(min.x)) / options.scale + 2.0 * PADDING + labels;
+	let height = (f64::from(max.y) - f64::from(min.y)) / options.scale + 2.0 * PADDING;
+
+	let columns = (width / area.0).ceil().max(1.0) as usize;
+	let rows = (height / area.1).ceil().max(1.0) as usize;
+
+	if columns.saturating_mul(rows) > limit {
+		let pages = (MAX_PAGES - limit).saturating_add(columns.saturating_mul(rows));
+		return Err(PdfError::TooManyPages(pages));
+	}
+
+	// the space to the left of and above the survey, across all the pages
+	let left = (columns as f64 * area.0 - width) / 2.0 + PADDING;
+	let top = (rows as f64 * area.1 - height) / 2.0 + PADDING;
+
+	let mut tiles = Vec::new();
+	for row in 0..rows {
+		for column in 0..columns {
+			tiles.push(Tile {
+				origin: (
+					f64::from(min.x) + (column as f64 * area.0 - left) * options.scale,
+					f64::from(min.y) + (row as f64 * area.1 - top) * options.scale,
+				),
+				area: (options.margin, options.margin, area.0, area.1),
+				scale: options.scale,
+				position: (columns * rows > 1).then_some((row, column)),
+			});
+		}
+	}
+
+	Ok(tiles)
+}
+
+// Map coordinates in metres, from the drawing's mm: eastings and northings in
+// plan, or altitudes in profile. The drawing is to true north, so in plan a
+// document with a CRS has its grid turned by the convergence.
+struct Grid {
+	// at the drawing's origin
+	easting: Option<f64>,
+	northing: f64,
+	// grid north's angle clockwise from the drawing's up, in radians
+	rotation: f64,
+	// between lines
+	spacing: f64,
+}
+
+impl Grid {
+	// `centerline` is reduced to grid north
+	fn new(
+		document: &Document,
+		centerline: &Centerline,
+		map: &Map,
+		sheet: Sheet,
+		scale: f64,
+	) -> Self {
+		let rotation = match sheet {
+			Sheet::Outline => centerline.corrections.convergence.unwrap_or_default(),
+			Sheet::Sideview => 0.0,
+		};
+
+		let mut grid = Grid {
+			easting: (sheet == Sheet::Outline).then_some(0.0),
+			northing: 0.0,
+			rotation: rotation.to_radians(),
+			spacing: round_length(units::metres(GRID * scale), true),
+		};
+
+		let anchor = centerline.anchor(document);
+		let reference = anchor.and_then(|anchor| {
+			let point = map.stations.get(&anchor.station)?;
+			Some((anchor.reference, point))
+		});
+
+		if let Some((reference, point)) = reference {
+			let (easting, northing) = grid.at((f64::from(point.x), f64::from(point.y)));
+
+			grid.easting = grid
+				.easting
+				.map(|_| units::metres(reference.east as f64) - easting);
+			grid.northing = match sheet {
+				Sheet::Outline => units::metres(reference.north as f64) - northing,
+				Sheet::Sideview => units::metres(reference.altitude) - northing,
+			};
+		}
+
+		grid
+	}
+
+	// the map coordinates of a point in the drawing's mm
+	fn at(&self, (x, y): (f64, f64)) -> (f64, f64) {
+		let (sin, cos) = self.rotation.sin_cos();
+		let (east, north) = (units::metres(x), -units::metres(y));
+
+		(
+			self.easting.unwrap_or_default() + east * cos - north * sin,
+			self.northing + north * cos + east * sin,
+		)
+	}
+
+	// The eastings and northings of the lines crossing the drawing between two
+	// corners, in mm.
+	fn values(&self, (x0, y0): (f64, f64), (x1, y1): (f64, f64)) -> (Vec<f64>, Vec<f64>) {
+		let corners = [(x0, y0), (x1, y0), (x0, y1), (x1, y1)].map(|corner| self.at(corner));
+
+		let values = |coordinates: [f64; 4]| -> Vec<f64> {
+			let min = coordinates.into_iter().fold(f64::INFINITY, f64::min);
+			let max = coordinates.into_iter().fold(f64::NEG_INFINITY, f64::max);
+
+			let first = (min / self.spacing).ceil() as i64;
+			let last = (max / self.spacing).floor() as i64;
+			(first..=last)
+				.map(|index| index as f64 * self.spacing)
+				.collect()
+		};
+
+		(
+			values(corners.map(|(easting, _)| easting)),
+			values(corners.map(|(_, northing)| northing)),
+		)
+	}
+
+	// where the line of an easting crosses the drawing's `y`, in mm
+	fn easting_x(&self, easting: f64, y: f64) -> f64 {
+		let (sin, cos) = self.rotation.sin_cos();
+		let east = easting - self.easting.unwrap_or_default();
+
+		(east * 1000.0 - y * sin) / cos
+	}
+
+	// where the line of a northing crosses the drawing's `x`, in mm
+	fn northing_y(&self, northing: f64, x: f64) -> f64 {
+		let (sin, cos) = self.rotation.sin_cos();
+		let north = northing - self.northing;
+
+		(x * sin - north * 1000.0) / cos
+	}
+
+	fn label(&self, value: f64) -> String {
+		let decimals = (-self.spacing.log10().floor()).max(0.0) as usize;
+		format!("{value:.decimals$}")
+	}
+}
+
+// a length of 1, 2 or 5 × 10ⁿ: the smallest at least `length`, or the largest
+// at most it
+fn round_length(length: f64, at_least: bool) -> f64 {
+	let magnitude = 10_f64.powf(length.log10().floor());
+
+	let candidates = [1.0, 2.0, 5.0, 10.0].map(|step| step * magnitude);
+	match at_least {
+		true => candidates
+			.into_iter()
+			.find(|candidate| *candidate >= length)
+			.unwrap_or(10.0 * magnitude),
+		false => candidates
+			.into_iter()
+			.rev()
+			.find(|candidate| *candidate <= length)
+			.unwrap_or(magnitude),
+	}
+}
+
+#[derive(Clone, Copy)]
+enum Font {
+	Regular,
+	Bold,
+}
+
+impl Font {
+	fn name(self) -> &'static [u8] {
+		match self {
+			Font::Regular => b"F1",
+			Font::Bold => b"F2",
+		}
+	}
+}
+
+#[derive(Clone, Copy)]
+enum Anchor {
+	Start,
+	Middle,
+}
+
+// A page's content, drawn in mm from the top left.
+struct Page {
+	content: Content,
+	height: f64,
+}
+
+impl Page {
+	fn new(height: f64) -> Self {
+		let mut content = Content::new();
+		content.set_line_cap(LineCapStyle::RoundCap);
+		content.set_line_join(LineJoinStyle::RoundJoin);
+
+		Page { content, height }
+	}
+
+	fn position(&self, (x, y): (f64, f64)) -> (f32, f32) {
+		(pt(x), pt(self.height - y))
+	}
+
+	fn map(&mut self, map: &Map, grid: &Grid, tile: &Tile, options: &Options) {
+		let (left, top, width, height) = tile.area;
+
+		self.content.save_state();
+		let (x, y) = self.position((left, top + height));
+		self.content
+			.rect(x, y, pt(width), pt(height))
+			.clip_nonzero()
+			.end_path();
+
+		self.grid(grid, tile);
+
+		for element in map.drawing.elements.iter() {
+			if let Element::Polygon(polygon) = element {
+				if tile.overlaps(&polygon.points) {
+					let points: Vec<_> = polygon
+						.points
+						.iter()
+						.map(|point| tile.paper(*point))
+						.collect();
+					self.line(&points, polygon.color.rgb(), POLYGON_WIDTH, false);
+				}
+			}
+		}
+
+		for element in map.drawing.elements.iter() {
+			if let Element::CrossSection(section) = element {
+				let position = tile.paper(section.position);
+
+				if let Some(station) = map.stations.get(&section.station) {
+					self.line(
+						&[tile.paper(*station), position],
+						BLACK,
+						CENTERLINE_WIDTH,
+						true,
+					);
+				}
+
+				self.circle(position, SECTION_RADIUS, BLACK, false);
+			}
+		}
+
+		for (from, to) in map.legs.iter() {
+			if tile.overlaps(&[*from, *to]) {
+				self.line(
+					&[tile.paper(*from), tile.paper(*to)],
+					CENTERLINE,
+					CENTERLINE_WIDTH,
+					false,
+				);
+			}
+		}
+
+		for (station, point) in map.stations.iter() {
+			if !tile.overlaps(&[*point]) {
+				continue;
+			}
+
+			let (x, y) = tile.paper(*point);
+			self.circle((x, y), STATION_RADIUS, CENTERLINE, true);
+
+			if options.labels {
+				let text = station.to_string();
+				self.text(
+					(x + 0.8, y - 0.8),
+					LABEL_SIZE,
+					Font::Regular,
+					&text,
+					Anchor::Start,
+					BLACK,
+				);
+			}
+		}
+
+		self.content.restore_state();
+
+		self.rectangle((left, top), (width, height), None, Some(FRAME_WIDTH));
+	}
+
+	// The lines run past the tile, which the map is clipped to, and are
+	// labelled where they cross its top and left edges.
+	fn grid(&mut self, grid: &Grid, tile: &Tile) {
+		let (left, top, _, _) = tile.area;
+		let ((x0, y0), (x1, y1)) = tile.visible();
+		let paper =
+			|(x, y): (f64, f64)| (left + (x - x0) / tile.scale, top + (y - y0) / tile.scale);
+
+		let (eastings, northings) = grid.values((x0, y0), (x1, y1));
+
+		if grid.easting.is_some() {
+			for value in eastings {
+				let (start, end) = (grid.easting_x(value, y0), grid.easting_x(value, y1));
+				self.line(
+					&[paper((start, y0)), paper((end, y1))],
+					GRID_COLOUR,
+					GRID_WIDTH,
+					false,
+				);
+
+				if (x0..=x1).contains(&start) {
+					let (x, _) = paper((start, y0));
+					self.text(
+						(x + 0.6, top + 2.5),
+						LABEL_SIZE,
+						Font::Regular,
+						&grid.label(value),
+						Anchor::Start,
+						GRID_COLOUR,
+					);
+				}
+			}
+		}
+
+		for value in northings {
+			let (start, end) = (grid.northing_y(value, x0), grid.northing_y(value, x1));
+			self.line(
+				&[paper((x0, start)), paper((x1, end))],
+				GRID_COLOUR,
+				GRID_WIDTH,
+				false,
+			);
+
+			if (y0..=y1).contains(&start) {
+				let (_, y) = paper((x0, start));
+				self.text(
+					(left + 0.6, y - 0.6),
+					LABEL_SIZE,
+					Font::Regular,
+					&grid.label(value),
+					Anchor::Start,
+					GRID_COLOUR,
+				);
+			}
+		}
+	}
+
+	fn band(
+		&mut self,
+		document: &Document,
+		map: &Map,
+		sheet: Sheet,
+		convergence: Option<f64>,
+		options: &Options,
+		(index, count, position): (usize, usize, Option<(usize, usize)>),
+	) {
+		let (width, height) = options.page;
+		let top = height - options.margin - BAND;
+		let left = options.margin;
+
+		self.scale_bar((left, top), options.scale);
+
+		if sheet == Sheet::Outline {
+			self.north_arrow((left + 70.0, top), document, convergence);
+		}
+
+		let legend = left + 84.0;
+		let right = width - options.margin - TITLE_WIDTH - 4.0;
+		self.legend((legend, top), right - legend, map, options);
+
+		let sheet = match sheet {
+			Sheet::Outline => "Plan",
+			Sheet::Sideview => "Profile",
+		};
+		let mut footer = format!("{sheet} 1:{}", options.scale);
+		if count > 1 {
+			footer += &format!(", sheet {} of {count}", index + 1);
+		}
+		if let Some((row, column)) = position {
+			footer += &format!(" (row {}, column {})", row + 1, column + 1);
+		}
+
+		self.title_block(
+			(width - options.margin - TITLE_WIDTH, top),
+			document,
+			options,
+			&footer,
+		);
+	}
+
+	fn scale_bar(&mut self, (left, top): (f64, f64), scale: f64) {
+		let length = round_length(units::metres(50.0 * scale), false);
+		let segments = match (length / 10_f64.powf(length.log10().floor())).round() as u32 {
+			2 => 4,
+			_ => 5,
+		};
+
+		let paper = length * 1000.0 / scale;
+		let step = paper / segments as f64;
+		let y = top + 14.0;
+
+		self.text(
+			(left, top + 6.0),
+			8.0,
+			Font::Bold,
+			&format!("Scale 1:{scale}"),
+			Anchor::Start,
+			BLACK,
+		);
+
+		for segment in 0..segments {
+			let fill = match segment % 2 {
+				0 => BLACK,
+				_ => WHITE,
+			};
+			self.rectangle(
+				(left + segment as f64 * step, y),
+				(step, 2.0),
+				Some(fill),
+				Some(GRID_WIDTH),
+			);
+		}
+
+		self.text(
+			(left, y + 6.0),
+			7.0,
+			Font::Regular,
+			"0",
+			Anchor::Middle,
+			BLACK,
+		);
+		self.text(
+			(left + paper, y + 6.0),
+			7.0,
+			Font::Regular,
+			&format!("{length} m"),
+			Anchor::Middle,
+			BLACK,
+		);
+	}
+
+	// true north up, with grid north for a document with a CRS and magnetic
+	// north for the most recent trip
+	fn north_arrow(&mut self, (x, top): (f64, f64), document: &Document, convergence: Option<f64>) {
+		let (base, tip) = ((x, top + 24.0), (x, top + 8.0));
+
+		self.line(&[base, tip], BLACK, FRAME_WIDTH, false);
+		self.polygon(
+			&[tip, (x - 1.5, tip.1 + 4.0), (x + 1.5, tip.1 + 4.0)],
+			BLACK,
+		);
+		self.text(
+			(x, tip.1 - 1.5),
+			8.0,
+			Font::Bold,
+			"N",
+			Anchor::Middle,
+			BLACK,
+		);
+
+		if let Some(convergence) = convergence {
+			let (sin, cos) = convergence.to_radians().sin_cos();
+			let grid = (base.0 + 14.0 * sin, base.1 - 14.0 * cos);
+
+			self.line(&[base, grid], BLACK, GRID_WIDTH, false);
+			self.text(
+				(grid.0, grid.1 - 1.0),
+				6.0,
+				Font::Regular,
+				"GN",
+				Anchor::Middle,
+				BLACK,
+			);
+		}
+
+		let Some(trip) = document.trips.iter().max_by_key(|trip| trip.time) else {
+			return;
+		};
+
+		let declination = units::degrees(trip.declination);
+		let (sin, cos) = declination.to_radians().sin_cos();
+		let magnetic = (base.0 + 14.0 * sin, base.1 - 14.0 * cos);
+
+		self.line(&[base, magnetic], BLACK, GRID_WIDTH, true);
+		self.text(
+			(magnetic.0, magnetic.1 - 1.0),
+			6.0,
+			Font::Regular,
+			"MN",
+			Anchor::Middle,
+			BLACK,
+		);
+
+		let side = match declination < 0.0 {
+			true => "W",
+			false => "E",
+		};
+		let text = format!("{:.1}° {side}", declination.abs());
+		self.text(
+			(x, base.1 + 4.0),
+			6.0,
+			Font::Regular,
+			&text,
+			Anchor::Middle,
+			BLACK,
+		);
+	}
+
+	fn legend(&mut self, (left, top): (f64, f64), width: f64, map: &Map, options: &Options) {
+		let mut entries: Vec<(Symbol, String)> =
+			vec![(Symbol::Centerline, "Centerline".to_owned())];
+
+		let sections = map
+			.drawing
+			.elements
+			.iter()
+			.any(|element| matches!(element, Element::CrossSection(_)));
+		if sections {
+			entries.push((Symbol::CrossSection, "Cross-section".to_owned()));
+		}
+
+		for color in COLORS {
+			let used = map.drawing.elements.iter().any(|element| match element {
+				Element::Polygon(polygon) => polygon.color == color,
+				_ => false,
+			});
+
+			if used {
+				let label = options
+					.legend
+					.iter()
+					.find(|(legend, _)| *legend == color)
+					.map_or(name(color), |(_, label)| label.as_str());
+				entries.push((Symbol::Line(color), label.to_owned()));
+			}
+		}
+
+		let columns = (width / LEGEND_WIDTH).floor().max(0.0) as usize;
+
+		for (index, (symbol, label)) in entries.into_iter().take(4 * columns).enumerate() {
+			let x = left + (index / 4) as f64 * LEGEND_WIDTH;
+			let y = top + 6.0 + (index % 4) as f64 * 7.0;
+			let middle = y - 1.2;
+
+			match symbol {
+				Symbol::Centerline => {
+					self.line(
+						&[(x, middle), (x + 8.0, middle)],
+						CENTERLINE,
+						CENTERLINE_WIDTH,
+						false,
+					);
+					self.circle((x, middle), STATION_RADIUS, CENTERLINE, true);
+					self.circle((x + 8.0, middle), STATION_RADIUS, CENTERLINE, true);
+				}
+				Symbol::CrossSection => {
+					self.circle((x + 4.0, middle), SECTION_RADIUS, BLACK, false)
+				}
+				Symbol::Line(color) => self.line(
+					&[(x, middle), (x + 8.0, middle)],
+					color.rgb(),
+					POLYGON_WIDTH * 2.0,
+					false,
+				),
+			}
+
+			let label = fit(&label, 7.0, LEGEND_WIDTH - 12.0);
+			self.text(
+				(x + 10.0, y),
+				7.0,
+				Font::Regular,
+				&label,
+				Anchor::Start,
+				BLACK,
+			);
+		}
+	}
+
+	fn title_block(
+		&mut self,
+		(left, top): (f64, f64),
+		document: &Document,
+		options: &Options,
+		footer: &str,
+	) {
+		self.rectangle((left, top), (TITLE_WIDTH, BAND), None, Some(FRAME_WIDTH));
+
+		let (x, width) = (left + 2.5, TITLE_WIDTH - 5.0);
+
+		let title = fit(&title(document, options), 12.0, width);
+		self.text(
+			(x, top + 6.5),
+			12.0,
+			Font::Bold,
+			&title,
+			Anchor::Start,
+			BLACK,
+		);
+
+		let dates = document.trips.iter().map(|trip| trip.time.date());
+		let surveyed = match (dates.clone().min(), dates.max()) {
+			(Some(first), Some(last)) if first == last => format!("Surveyed {first}"),
+			(Some(first), Some(last)) => format!("Surveyed {first} to {last}"),
+			_ => String::new(),
+		};
+		self.text(
+			(x, top + 11.5),
+			8.0,
+			Font::Regular,
+			&surveyed,
+			Anchor::Start,
+			BLACK,
+		);
+
+		let mut trips: Vec<String> = document
+			.trips
+			.iter()
+			.filter(|trip| !trip.comment.trim().is_empty())
+			.map(|trip| format!("{} {}", trip.time.date(), first_line(&trip.comment)))
+			.collect();
+
+		if trips.len() > 3 {
+			let more = trips.len() - 2;
+			trips.truncate(2);
+			trips.push(format!("and {more} more trips"));
+		}
+
+		for (index, trip) in trips.iter().enumerate() {
+			let trip = fit(trip, 7.0, width);
+			self.text(
+				(x, top + 16.0 + index as f64 * 3.5),
+				7.0,
+				Font::Regular,
+				&trip,
+				Anchor::Start,
+				BLACK,
+			);
+		}
+
+		let footer = fit(footer, 7.0, width);
+		self.text(
+			(x, top + BAND - 2.5),
+			7.0,
+			Font::Bold,
+			&footer,
+			Anchor::Start,
+			BLACK,
+		);
+	}
+
+	fn line(&mut self, points: &[(f64, f64)], colour: [u8; 3], width: f64, dashed: bool) {
+		let Some((first, rest)) = points.split_first() else {
+			return;
+		};
+
+		self.content.save_state();
+		self.stroke_style(colour, width);
+		if dashed {
+			self.content.set_dash_pattern([pt(1.0), pt(1.0)], 0.0);
+		}
+
+		let (x, y) = self.position(*first);
+		self.content.move_to(x, y);
+		for point in rest {
+			let (x, y) = self.position(*point);
+			self.content.line_to(x, y);
+		}
+
+		self.content.stroke();
+		self.content.restore_state();
+	}
+
+	fn polygon(&mut self, points: &[(f64, f64)], colour: [u8; 3]) {
+		let Some((first, rest)) = points.split_first() else {
+			return;
+		};
+
+		self.content.save_state();
+		self.fill_style(colour);
+
+		let (x, y) = self.position(*first);
+		self.content.move_to(x, y);
+		for point in rest {
+			let (x, y) = self.position(*point);
+			self.content.line_to(x, y);
+		}
+
+		self.content.close_path().fill_nonzero();
+		self.content.restore_state();
+	}
+
+	// four Bézier curves
+	fn circle(&mut self, (x, y): (f64, f64), radius: f64, colour: [u8; 3], filled: bool) {
+		const K: f64 = 0.552_284_75;
+		let r = radius;
+		let k = K * radius;
+
+		self.content.save_state();
+		match filled {
+			true => self.fill_style(colour),
+			false => self.stroke_style(colour, CENTERLINE_WIDTH),
+		}
+
+		let (sx, sy) = self.position((x + r, y));
+		self.content.move_to(sx, sy);
+
+		let quarters = [
+			[(x + r, y + k), (x + k, y + r), (x, y + r)],
+			[(x - k, y + r), (x - r, y + k), (x - r, y)],
+			[(x - r, y - k), (x - k, y - r), (x, y - r)],
+			[(x + k, y - r), (x + r, y - k), (x + r, y)],
+		];
+		for [a, b, c] in quarters {
+			let (a, b, c) = (self.position(a), self.position(b), self.position(c));
+			self.content.cubic_to(a.0, a.1, b.0, b.1, c.0, c.1);
+		}
+
+		self.content.close_path();
+		match filled {
+			true => self.content.fill_nonzero(),
+			false => self.content.stroke(),
+		};
+		self.content.restore_state();
+	}
+
+	fn rectangle(
+		&mut self,
+		(left, top): (f64, f64),
+		(width, height): (f64, f64),
+		fill: Option<[u8; 3]>,
+		stroke: Option<f64>,
+	) {
+		self.content.save_state();
+
+		let (x, y) = self.position((left, top + height));
+		self.content.rect(x, y, pt(width), pt(height));
+
+		if let Some(fill) = fill {
+			self.fill_style(fill);
+		}
+		if let Some(stroke) = stroke {
+			self.stroke_style(BLACK, stroke);
+		}
+
+		match (fill, stroke) {
+			(Some(_), Some(_)) => self.content.fill_nonzero_and_stroke(),
+			(Some(_), None) => self.content.fill_nonzero(),
+			(None, Some(_)) => self.content.stroke(),
+			(None, None) => self.content.end_path(),
+		};
+		self.content.restore_state();
+	}
+
+	// text with its baseline at `y`, in a size in points
+	fn text(
+		&mut self,
+		(x, y): (f64, f64),
+		size: f64,
+		font: Font,
+		text: &str,
+		anchor: Anchor,
+		colour: [u8; 3],
+	) {
+		if text.is_empty() {
+			return;
+		}
+
+		let x = match anchor {
+			Anchor::Start => x,
+			Anchor::Middle => x - text_width(text, size) / 2.0,
+		};
+
+		let (x, y) = self.position((x, y));
+		let [r, g, b] = colour.map(|channel| f32::from(channel) / 255.0);
+
+		self.content
+			.begin_text()
+			.set_fill_rgb(r, g, b)
+			.set_font(Name(font.name()), size as f32)
+			.next_line(x, y)
+			.show(Str(&encode(text)))
+			.end_text();
+	}
+
+	fn stroke_style(&mut self, colour: [u8; 3], width: f64) {
+		let [r, g, b] = colour.map(|channel| f32::from(channel) / 255.0);
+		self.content
+			.set_stroke_rgb(r, g, b)
+			.set_line_width(pt(width));
+	}
+
+	fn fill_style(&mut self, colour: [u8; 3]) {
+		let [r, g, b] = colour.map(|channel| f32::from(channel) / 255.0);
+		self.content.set_fill_rgb(r, g, b);
+	}
+}
+
+enum Symbol {
+	Centerline,
+	CrossSection,
+	Line(Color),
+}
+
+const COLORS: [Color; 7] = [
+	Color::Black,
+	Color::Blue,
+	Color::Brown,
+	Color::Gray,
+	Color::Green,
+	Color::Orange,
+	Color::Red,
+];
+
+fn name(color: Color) -> &'static str {
+	match color {
+		Color::Black => "Black",
+		Color::Blue => "Blue",
+		Color::Brown => "Brown",
+		Color::Gray => "Gray",
+		Color::Green => "Green",
+		Color::Orange => "Orange",
+		Color::Red => "Red",
+	}
+}
+
+fn title(document: &Document, options: &Options) -> String {
+	if let Some(title) = &options.title {
+		return title.clone();
+	}
+
+	document
+		.trips
+		.iter()
+		.map(|trip| first_line(&trip.comment))
+		.find(|comment| !comment.is_empty())
+		.unwrap_or("Survey")
+		.to_owned()
+}
+
+fn first_line(text: &str) -> &str {
+	text.lines().next().unwrap_or_default().trim()
+}
+
+// from mm to points
+fn pt(mm: f64) -> f32 {
+	(mm * 72.0 / 25.4) as f32
+}
+
+// Windows-1252 for the standard fonts, with `?` for characters it doesn't have
+fn encode(text: &str) -> Vec<u8> {
+	text.chars()
+		.map(|character| {
+			let mut buffer = [0; 4];
+			let (bytes, _, unmappable) = WINDOWS_1252.encode(character.encode_utf8(&mut buffer));
+
+			match unmappable || bytes.len() != 1 {
+				true => b'?',
+				false => bytes[0],
+			}
+		})
+		.collect()
+}
+
+// shortens text to a width in mm, with an ellipsis
+fn fit(text: &str, size: f64, width: f64) -> String {
+	if text_width(text, size) <= width {
+		return text.to_owned();
+	}
+
+	let mut fitted: String = text.to_owned();
+	while !fitted.is_empty() && text_width(&format!("{fitted}…"), size) > width {
+		fitted.pop();
+	}
+
+	format!("{}…", fitted.trim_end())
+}
+
+// Helvetica's widths, in mm for a size in points
+fn text_width(text: &str, size: f64) -> f64 {
+	let units: u32 = text
+		.chars()
+		.map(|character| match character {
+			' '..='~' => u32::from(HELVETICA[character as usize - 32]),
+			_ => 556,
+		})
+		.sum();
+
+	f64::from(units) / 1000.0 * size * 25.4 / 72.0
+}
+
+// the widths of ' ' to '~' in thousandths of an em
+const HELVETICA: [u16; 95] = [
+	278, 278, 355, 556, 556, 889, 667, 191, 333, 333, 389, 584, 278, 333, 278,
+	278, // ' ' to '/'
+	556, 556, 556, 556, 556, 556, 556, 556, 556, 556, 278, 278, 584, 584, 584,
+	556, // '0' to '?'
+	1015, 667, 667, 722, 722, 667, 611, 778, 722, 278, 500, 667, 556, 833, 722,
+	778, // '@' to 'O'
+	667, 778, 722, 667, 611, 722, 667, 944, 667, 667, 611, 278, 278, 278, 469,
+	556, // 'P' to '_'
+	333, 556, 556, 500, 556, 556, 278, 556, 556, 222, 222, 500, 222, 833, 556,
+	556, // '`' to 'o'
+	556, 556, 333, 500, 278, 556, 500, 722, 500, 500, 500, 334, 260, 334, 584, // 'p' to '~'
+];
@@ -0,0 +1,275 @@
+use crate::{
+	map::{self, Sheet},
+	parser::Document,
+	Color, Element, Point,
+};
+
+// The characters to draw with.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Charset {
+	// 2 × 4 dots to a character
+	#[default]
+	Braille,
+	// half blocks, 1 × 2 to a character
+	Blocks,
+}
+
+impl Charset {
+	// dots across and down each character
+	fn dots(self) -> (usize, usize) {
+		match self {
+			Charset::Braille => (2, 4),
+			Charset::Blocks => (1, 2),
+		}
+	}
+
+	fn character(self, bits: u8) -> char {
+		match self {
+			Charset::Braille => char::from_u32(0x2800 + u32::from(bits)).unwrap_or(' '),
+			Charset::Blocks => match bits {
+				1 => '▀',
+				2 => '▄',
+				_ => '█',
+			},
+		}
+	}
+
+	// the bit for a dot within a character
+	fn bit(self, x: usize, y: usize) -> u8 {
+		match self {
+			Charset::Braille => match (x, y) {
+				(0, 3) => 0x40,
+				(1, 3) => 0x80,
+				(0, y) => 1 << y,
+				(_, y) => 1 << (y + 3),
+			},
+			Charset::Blocks => 1 << y,
+		}
+	}
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Options {
+	pub sheet: Sheet,
+	// in characters
+	pub width: usize,
+	// the most lines to use, if any
+	pub height: Option<usize>,
+	pub charset: Charset,
+	// whether to colour with ANSI escape codes
+	pub colour: bool,
+	pub labels: bool,
+}
+
+impl Default for Options {
+	fn default() -> Self {
+		Self {
+			sheet: Sheet::default(),
+			width: 80,
+			height: None,
+			charset: Charset::default(),
+			colour: true,
+			labels: false,
+		}
+	}
+}
+
+// The ANSI colours of the drawings, with black as the terminal's default
+// colour so that it shows on a dark background.
+fn style(color: Color) -> &'static str {
+	match color {
+		Color::Black => "39",
+		Color::Blue => "34",
+		Color::Brown => "38;5;130",
+		Color::Gray => "90",
+		Color::Green => "32",
+		Color::Orange => "38;5;208",
+		Color::Red => "31",
+	}
+}
+
+const CENTERLINE: &str = "91";
+const LABELS: &str = "39";
+
+// Draws a drawing and its centerline as lines of text, with square dots,
+// scaled to fit `options.width` and `options.height`. Each character takes
+// the colour of the last thing drawn in it, so the centerline is on top.
+// Labels are written to the right of their stations. Returns an empty string
+// if there's nothing to draw.
+pub fn preview(document: &Document, options: &Options) -> String {
+	let map = map::map(document, options.sheet);
+	let Some((min, max)) = map.bounds() else {
+		return String::new();
+	};
+
+	let (across, down) = options.charset.dots();
+	let span = (
+		f64::from(max.x) - f64::from(min.x),
+		f64::from(max.y) - f64::from(min.y),
+	);
+
+	// mm to a dot, fitting the width and the height
+	let mut scale = span.0 / ((options.width.max(1) * across - 1) as f64).max(1.0);
+	if let Some(height) = options.height {
+		scale = scale.max(span.1 / ((height.max(1) * down - 1) as f64).max(1.0));
+	}
+	if scale <= 0.0 {
+		scale = 1.0;
+	}
+
+	let columns = ((span.0 / scale).round() as usize + 1).div_ceil(across);
+	let rows = ((span.1 / scale).round() as usize + 1).div_ceil(down);
+
+	let mut canvas = Canvas {
+		charset: options.charset,
+		columns,
+		cells: vec![Cell::default(); columns * rows],
+		dot: move |point: Point| {
+			(
+				((f64::from(point.x) - f64::from(min.x)) / scale).round() as usize,
+				((f64::from(point.y) - f64::from(min.y)) / scale).round() as usize,
+			)
+		},
+	};
+
+	for element in map.drawing.elements.iter() {
+		match element {
+			Element::Polygon(polygon) => {
+				for pair in polygon.points.windows(2) {
+					canvas.line(pair[0], pair[1], style(polygon.color));
+				}
+			}
+			Element::CrossSection(section) => canvas.point(section.position, LABELS),
+			Element::Unknown { .. } => {}
+		}
+	}
+
+	for (from, to) in map.legs.iter() {
+		canvas.line(*from, *to, CENTERLINE);
+	}
+
+	for point in map.stations.values() {
+		canvas.point(*point, CENTERLINE);
+	}
+
+	if options.labels {
+		for (station, point) in map.stations.iter() {
+			canvas.label(*point, &station.to_string());
+		}
+	}
+
+	canvas.text(options.colour)
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+struct Cell {
+	bits: u8,
+	style: Option<&'static str>,
+	text: Option<char>,
+}
+
+struct Canvas<T: Fn(Point) -> (usize, usize)> {
+	charset: Charset,
+	columns: usize,
+	cells: Vec<Cell>,
+	// from the drawing's mm to dots
+	dot: T,
+}
+
+impl<T: Fn(Point) -> (usize, usize)> Canvas<T> {
+	fn set(&mut self, (x, y): (usize, usize), style: &'static str) {
+		let (across, down) = self.charset.dots();
+		let index = (y / down) * self.columns + x / across;
+
+		if let Some(cell) = self.cells.get_mut(index) {
+			cell.bits |= self.charset.bit(x % across, y % down);
+			cell.style = Some(style);
+		}
+	}
+
+	fn point(&mut self, point: Point, style: &'static str) {
+		let dot = (self.dot)(point);
+		self.set(dot, style);
+	}
+
+	// Bresenham's, between the dots nearest the ends
+	fn line(&mut self, from: Point, to: Point, style: &'static str) {
+		let (x0, y0) = (self.dot)(from);
+		let (x1, y1) = (self.dot)(to);
+		let (mut x, mut y) = (x0 as isize, y0 as isize);
+		let (x1, y1) = (x1 as isize, y1 as isize);
+
+		let dx = (x1 - x).abs();
+		let dy = -(y1 - y).abs();
+		let (sx, sy) = ((x1 - x).signum(), (y1 - y).signum());
+		let mut error = dx + dy;
+
+		loop {
+			self.set((x as usize, y as usize), style);
+
+			if x == x1 && y == y1 {
+				break;
+			}
+
+			let double = 2 * error;
+			if double >= dy {
+				error += dy;
+				x += sx;
+			}
+			if double <= dx {
+				error += dx;
+				y += sy;
+			}
+		}
+	}
+
+	fn label(&mut self, point: Point, text: &str) {
+		let (across, down) = self.charset.dots();
+		let (x, y) = (self.dot)(point);
+		let (column, row) = (x / across + 1, y / down);
+
+		for (offset, character) in text.chars().enumerate() {
+			if column + offset >= self.columns {
+				break;
+			}
+
+			let cell = &mut self.cells[row * self.columns + column + offset];
+			cell.text = Some(character);
+			cell.style = Some(LABELS);
+		}
+	}
+
+	fn text(&self, colour: bool) -> String {
+		let mut text = String::new();
+
+		for row in self.cells.chunks(self.columns) {
+			let mut current = None;
+			let mut line = String::new();
+
+			for cell in row {
+				let character = match (cell.text, cell.bits) {
+					(Some(character), _) => character,
+					(None, 0) => ' ',
+					(None, bits) => self.charset.character(bits),
+				};
+
+				if colour && character != ' ' && cell.style != current {
+					current = cell.style;
+					line += &format!("\x1b[{}m", current.unwrap_or("39"));
+				}
+
+				line.push(character);
+			}
+
+			let mut line = line.trim_end().to_owned();
+			if current.is_some() {
+				line += "\x1b[0m";
+			}
+
+			text += &line;
+			text.push('\n');
+		}
+
+		text
+	}
+}
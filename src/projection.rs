@@ -0,0 +1,142 @@
+use std::collections::BTreeMap;
+
+use crate::{
+	lrud::Lrud,
+	parser::Document,
+	reduction::{self, Position},
+	units, Point, StationId,
+};
+
+// How a survey is viewed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum View {
+	// from above, with north up
+	Plan,
+	// from the side, looking towards an azimuth in degrees
+	Elevation(f64),
+}
+
+impl View {
+	// the view of a cross-section's `direction`: -1 for a horizontal section,
+	// otherwise the azimuth in internal units
+	pub fn from_cross_section(direction: i32) -> View {
+		match direction {
+			-1 => View::Plan,
+			direction => View::Elevation(units::degrees(direction as u16 as i16).rem_euclid(360.0)),
+		}
+	}
+
+	// Projects a position in metres into drawing coordinates: mm, with x to
+	// the right and y downwards. In plan x is east; looking towards an azimuth,
+	// x is 90° clockwise of it.
+	pub fn project(&self, position: Position) -> Point {
+		let (x, y) = match self {
+			View::Plan => (position.east, -position.north),
+			View::Elevation(azimuth) => {
+				let (sin, cos) = azimuth.to_radians().sin_cos();
+				(position.east * cos - position.north * sin, -position.up)
+			}
+		};
+
+		Point {
+			x: (x * 1000.0).round() as i32,
+			y: (y * 1000.0).round() as i32,
+		}
+	}
+}
+
+pub type Polyline = Vec<Point>;
+
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Projection {
+	pub stations: BTreeMap<StationId, Point>,
+	// a polyline for each leg
+	pub centerline: Vec<Polyline>,
+	pub splays: Vec<Polyline>,
+	// left and right walls in plan, or upper and lower walls in elevation,
+	// between the stations of each leg which both have passage dimensions
+	pub walls: Vec<Polyline>,
+}
+
+// Projects the stations connected to the first station, which is at the origin
+// as in the drawings.
+pub fn project(document: &Document, view: View, lruds: &BTreeMap<StationId, Lrud>) -> Projection {
+	let centerline = reduction::reduce(document);
+	let position = |station: &StationId| centerline.position(station);
+
+	let stations = centerline
+		.stations
+		.iter()
+		.map(|(station, position)| (*station, view.project(*position)))
+		.collect();
+
+	let legs = centerline
+		.legs
+		.iter()
+		.filter_map(|leg| Some((leg, position(&leg.from)?, position(&leg.to)?)));
+
+	let mut lines = Vec::new();
+	let mut walls = Vec::new();
+
+	for (leg, from, to) in legs {
+		lines.push(vec![view.project(from), view.project(to)]);
+
+		let (Some(from_lrud), Some(to_lrud)) = (lruds.get(&leg.from), lruds.get(&leg.to)) else {
+			continue;
+		};
+
+		for side in sides(view) {
+			walls.push(vec![
+				view.project(from + side(from_lrud)),
+				view.project(to + side(to_lrud)),
+			]);
+		}
+	}
+
+	let splays = centerline
+		.splays
+		.iter()
+		.filter_map(|splay| {
+			let from = position(&splay.from)?;
+			Some(vec![view.project(from), view.project(from + splay.vector)])
+		})
+		.collect();
+
+	Projection {
+		stations,
+		centerline: lines,
+		splays,
+		walls,
+	}
+}
+
+// the walls seen in a view, as displacements from a station
+fn sides(view: View) -> [fn(&Lrud) -> Position; 2] {
+	match view {
+		View::Plan => [
+			|lrud| horizontal(lrud.direction - 90.0, lrud.left),
+			|lrud| horizontal(lrud.direction + 90.0, lrud.right),
+		],
+		View::Elevation(_) => [
+			|lrud| Position {
+				up: lrud.up,
+				..Position::default()
+			},
+			|lrud| Position {
+				up: -lrud.down,
+				..Position::default()
+			},
+		],
+	}
+}
+
+fn horizontal(azimuth: f64, distance: f64) -> Position {
+	let (sin, cos) = azimuth.to_radians().sin_cos();
+
+	Position {
+		east: distance * sin,
+		north: distance * cos,
+		up: 0.0,
+	}
+}
@@ -0,0 +1,414 @@
+use std::{
+	collections::{BTreeMap, HashMap},
+	ops::{Add, Mul, Neg, Sub},
+};
+
+use crate::{parser::Document, units, Reference, Shot, StationId};
+
+// A position or displacement in metres.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Position {
+	pub east: f64,
+	pub north: f64,
+	pub up: f64,
+}
+
+impl Position {
+	pub fn length(&self) -> f64 {
+		(self.east * self.east + self.north * self.north + self.up * self.up).sqrt()
+	}
+
+	pub fn horizontal_length(&self) -> f64 {
+		(self.east * self.east + self.north * self.north).sqrt()
+	}
+
+	// degrees clockwise from north, in [0, 360)
+	pub fn azimuth(&self) -> f64 {
+		self.east.atan2(self.north).to_degrees().rem_euclid(360.0)
+	}
+
+	pub fn dot(&self, other: Position) -> f64 {
+		self.east * other.east + self.north * other.north + self.up * other.up
+	}
+
+	pub fn cross(&self, other: Position) -> Position {
+		Position {
+			east: self.north * other.up - self.up * other.north,
+			north: self.up * other.east - self.east * other.up,
+			up: self.east * other.north - self.north * other.east,
+		}
+	}
+
+	// the displacement scaled to a length of 1, or zero if it's zero
+	pub fn unit(&self) -> Position {
+		match self.length() > 0.0 {
+			true => *self * (1.0 / self.length()),
+			false => *self,
+		}
+	}
+
+	// the angle to another displacement in degrees, or 0 if either is zero
+	pub fn angle(&self, other: Position) -> f64 {
+		let lengths = self.length() * other.length();
+		if lengths == 0.0 {
+			return 0.0;
+		}
+
+		(self.dot(other) / lengths)
+			.clamp(-1.0, 1.0)
+			.acos()
+			.to_degrees()
+	}
+}
+
+impl Add for Position {
+	type Output = Position;
+
+	fn add(self, other: Position) -> Position {
+		Position {
+			east: self.east + other.east,
+			north: self.north + other.north,
+			up: self.up + other.up,
+		}
+	}
+}
+
+impl Sub for Position {
+	type Output = Position;
+
+	fn sub(self, other: Position) -> Position {
+		self + -other
+	}
+}
+
+impl Neg for Position {
+	type Output = Position;
+
+	fn neg(self) -> Position {
+		Position {
+			east: -self.east,
+			north: -self.north,
+			up: -self.up,
+		}
+	}
+}
+
+impl Mul<f64> for Position {
+	type Output = Position;
+
+	fn mul(self, scale: f64) -> Position {
+		Position {
+			east: self.east * scale,
+			north: self.north * scale,
+			up: self.up * scale,
+		}
+	}
+}
+
+// A leg between two stations, averaged over all of the shots between them.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Leg {
+	pub from: StationId,
+	pub to: StationId,
+	// indices into `Document::shots`, in either direction
+	pub shots: Box<[usize]>,
+	pub vector: Position,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Splay {
+	pub from: StationId,
+	// index into `Document::shots`
+	pub shot: usize,
+	pub vector: Position,
+}
+
+// The direction azimuths are reduced to.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum North {
+	// as measured, without the trips' declinations
+	Magnetic,
+	// with the trips' declinations, as PocketTopo reduces
+	#[default]
+	True,
+	// with the trips' declinations, less the grid convergence at the
+	// centerline's anchor, for a document with a CRS
+	Grid,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Options {
+	pub north: North,
+}
+
+// The corrections which were added to each shot's azimuth.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Corrections {
+	// the north which was reduced to, which is true north if grid north was
+	// asked for but the convergence isn't known
+	pub north: North,
+	// whether each shot's trip's declination was added
+	pub declination: bool,
+	// in degrees, subtracted from every azimuth: grid north's angle clockwise
+	// from true north
+	pub convergence: Option<f64>,
+}
+
+impl Default for Corrections {
+	fn default() -> Self {
+		Self {
+			north: North::True,
+			declination: true,
+			convergence: None,
+		}
+	}
+}
+
+impl Corrections {
+	// The corrections for a document: grid north needs a CRS and a reference to
+	// find the convergence at, which should be the centerline's anchor.
+	pub fn new(document: &Document, reference: Option<&Reference>, options: &Options) -> Self {
+		let convergence = document
+			.crs
+			.zip(reference)
+			.map(|(crs, reference)| crs.reference(reference).1.convergence);
+
+		match (options.north, convergence) {
+			(North::Magnetic, _) => Self {
+				north: North::Magnetic,
+				declination: false,
+				convergence: None,
+			},
+			(North::Grid, Some(convergence)) => Self {
+				north: North::Grid,
+				declination: true,
+				convergence: Some(convergence),
+			},
+			(North::True | North::Grid, _) => Self::default(),
+		}
+	}
+}
+
+// The reference which georeferences a centerline, and the offset from the
+// centerline's positions to the reference's coordinates.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Anchor<'a> {
+	pub reference: &'a Reference<'a>,
+	pub station: StationId,
+	pub offset: Position,
+}
+
+// The stations' positions relative to `origin`, the `from` station of the
+// first shot, which is also the origin of the drawings' `Point`s. Stations
+// which aren't connected to the origin have no position. Loops aren't
+// adjusted: each station is positioned by the first leg which reaches it.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Centerline {
+	pub origin: Option<StationId>,
+	pub stations: BTreeMap<StationId, Position>,
+	pub legs: Box<[Leg]>,
+	pub splays: Box<[Splay]>,
+	pub corrections: Corrections,
+}
+
+impl Centerline {
+	pub fn position(&self, station: &StationId) -> Option<Position> {
+		self.stations.get(station).copied()
+	}
+
+	// The direction the passage faces at a station, as a unit vector: the
+	// bisector of its first incoming and first outgoing legs, in the direction
+	// they were surveyed, or along its only leg. If `horizontal`, the legs'
+	// inclinations are ignored. Zero if the station has no legs or they cancel
+	// out.
+	pub fn bisector(&self, station: StationId, horizontal: bool) -> Position {
+		let incoming = self.legs.iter().find(|leg| leg.to == station);
+		let outgoing = self.legs.iter().find(|leg| leg.from == station);
+
+		let vector = |leg: &Leg| match horizontal {
+			true => Position {
+				up: 0.0,
+				..leg.vector
+			},
+			false => leg.vector,
+		};
+
+		let direction = match (incoming, outgoing) {
+			(Some(incoming), Some(outgoing)) => vector(incoming).unit() + vector(outgoing).unit(),
+			(Some(leg), None) | (None, Some(leg)) => vector(leg),
+			(None, None) => Position::default(),
+		};
+
+		direction.unit()
+	}
+
+	// The first of the document's references whose station has a position.
+	// Every export which is georeferenced, and the grid convergence, uses it.
+	pub fn anchor<'a>(&self, document: &'a Document) -> Option<Anchor<'a>> {
+		document.references.iter().find_map(|reference| {
+			let station = reference.station?;
+			let position = self.position(&station)?;
+			let coordinates = Position {
+				east: units::metres(reference.east as f64),
+				north: units::metres(reference.north as f64),
+				up: units::metres(reference.altitude),
+			};
+
+			Some(Anchor {
+				reference,
+				station,
+				offset: coordinates - position,
+			})
+		})
+	}
+}
+
+// The displacement measured by a shot, in metres, with azimuths corrected for
+// the declination of the shot's trip.
+pub fn vector(document: &Document, shot: &Shot) -> Position {
+	corrected_vector(document, shot, &Corrections::default())
+}
+
+// The displacement measured by a shot, in metres, with the corrections applied
+// to its azimuth.
+pub fn corrected_vector(document: &Document, shot: &Shot, corrections: &Corrections) -> Position {
+	let declination = usize::try_from(shot.trip_index)
+		.ok()
+		.and_then(|index| document.trips.get(index))
+		.filter(|_| corrections.declination)
+		.map_or(0, |trip| trip.declination);
+
+	let azimuth = units::degrees(shot.azimuth.wrapping_add(declination));
+	let azimuth = (azimuth - corrections.convergence.unwrap_or(0.0)).to_radians();
+	let inclination = units::degrees(shot.inclination).to_radians();
+	let distance = units::metres(shot.distance);
+
+	let horizontal = distance * inclination.cos();
+
+	Position {
+		east: horizontal * azimuth.sin(),
+		north: horizontal * azimuth.cos(),
+		up: distance * inclination.sin(),
+	}
+}
+
+// Reduces to true north, as PocketTopo does, so that the stations line up with
+// the drawings.
+pub fn reduce(document: &Document) -> Centerline {
+	reduce_with_options(document, &Options::default())
+}
+
+// The convergence is only known once the anchor is, so the shots are reduced
+// to true north and then rotated to grid north.
+pub fn reduce_with_options(document: &Document, options: &Options) -> Centerline {
+	let corrections = Corrections::new(document, None, options);
+
+	// legs, keyed by their stations in the order they were first surveyed
+	let mut legs: Vec<Leg> = Vec::new();
+	let mut keys: HashMap<(StationId, StationId), usize> = HashMap::new();
+	let mut splays = Vec::new();
+
+	for (index, shot) in document.shots.iter().enumerate() {
+		let vector = corrected_vector(document, shot, &corrections);
+
+		match (shot.from, shot.to) {
+			(Some(from), Some(to)) if from != to => {
+				if let Some(leg) = keys.get(&(from, to)) {
+					legs[*leg].shots = legs[*leg].shots.iter().copied().chain([index]).collect();
+					legs[*leg].vector = legs[*leg].vector + vector;
+				} else if let Some(leg) = keys.get(&(to, from)) {
+					legs[*leg].shots = legs[*leg].shots.iter().copied().chain([index]).collect();
+					legs[*leg].vector = legs[*leg].vector - vector;
+				} else {
+					keys.insert((from, to), legs.len());
+					legs.push(Leg {
+						from,
+						to,
+						shots: Box::new([index]),
+						vector,
+					});
+				}
+			}
+			(Some(from), None) => splays.push(Splay {
+				from,
+				shot: index,
+				vector,
+			}),
+			_ => {}
+		}
+	}
+
+	for leg in legs.iter_mut() {
+		leg.vector = leg.vector * (1.0 / leg.shots.len() as f64);
+	}
+
+	let origin = document.shots.iter().find_map(|shot| shot.from);
+
+	let mut stations = BTreeMap::new();
+	if let Some(origin) = origin {
+		stations.insert(origin, Position::default());
+	}
+
+	// position stations outwards from the origin until no more can be reached
+	let mut progress = true;
+	while progress {
+		progress = false;
+
+		for leg in legs.iter() {
+			let from = stations.get(&leg.from).copied();
+			let to = stations.get(&leg.to).copied();
+
+			match (from, to) {
+				(Some(from), None) => {
+					stations.insert(leg.to, from + leg.vector);
+					progress = true;
+				}
+				(None, Some(to)) => {
+					stations.insert(leg.from, to - leg.vector);
+					progress = true;
+				}
+				_ => {}
+			}
+		}
+	}
+
+	let mut centerline = Centerline {
+		origin,
+		stations,
+		legs: legs.into_boxed_slice(),
+		splays: splays.into_boxed_slice(),
+		corrections,
+	};
+
+	let reference = centerline.anchor(document).map(|anchor| anchor.reference);
+	centerline.corrections = Corrections::new(document, reference, options);
+
+	if let Some(convergence) = centerline.corrections.convergence {
+		let rotate = |position: &mut Position| *position = rotate(*position, convergence);
+
+		centerline.stations.values_mut().for_each(rotate);
+		for leg in centerline.legs.iter_mut() {
+			rotate(&mut leg.vector);
+		}
+		for splay in centerline.splays.iter_mut() {
+			rotate(&mut splay.vector);
+		}
+	}
+
+	centerline
+}
+
+// turns a displacement's azimuth anticlockwise by `degrees`
+fn rotate(position: Position, degrees: f64) -> Position {
+	let (sin, cos) = degrees.to_radians().sin_cos();
+
+	Position {
+		east: position.east * cos - position.north * sin,
+		north: position.north * cos + position.east * sin,
+		up: position.up,
+	}
+}
@@ -0,0 +1,337 @@
+use std::io::Write;
+
+use thiserror::Error;
+use tiny_skia::{
+	FillRule, LineCap, LineJoin, Paint, PathBuilder, Pixmap, Rect, Stroke, StrokeDash, Transform,
+};
+
+use crate::{
+	map::{self, Map, Sheet},
+	parser::Document,
+	Element, Point,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Options {
+	pub sheet: Sheet,
+	// pixels per inch of paper
+	pub dpi: f64,
+	// the map scale's denominator, e.g. 500 for 1:500
+	pub scale: f64,
+	// the space around the survey, in mm of paper
+	pub margin: f64,
+	pub labels: bool,
+}
+
+impl Default for Options {
+	fn default() -> Self {
+		Self {
+			sheet: Sheet::default(),
+			dpi: 150.0,
+			scale: 500.0,
+			margin: 5.0,
+			labels: true,
+		}
+	}
+}
+
+#[derive(Debug, Error)]
+pub enum RenderError {
+	#[error("invalid scale or resolution")]
+	InvalidOptions,
+
+	#[error("image too large: {width} × {height} pixels")]
+	TooLarge { width: f64, height: f64 },
+
+	#[error(transparent)]
+	Png(#[from] png::EncodingError),
+}
+
+// line widths and sizes, in mm of paper
+const POLYGON_WIDTH: f64 = 0.25;
+const CENTERLINE_WIDTH: f64 = 0.18;
+const STATION_RADIUS: f64 = 0.35;
+const SECTION_RADIUS: f64 = 1.2;
+const LABEL_HEIGHT: f64 = 1.8;
+
+const CENTERLINE: [u8; 3] = [204, 0, 0];
+const LABELS: [u8; 3] = [0, 0, 0];
+
+// Renders a drawing with its centerline and stations, anti-aliased on white,
+// as an 8-bit RGB PNG. Cross-sections are marked by a circle, joined to their
+// station.
+pub fn write_png<W: Write>(
+	document: &Document,
+	options: &Options,
+	output: W,
+) -> Result<(), RenderError> {
+	let pixmap = render(document, options)?;
+
+	let mut encoder = png::Encoder::new(output, pixmap.width(), pixmap.height());
+	encoder.set_color(png::ColorType::Rgb);
+	encoder.set_depth(png::BitDepth::Eight);
+	encoder.set_pixel_dims(Some(png::PixelDimensions {
+		xppu: (options.dpi / 0.0254).round() as u32,
+		yppu: (options.dpi / 0.0254).round() as u32,
+		unit: png::Unit::Meter,
+	}));
+
+	// the background is opaque, so the premultiplied colours are unchanged
+	let data: Vec<u8> = pixmap
+		.data()
+		.chunks_exact(4)
+		.flat_map(|pixel| &pixel[..3])
+		.copied()
+		.collect();
+
+	let mut writer = encoder.write_header()?;
+	writer.write_image_data(&data)?;
+	writer.finish()?;
+
+	Ok(())
+}
+
+fn render(document: &Document, options: &Options) -> Result<Pixmap, RenderError> {
+	let valid = |value: f64| value.is_finite() && value > 0.0;
+	if !valid(options.dpi) || !valid(options.scale) || !options.margin.is_finite() {
+		return Err(RenderError::InvalidOptions);
+	}
+
+	let sheet = map::map(document, options.sheet);
+
+	let origin = Point { x: 0, y: 0 };
+	let (min, max) = sheet.bounds().unwrap_or((origin, origin));
+
+	let Map {
+		drawing,
+		stations,
+		legs,
+	} = sheet;
+
+	// pixels per mm of paper, and per mm of the survey
+	let paper = options.dpi / 25.4;
+	let survey = paper / options.scale;
+	let margin = options.margin * paper;
+
+	// room for the labels to the right of the stations
+	let labels = match options.labels {
+		true => stations
+			.keys()
+			.map(|station| station.to_string().len())
+			.max(),
+		false => None,
+	};
+	let labels = labels.map_or(0.0, |characters| {
+		(characters * 6 + 2) as f64 * LABEL_HEIGHT / 7.0 * paper
+	});
+
+	let width = ((f64::from(max.x) - f64::from(min.x)) * survey + 2.0 * margin + labels)
+		.ceil()
+		.max(1.0);
+	let height = ((f64::from(max.y) - f64::from(min.y)) * survey + 2.0 * margin)
+		.ceil()
+		.max(1.0);
+
+	let mut pixmap = match width * height <= MAX_PIXELS {
+		true => Pixmap::new(width as u32, height as u32),
+		false => None,
+	}
+	.ok_or(RenderError::TooLarge { width, height })?;
+	pixmap.fill(tiny_skia::Color::WHITE);
+
+	let mut canvas = Canvas {
+		pixmap: &mut pixmap,
+		transform: move |point: Point| {
+			(
+				((f64::from(point.x) - f64::from(min.x)) * survey + margin) as f32,
+				((f64::from(point.y) - f64::from(min.y)) * survey + margin) as f32,
+			)
+		},
+		paper: paper as f32,
+	};
+
+	for element in drawing.elements.iter() {
+		if let Element::Polygon(polygon) = element {
+			canvas.line(&polygon.points, polygon.color.rgb(), POLYGON_WIDTH, false);
+		}
+	}
+
+	for element in drawing.elements.iter() {
+		if let Element::CrossSection(section) = element {
+			if let Some(station) = stations.get(&section.station) {
+				canvas.line(
+					&[*station, section.position],
+					LABELS,
+					CENTERLINE_WIDTH,
+					true,
+				);
+			}
+
+			canvas.circle(section.position, SECTION_RADIUS, LABELS, false);
+		}
+	}
+
+	for (from, to) in legs {
+		canvas.line(&[from, to], CENTERLINE, CENTERLINE_WIDTH, false);
+	}
+
+	for (station, point) in stations.iter() {
+		canvas.circle(*point, STATION_RADIUS, CENTERLINE, true);
+
+		if options.labels {
+			canvas.label(*point, &station.to_string());
+		}
+	}
+
+	Ok(pixmap)
+}
+
+// tiny-skia's limit is larger, but an image this size is a mistake
+const MAX_PIXELS: f64 = 20_000.0 * 20_000.0;
+
+struct Canvas<'a, T: Fn(Point) -> (f32, f32)> {
+	pixmap: &'a mut Pixmap,
+	// from the drawing's mm to pixels
+	transform: T,
+	// pixels per mm of paper
+	paper: f32,
+}
+
+impl<T: Fn(Point) -> (f32, f32)> Canvas<'_, T> {
+	fn line(&mut self, points: &[Point], colour: [u8; 3], width: f64, dashed: bool) {
+		let mut builder = PathBuilder::new();
+
+		for (index, point) in points.iter().enumerate() {
+			let (x, y) = (self.transform)(*point);
+			match index {
+				0 => builder.move_to(x, y),
+				_ => builder.line_to(x, y),
+			}
+		}
+
+		let Some(path) = builder.finish() else {
+			return;
+		};
+
+		let dash = dashed.then(|| StrokeDash::new(vec![self.paper, self.paper], 0.0));
+		let stroke = Stroke {
+			width: (width as f32 * self.paper).max(1.0),
+			line_cap: LineCap::Round,
+			line_join: LineJoin::Round,
+			dash: dash.flatten(),
+			..Stroke::default()
+		};
+
+		self.pixmap
+			.stroke_path(&path, &paint(colour), &stroke, Transform::identity(), None);
+	}
+
+	fn circle(&mut self, centre: Point, radius: f64, colour: [u8; 3], filled: bool) {
+		let (x, y) = (self.transform)(centre);
+		let radius = (radius as f32 * self.paper).max(1.0);
+
+		let Some(path) = PathBuilder::from_circle(x, y, radius) else {
+			return;
+		};
+
+		match filled {
+			true => self.pixmap.fill_path(
+				&path,
+				&paint(colour),
+				FillRule::Winding,
+				Transform::identity(),
+				None,
+			),
+			false => {
+				let stroke = Stroke {
+					width: (CENTERLINE_WIDTH as f32 * self.paper).max(1.0),
+					..Stroke::default()
+				};
+				self.pixmap.stroke_path(
+					&path,
+					&paint(colour),
+					&stroke,
+					Transform::identity(),
+					None,
+				);
+			}
+		}
+	}
+
+	// draws text above and to the right of a point, in a bitmap font which
+	// has the characters of station ids
+	fn label(&mut self, point: Point, text: &str) {
+		let (x, y) = (self.transform)(point);
+		let size = (LABEL_HEIGHT as f32 * self.paper / 7.0).max(1.0);
+		let (x, y) = (x + 2.0 * size, y - 9.0 * size);
+
+		let mut builder = PathBuilder::new();
+
+		for (index, character) in text.chars().enumerate() {
+			let Some(rows) = glyph(character) else {
+				continue;
+			};
+
+			let left = x + index as f32 * 6.0 * size;
+
+			for (row, bits) in rows.iter().enumerate() {
+				for column in 0..5 {
+					if bits & (0b10000 >> column) == 0 {
+						continue;
+					}
+
+					let rect = Rect::from_xywh(
+						left + column as f32 * size,
+						y + row as f32 * size,
+						size,
+						size,
+					);
+
+					if let Some(rect) = rect {
+						builder.push_rect(rect);
+					}
+				}
+			}
+		}
+
+		if let Some(path) = builder.finish() {
+			self.pixmap.fill_path(
+				&path,
+				&paint(LABELS),
+				FillRule::Winding,
+				Transform::identity(),
+				None,
+			);
+		}
+	}
+}
+
+fn paint(colour: [u8; 3]) -> Paint<'static> {
+	let [r, g, b] = colour;
+
+	let mut paint = Paint::default();
+	paint.set_color_rgba8(r, g, b, 255);
+	paint.anti_alias = true;
+	paint
+}
+
+// 5 × 7 pixels, a row to a byte
+fn glyph(character: char) -> Option<[u8; 7]> {
+	let rows = match character {
+		'0' => [0x0E, 0x11, 0x13, 0x15, 0x19, 0x11, 0x0E],
+		'1' => [0x04, 0x0C, 0x04, 0x04, 0x04, 0x04, 0x0E],
+		'2' => [0x0E, 0x11, 0x01, 0x02, 0x04, 0x08, 0x1F],
+		'3' => [0x1F, 0x02, 0x04, 0x02, 0x01, 0x11, 0x0E],
+		'4' => [0x02, 0x06, 0x0A, 0x12, 0x1F, 0x02, 0x02],
+		'5' => [0x1F, 0x10, 0x1E, 0x01, 0x01, 0x11, 0x0E],
+		'6' => [0x06, 0x08, 0x10, 0x1E, 0x11, 0x11, 0x0E],
+		'7' => [0x1F, 0x01, 0x02, 0x04, 0x08, 0x08, 0x08],
+		'8' => [0x0E, 0x11, 0x11, 0x0E, 0x11, 0x11, 0x0E],
+		'9' => [0x0E, 0x11, 0x11, 0x0F, 0x01, 0x02, 0x0C],
+		'.' => [0x00, 0x00, 0x00, 0x00, 0x00, 0x0C, 0x0C],
+		'-' => [0x00, 0x00, 0x00, 0x1F, 0x00, 0x00, 0x00],
+		_ => return None,
+	};
+
+	Some(rows)
+}
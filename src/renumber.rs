@@ -0,0 +1,164 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use thiserror::Error;
+
+use crate::{parser::Document, Element, StationId};
+
+#[derive(Debug, Error, Eq, PartialEq)]
+pub enum RenumberError {
+	#[error("station {0} would be joined with another station")]
+	Collision(StationId),
+
+	#[error("station id out of range: {0}")]
+	OutOfRange(StationId),
+
+	#[error("no station {0}")]
+	UnknownStation(StationId),
+}
+
+// Every station a document refers to, in shots, references and cross-sections.
+pub fn stations(document: &Document) -> BTreeSet<StationId> {
+	let shots = document
+		.shots
+		.iter()
+		.flat_map(|shot| [shot.from, shot.to])
+		.flatten();
+
+	let references = document
+		.references
+		.iter()
+		.filter_map(|reference| reference.station);
+
+	let cross_sections = document
+		.outline
+		.elements
+		.iter()
+		.chain(document.sideview.elements.iter())
+		.filter_map(|element| match element {
+			Element::CrossSection(cross_section) => Some(cross_section.station),
+			_ => None,
+		});
+
+	shots.chain(references).chain(cross_sections).collect()
+}
+
+pub fn rename(
+	document: &mut Document,
+	from: StationId,
+	to: StationId,
+) -> Result<(), RenumberError> {
+	if !stations(document).contains(&from) {
+		return Err(RenumberError::UnknownStation(from));
+	}
+
+	apply(document, &BTreeMap::from([(from, to)]))
+}
+
+// Moves every station of the `from` series to the `to` series, keeping their
+// minor numbers.
+pub fn shift_series(document: &mut Document, from: u16, to: u16) -> Result<(), RenumberError> {
+	let mapping = stations(document)
+		.into_iter()
+		.filter_map(|station| match station {
+			StationId::MajorMinor(major, minor) if major == from => {
+				Some((station, StationId::MajorMinor(to, minor)))
+			}
+			_ => None,
+		})
+		.collect();
+
+	apply(document, &mapping)
+}
+
+// Moves plain stations into the `major` series: `12` becomes `major.12`.
+pub fn plain_to_series(document: &mut Document, major: u16) -> Result<(), RenumberError> {
+	let mapping = stations(document)
+		.into_iter()
+		.filter_map(|station| match station {
+			StationId::Plain(number) => Some(
+				u16::try_from(number)
+					.map(|minor| (station, StationId::MajorMinor(major, minor)))
+					.map_err(|_| RenumberError::OutOfRange(station)),
+			),
+			StationId::MajorMinor(..) => None,
+		})
+		.collect::<Result<_, _>>()?;
+
+	apply(document, &mapping)
+}
+
+// Numbers the stations of the `major` series plainly, from `first`: `major.12`
+// becomes `first + 12`.
+pub fn series_to_plain(
+	document: &mut Document,
+	major: u16,
+	first: u32,
+) -> Result<(), RenumberError> {
+	let mapping = stations(document)
+		.into_iter()
+		.filter_map(|station| match station {
+			StationId::MajorMinor(series, minor) if series == major => Some(
+				first
+					.checked_add(minor.into())
+					.map(|number| (station, StationId::Plain(number)))
+					.ok_or(RenumberError::OutOfRange(station)),
+			),
+			_ => None,
+		})
+		.collect::<Result<_, _>>()?;
+
+	apply(document, &mapping)
+}
+
+// Renames stations according to `mapping`; stations which aren't in it keep
+// their ids. Renaming two stations to the same id, or a station to the id of
+// one which isn't renamed, is an error rather than joining them. The document
+// is unchanged if there's an error.
+pub fn apply(
+	document: &mut Document,
+	mapping: &BTreeMap<StationId, StationId>,
+) -> Result<(), RenumberError> {
+	let stations = stations(document);
+	let station = |station: StationId| mapping.get(&station).copied().unwrap_or(station);
+
+	let mut renamed = BTreeSet::new();
+	for existing in stations.iter() {
+		let renamed_to = station(*existing);
+
+		let in_range = match renamed_to {
+			StationId::MajorMinor(major, _) => major <= StationId::MAX_MAJOR,
+			StationId::Plain(number) => number <= StationId::MAX_PLAIN,
+		};
+
+		if !in_range {
+			return Err(RenumberError::OutOfRange(renamed_to));
+		}
+
+		if !renamed.insert(renamed_to) {
+			return Err(RenumberError::Collision(renamed_to));
+		}
+	}
+
+	for shot in document.shots.iter_mut() {
+		shot.from = shot.from.map(station);
+		shot.to = shot.to.map(station);
+	}
+
+	for reference in document.references.iter_mut() {
+		reference.station = reference.station.map(station);
+	}
+
+	let elements = document
+		.outline
+		.elements
+		.iter_mut()
+		.chain(document.sideview.elements.iter_mut());
+
+	for element in elements {
+		if let Element::CrossSection(cross_section) = element {
+			cross_section.station = station(cross_section.station);
+		}
+	}
+
+	Ok(())
+}
@@ -0,0 +1,420 @@
+use std::{
+	collections::BTreeMap,
+	fmt::Write as _,
+	io::{self, Write},
+};
+
+use crate::{
+	mesh,
+	parser::Document,
+	reduction::{self, North, Position},
+	units, StationId,
+};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Options {
+	// the passage mesh to include, if any, which is reduced to `north`
+	pub passage: Option<mesh::Options>,
+	pub north: North,
+}
+
+// Writes a binary glTF 2.0 scene with nodes for the centerline, splays,
+// stations and optionally the passage walls.
+//
+// The scene is in metres in a local frame with glTF's axes: x east, y up and z
+// south. Its origin is the station of the centerline's anchor, or else the
+// first station. The anchor's coordinates are in the scene's `extras`, so that
+// it can be georeferenced, with the EPSG code and WGS84 latitude and longitude
+// if the document has a CRS, and the corrections the azimuths were reduced
+// with.
+pub fn write_glb<W: Write>(
+	document: &Document,
+	options: &Options,
+	output: &mut W,
+) -> io::Result<()> {
+	let centerline = reduction::reduce_with_options(
+		document,
+		&reduction::Options {
+			north: options.north,
+		},
+	);
+
+	let anchor = centerline.anchor(document);
+	let origin = anchor
+		.and_then(|anchor| centerline.position(&anchor.station))
+		.unwrap_or_default();
+	let local = |position: Position| position - origin;
+
+	let mut scene = Scene::default();
+	let mut nodes = Vec::new();
+
+	let stations: Vec<(StationId, Position)> = centerline
+		.stations
+		.iter()
+		.map(|(station, position)| (*station, local(*position)))
+		.collect();
+
+	// lines between each pair of stations with positions
+	let index: BTreeMap<StationId, u32> = stations
+		.iter()
+		.zip(0..)
+		.map(|((station, _), index)| (*station, index))
+		.collect();
+	let indices: Vec<u32> = centerline
+		.legs
+		.iter()
+		.filter_map(|leg| Some([*index.get(&leg.from)?, *index.get(&leg.to)?]))
+		.flatten()
+		.collect();
+
+	if !indices.is_empty() {
+		let positions: Vec<Position> = stations.iter().map(|(_, position)| *position).collect();
+		let positions = scene.positions(&positions)?;
+		let indices = scene.indices(&indices);
+		let mesh = scene.mesh("centerline", LINES, positions, Some(indices), CENTERLINE);
+		nodes.push(scene.node(&format!(r#""name":"centerline","mesh":{mesh}"#)));
+	}
+
+	let splays: Vec<Position> = centerline
+		.splays
+		.iter()
+		.filter_map(|splay| {
+			let from = local(centerline.position(&splay.from)?);
+			Some([from, from + splay.vector])
+		})
+		.flatten()
+		.collect();
+
+	if !splays.is_empty() {
+		let positions = scene.positions(&splays)?;
+		let mesh = scene.mesh("splays", LINES, positions, None, SPLAYS);
+		nodes.push(scene.node(&format!(r#""name":"splays","mesh":{mesh}"#)));
+	}
+
+	// a node for each station, sharing a point at its origin as a marker
+	if !stations.is_empty() {
+		let positions = scene.positions(&[Position::default()])?;
+		let marker = scene.mesh("station", POINTS, positions, None, CENTERLINE);
+
+		let children = stations
+			.iter()
+			.map(|(station, position)| {
+				let [x, y, z] = coordinates(*position);
+				Ok(scene.node(&format!(
+					r#""name":{},"mesh":{marker},"translation":[{},{},{}]"#,
+					string(&station.to_string()),
+					finite(x)?,
+					finite(y)?,
+					finite(z)?,
+				)))
+			})
+			.collect::<io::Result<Vec<usize>>>()?;
+
+		nodes.push(scene.node(&format!(
+			r#""name":"stations","children":{}"#,
+			list(&children)
+		)));
+	}
+
+	if let Some(passage) = options.passage {
+		let passage = mesh::mesh(
+			document,
+			&mesh::Options {
+				north: options.north,
+				..passage
+			},
+		);
+
+		if !passage.triangles.is_empty() {
+			let vertices: Vec<Position> = passage
+				.vertices
+				.iter()
+				.map(|vertex| local(*vertex))
+				.collect();
+			let indices: Vec<u32> = passage.triangles.iter().flatten().copied().collect();
+
+			let positions = scene.positions(&vertices)?;
+			let indices = scene.indices(&indices);
+			let mesh = scene.mesh("passage", TRIANGLES, positions, Some(indices), PASSAGE);
+			nodes.push(scene.node(&format!(r#""name":"passage","mesh":{mesh}"#)));
+		}
+	}
+
+	// how the scene is aligned with the reference's coordinates
+	let corrections = centerline.corrections;
+	let north = match corrections.north {
+		North::Magnetic => "magnetic",
+		North::True => "true",
+		North::Grid => "grid",
+	};
+	let mut extras = format!(
+		r#""reduction":{{"north":"{north}","declination":{}"#,
+		corrections.declination
+	);
+	if let Some(convergence) = corrections.convergence {
+		let _ = write!(extras, r#","convergence":{}"#, finite(convergence)?);
+	}
+	extras.push('}');
+
+	if let Some(anchor) = anchor {
+		let reference = anchor.reference;
+		let mut crs = String::new();
+		if let Some(system) = document.crs {
+			let (position, _) = system.reference(reference);
+			let _ = write!(
+				crs,
+				r#","crs":"EPSG:{}","latitude":{},"longitude":{}"#,
+				system.epsg(),
+				finite(position.latitude)?,
+				finite(position.longitude)?,
+			);
+		}
+
+		let _ = write!(
+			extras,
+			r#","reference":{{"station":{},"east":{},"north":{},"altitude":{}{crs}}}"#,
+			string(&anchor.station.to_string()),
+			units::metres(reference.east as f64),
+			units::metres(reference.north as f64),
+			units::metres(reference.altitude),
+		);
+	}
+
+	scene.write(
+		&format!(r#"{{"nodes":{},"extras":{{{extras}}}}}"#, list(&nodes)),
+		output,
+	)
+}
+
+// primitive modes
+const POINTS: u32 = 0;
+const LINES: u32 = 1;
+const TRIANGLES: u32 = 4;
+
+// materials' base colours
+const CENTERLINE: [f32; 4] = [0.9, 0.1, 0.1, 1.0];
+const SPLAYS: [f32; 4] = [0.6, 0.6, 0.6, 1.0];
+const PASSAGE: [f32; 4] = [0.8, 0.75, 0.65, 1.0];
+
+// The JSON objects of each of the glTF's arrays, and its binary buffer.
+#[derive(Default)]
+struct Scene {
+	nodes: Vec<String>,
+	meshes: Vec<String>,
+	materials: Vec<[f32; 4]>,
+	accessors: Vec<String>,
+	buffer_views: Vec<String>,
+	buffer: Vec<u8>,
+}
+
+impl Scene {
+	fn node(&mut self, node: &str) -> usize {
+		self.nodes.push(format!("{{{node}}}"));
+		self.nodes.len() - 1
+	}
+
+	fn mesh(
+		&mut self,
+		name: &str,
+		mode: u32,
+		positions: usize,
+		indices: Option<usize>,
+		colour: [f32; 4],
+	) -> usize {
+		let material = match self
+			.materials
+			.iter()
+			.position(|material| *material == colour)
+		{
+			Some(material) => material,
+			None => {
+				self.materials.push(colour);
+				self.materials.len() - 1
+			}
+		};
+
+		let indices = indices.map_or(String::new(), |indices| format!(r#","indices":{indices}"#));
+
+		self.meshes.push(format!(
+			r#"{{"name":{},"primitives":[{{"attributes":{{"POSITION":{positions}}}{indices},"mode":{mode},"material":{material}}}]}}"#,
+			string(name)
+		));
+		self.meshes.len() - 1
+	}
+
+	fn positions(&mut self, positions: &[Position]) -> io::Result<usize> {
+		let coordinates: Vec<[f32; 3]> = positions
+			.iter()
+			.map(|position| coordinates(*position))
+			.collect();
+
+		for value in coordinates.iter().flatten() {
+			finite(*value)?;
+		}
+
+		let mut min = [f32::INFINITY; 3];
+		let mut max = [f32::NEG_INFINITY; 3];
+		for point in coordinates.iter() {
+			for axis in 0..3 {
+				min[axis] = min[axis].min(point[axis]);
+				max[axis] = max[axis].max(point[axis]);
+			}
+		}
+
+		let bytes: Vec<u8> = coordinates
+			.iter()
+			.flatten()
+			.flat_map(|value| value.to_le_bytes())
+			.collect();
+		let view = self.buffer_view(&bytes, ARRAY_BUFFER);
+
+		self.accessors.push(format!(
+			r#"{{"bufferView":{view},"componentType":{FLOAT},"count":{},"type":"VEC3","min":{},"max":{}}}"#,
+			positions.len(),
+			list(&min),
+			list(&max),
+		));
+		Ok(self.accessors.len() - 1)
+	}
+
+	fn indices(&mut self, indices: &[u32]) -> usize {
+		let bytes: Vec<u8> = indices
+			.iter()
+			.flat_map(|index| index.to_le_bytes())
+			.collect();
+		let view = self.buffer_view(&bytes, ELEMENT_ARRAY_BUFFER);
+
+		self.accessors.push(format!(
+			r#"{{"bufferView":{view},"componentType":{UNSIGNED_INT},"count":{},"type":"SCALAR"}}"#,
+			indices.len(),
+		));
+		self.accessors.len() - 1
+	}
+
+	// every component is 4 bytes, so views stay aligned
+	fn buffer_view(&mut self, bytes: &[u8], target: u32) -> usize {
+		self.buffer_views.push(format!(
+			r#"{{"buffer":0,"byteOffset":{},"byteLength":{},"target":{target}}}"#,
+			self.buffer.len(),
+			bytes.len(),
+		));
+		self.buffer.extend_from_slice(bytes);
+		self.buffer_views.len() - 1
+	}
+
+	fn write<W: Write>(self, scene: &str, output: &mut W) -> io::Result<()> {
+		let materials: Vec<String> = self
+			.materials
+			.iter()
+			.map(|colour| {
+				format!(
+					r#"{{"pbrMetallicRoughness":{{"baseColorFactor":{},"metallicFactor":0}}}}"#,
+					list(colour)
+				)
+			})
+			.collect();
+
+		let mut json = format!(
+			r#"{{"asset":{{"version":"2.0","generator":"pocket-topo"}},"scene":0,"scenes":[{scene}],"nodes":[{}]"#,
+			self.nodes.join(",")
+		);
+
+		// glTF doesn't allow empty arrays
+		for (name, array) in [
+			("meshes", self.meshes),
+			("materials", materials),
+			("accessors", self.accessors),
+			("bufferViews", self.buffer_views),
+		] {
+			if !array.is_empty() {
+				let _ = write!(json, r#","{name}":[{}]"#, array.join(","));
+			}
+		}
+
+		if !self.buffer.is_empty() {
+			let _ = write!(
+				json,
+				r#","buffers":[{{"byteLength":{}}}]"#,
+				self.buffer.len()
+			);
+		}
+
+		json.push('}');
+
+		let mut json = json.into_bytes();
+		json.resize(json.len().next_multiple_of(4), b' ');
+
+		let mut buffer = self.buffer;
+		buffer.resize(buffer.len().next_multiple_of(4), 0);
+
+		let mut length = 12 + 8 + json.len();
+		if !buffer.is_empty() {
+			length += 8 + buffer.len();
+		}
+
+		output.write_all(b"glTF")?;
+		output.write_all(&2_u32.to_le_bytes())?;
+		output.write_all(&(length as u32).to_le_bytes())?;
+
+		output.write_all(&(json.len() as u32).to_le_bytes())?;
+		output.write_all(b"JSON")?;
+		output.write_all(&json)?;
+
+		if !buffer.is_empty() {
+			output.write_all(&(buffer.len() as u32).to_le_bytes())?;
+			output.write_all(b"BIN\0")?;
+			output.write_all(&buffer)?;
+		}
+
+		Ok(())
+	}
+}
+
+// buffer view targets and component types
+const ARRAY_BUFFER: u32 = 34962;
+const ELEMENT_ARRAY_BUFFER: u32 = 34963;
+const FLOAT: u32 = 5126;
+const UNSIGNED_INT: u32 = 5125;
+
+fn coordinates(position: Position) -> [f32; 3] {
+	[
+		position.east as f32,
+		position.up as f32,
+		-position.north as f32,
+	]
+}
+
+// a number for the JSON, which has no NaN or infinities
+fn finite<T: Copy + Into<f64>>(value: T) -> io::Result<T> {
+	match value.into().is_finite() {
+		true => Ok(value),
+		false => Err(io::Error::new(
+			io::ErrorKind::InvalidData,
+			format!("{} isn't a finite number", value.into()),
+		)),
+	}
+}
+
+fn list<T: ToString>(values: &[T]) -> String {
+	let values: Vec<String> = values.iter().map(ToString::to_string).collect();
+	format!("[{}]", values.join(","))
+}
+
+// a JSON string
+fn string(value: &str) -> String {
+	let mut string = String::from('"');
+
+	for character in value.chars() {
+		match character {
+			'"' => string.push_str("\\\""),
+			'\\' => string.push_str("\\\\"),
+			character if character.is_control() => {
+				let _ = write!(string, "\\u{:04x}", character as u32);
+			}
+			character => string.push(character),
+		}
+	}
+
+	string.push('"');
+	string
+}
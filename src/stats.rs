@@ -0,0 +1,241 @@
+use std::collections::BTreeSet;
+
+use chrono::NaiveDate;
+
+use crate::{
+	parser::Document,
+	reduction::{self, Centerline, Leg, Splay},
+	StationId,
+};
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Report {
+	pub document: Statistics,
+	// by trip index; shots without a trip only count towards the document
+	pub trips: Vec<Statistics>,
+}
+
+// Lengths are in metres. Repeated shots of a leg count as one leg.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Statistics {
+	pub length: f64,
+	pub stations: usize,
+	pub legs: usize,
+	pub splays: usize,
+	// the greatest horizontal distance between two stations
+	pub horizontal_extent: f64,
+	// the station heights are relative to: the first referenced station, or
+	// the first station if there are no references
+	pub datum: Option<StationId>,
+	pub highest: Option<Extreme>,
+	pub deepest: Option<Extreme>,
+	pub vertical_range: f64,
+	pub longest_leg: Option<LongestLeg>,
+	pub first_date: Option<NaiveDate>,
+	pub last_date: Option<NaiveDate>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Extreme {
+	pub station: StationId,
+	pub height: f64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct LongestLeg {
+	pub from: StationId,
+	pub to: StationId,
+	pub length: f64,
+}
+
+// Stations which aren't connected to the first station have no position, so
+// they're counted but don't contribute to the extent or vertical range.
+pub fn statistics(document: &Document) -> Report {
+	let centerline = reduction::reduce(document);
+
+	let datum = document
+		.references
+		.iter()
+		.filter_map(|reference| reference.station)
+		.find(|station| centerline.stations.contains_key(station))
+		.or(centerline.origin);
+
+	let dates = document.trips.iter().map(|trip| trip.time.date());
+	let mut statistics = compute(
+		&centerline,
+		datum,
+		centerline.legs.iter(),
+		centerline.splays.iter(),
+	);
+	statistics.first_date = dates.clone().min();
+	statistics.last_date = dates.max();
+
+	let trips = document.trips.iter().enumerate().map(|(index, trip)| {
+		let in_trip = |shot: usize| document.shots[shot].trip_index as isize == index as isize;
+
+		let legs = centerline.legs.iter().filter(|leg| in_trip(leg.shots[0]));
+		let splays = centerline.splays.iter().filter(|splay| in_trip(splay.shot));
+
+		let mut statistics = compute(&centerline, datum, legs, splays);
+		statistics.first_date = Some(trip.time.date());
+		statistics.last_date = Some(trip.time.date());
+
+		statistics
+	});
+
+	Report {
+		document: statistics,
+		trips: trips.collect(),
+	}
+}
+
+fn compute<'a>(
+	centerline: &Centerline,
+	datum: Option<StationId>,
+	legs: impl Iterator<Item = &'a Leg>,
+	splays: impl Iterator<Item = &'a Splay>,
+) -> Statistics {
+	let mut statistics = Statistics {
+		datum,
+		..Statistics::default()
+	};
+
+	let mut stations = BTreeSet::new();
+
+	for leg in legs {
+		let length = leg.vector.length();
+
+		statistics.length += length;
+		statistics.legs += 1;
+		stations.extend([leg.from, leg.to]);
+
+		if statistics
+			.longest_leg
+			.is_none_or(|longest| length > longest.length)
+		{
+			statistics.longest_leg = Some(LongestLeg {
+				from: leg.from,
+				to: leg.to,
+				length,
+			});
+		}
+	}
+
+	for splay in splays {
+		statistics.splays += 1;
+		stations.insert(splay.from);
+	}
+
+	statistics.stations = stations.len();
+
+	let positions: Vec<_> = stations
+		.iter()
+		.filter_map(|station| Some((*station, centerline.position(station)?)))
+		.collect();
+
+	let points = positions
+		.iter()
+		.map(|(_, position)| (position.east, position.north));
+	statistics.horizontal_extent = diameter(points.collect());
+
+	let datum = datum
+		.and_then(|datum| centerline.position(&datum))
+		.map_or(0.0, |datum| datum.up);
+
+	for (station, position) in positions.iter() {
+		let extreme = Extreme {
+			station: *station,
+			height: position.up - datum,
+		};
+
+		if statistics
+			.highest
+			.is_none_or(|highest| extreme.height > highest.height)
+		{
+			statistics.highest = Some(extreme);
+		}
+
+		if statistics
+			.deepest
+			.is_none_or(|deepest| extreme.height < deepest.height)
+		{
+			statistics.deepest = Some(extreme);
+		}
+	}
+
+	if let (Some(highest), Some(deepest)) = (statistics.highest, statistics.deepest) {
+		statistics.vertical_range = highest.height - deepest.height;
+	}
+
+	statistics
+}
+
+// The greatest distance between two points, from rotating calipers around
+// their convex hull.
+fn diameter(mut points: Vec<(f64, f64)>) -> f64 {
+	let hull = convex_hull(&mut points);
+	let distance = |a: (f64, f64), b: (f64, f64)| (a.0 - b.0).hypot(a.1 - b.1);
+
+	match hull.len() {
+		0 | 1 => return 0.0,
+		2 => return distance(hull[0], hull[1]),
+		_ => {}
+	}
+
+	// twice the area of the triangle of an edge and a point, which is the
+	// point's distance from the edge's line scaled by the edge's length
+	let area = |a: (f64, f64), b: (f64, f64), c: (f64, f64)| cross(a, b, c).abs();
+
+	let mut diameter: f64 = 0.0;
+	let mut far = 1;
+
+	for (index, &a) in hull.iter().enumerate() {
+		let b = hull[(index + 1) % hull.len()];
+
+		// the point furthest from the edge, which only moves forwards
+		while area(a, b, hull[(far + 1) % hull.len()]) > area(a, b, hull[far]) {
+			far = (far + 1) % hull.len();
+		}
+
+		diameter = diameter
+			.max(distance(a, hull[far]))
+			.max(distance(b, hull[far]));
+	}
+
+	diameter
+}
+
+// Andrew's monotone chain, anticlockwise without collinear points.
+fn convex_hull(points: &mut [(f64, f64)]) -> Vec<(f64, f64)> {
+	points.sort_by(|a, b| a.0.total_cmp(&b.0).then(a.1.total_cmp(&b.1)));
+
+	let chain = |points: &mut dyn Iterator<Item = &(f64, f64)>| {
+		let mut chain: Vec<(f64, f64)> = Vec::new();
+		for &point in points {
+			while chain.len() >= 2
+				&& cross(chain[chain.len() - 2], chain[chain.len() - 1], point) <= 0.0
+			{
+				chain.pop();
+			}
+			chain.push(point);
+		}
+
+		// each chain's last point is the other's first
+		chain.pop();
+		chain
+	};
+
+	let mut hull = chain(&mut points.iter());
+	hull.extend(chain(&mut points.iter().rev()));
+	hull
+}
+
+// the z component of the cross product of `a` to `b` and `a` to `c`, positive
+// if `c` is to the left of `a` to `b`
+fn cross(a: (f64, f64), b: (f64, f64), c: (f64, f64)) -> f64 {
+	(b.0 - a.0) * (c.1 - a.1) - (b.1 - a.1) * (c.0 - a.0)
+}
@@ -0,0 +1,536 @@
+use std::io::{self, Write};
+
+use thiserror::Error;
+
+use crate::{
+	geodesy::Crs,
+	parser::Document,
+	reduction::{self, Centerline, Position},
+	StationId,
+};
+
+// the EPSG code of WGS84 latitude and longitude, which global elevation models
+// such as SRTM are usually in
+const WGS84: u16 = 4326;
+
+// A digital elevation model: a grid of surface altitudes in metres, each the
+// value at the centre of its cell, with rows from north to south.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Dem {
+	pub columns: usize,
+	pub rows: usize,
+	// the coordinates of the grid's north-west corner, in metres or degrees
+	pub west: f64,
+	pub north: f64,
+	pub cell_width: f64,
+	pub cell_height: f64,
+	// the coordinate system, if the file records one
+	pub epsg: Option<u16>,
+	// NaN where there's no data
+	altitudes: Vec<f32>,
+}
+
+#[derive(Debug, Error, PartialEq)]
+pub enum DemError {
+	#[error("invalid grid header: {0:?}")]
+	InvalidHeader(String),
+
+	#[error("no {0} in grid header")]
+	MissingHeader(&'static str),
+
+	#[error("invalid grid value: {0:?}")]
+	InvalidValue(String),
+
+	#[error("expected {expected} grid values, found {found}")]
+	WrongSize { expected: usize, found: usize },
+
+	#[error("GeoTIFF isn't georeferenced")]
+	NotGeoreferenced,
+
+	#[error("unsupported GeoTIFF: {0}")]
+	Unsupported(&'static str),
+
+	#[error("invalid GeoTIFF: {0}")]
+	Tiff(String),
+}
+
+#[derive(Debug, Error, PartialEq)]
+pub enum SurfaceError {
+	#[error("no reference with a surveyed station")]
+	NoReference,
+
+	#[error("the elevation model is in latitude and longitude, so the references need a CRS")]
+	NoCrs,
+
+	#[error("the elevation model is in EPSG:{model}, but the references are in EPSG:{references}")]
+	MismatchedCrs { model: u16, references: u16 },
+}
+
+impl Dem {
+	// Parses an ESRI ASCII grid, or a GeoTIFF with the `geotiff` feature.
+	pub fn parse(contents: &[u8]) -> Result<Self, DemError> {
+		if contents.starts_with(b"II*\0") || contents.starts_with(b"MM\0*") {
+			return Self::parse_geotiff(contents);
+		}
+
+		let contents = std::str::from_utf8(contents)
+			.map_err(|_| DemError::InvalidHeader("not UTF-8".to_owned()))?;
+		Self::parse_ascii(contents)
+	}
+
+	// Parses an ESRI ASCII grid: a header of keys and values, such as `ncols`
+	// and `cellsize`, followed by the altitudes row by row from the north.
+	pub fn parse_ascii(contents: &str) -> Result<Self, DemError> {
+		let mut tokens = contents.split_whitespace().peekable();
+
+		let (mut columns, mut rows, mut cell_width, mut cell_height) = (None, None, None, None);
+		let (mut west, mut south, mut centre) = (None, None, false);
+		let mut nodata = None;
+
+		while let Some(key) = tokens.next_if(|token| token.starts_with(char::is_alphabetic)) {
+			let value = tokens
+				.next()
+				.ok_or_else(|| DemError::InvalidHeader(key.to_owned()))?;
+			let number = || {
+				value
+					.parse::<f64>()
+					.map_err(|_| DemError::InvalidHeader(format!("{key} {value}")))
+			};
+			let count = || {
+				value
+					.parse::<usize>()
+					.map_err(|_| DemError::InvalidHeader(format!("{key} {value}")))
+			};
+
+			match key.to_ascii_lowercase().as_str() {
+				"ncols" => columns = Some(count()?),
+				"nrows" => rows = Some(count()?),
+				"xllcorner" => west = Some(number()?),
+				"yllcorner" => south = Some(number()?),
+				"xllcenter" => (west, centre) = (Some(number()?), true),
+				"yllcenter" => (south, centre) = (Some(number()?), true),
+				"cellsize" => (cell_width, cell_height) = (Some(number()?), Some(number()?)),
+				"dx" => cell_width = Some(number()?),
+				"dy" => cell_height = Some(number()?),
+				"nodata_value" => nodata = Some(number()?),
+				_ => return Err(DemError::InvalidHeader(key.to_owned())),
+			}
+		}
+
+		let columns = columns.ok_or(DemError::MissingHeader("ncols"))?;
+		let rows = rows.ok_or(DemError::MissingHeader("nrows"))?;
+		let mut west = west.ok_or(DemError::MissingHeader("xllcorner"))?;
+		let mut south = south.ok_or(DemError::MissingHeader("yllcorner"))?;
+		let cell_width = cell_width.ok_or(DemError::MissingHeader("cellsize"))?;
+		let cell_height = cell_height.ok_or(DemError::MissingHeader("cellsize"))?;
+
+		// the lower left cell's centre rather than its corner
+		if centre {
+			west -= cell_width / 2.0;
+			south -= cell_height / 2.0;
+		}
+
+		let altitudes = tokens
+			.map(|token| match token.parse::<f64>() {
+				Ok(altitude) if Some(altitude) == nodata => Ok(f32::NAN),
+				Ok(altitude) => Ok(altitude as f32),
+				Err(_) => Err(DemError::InvalidValue(token.to_owned())),
+			})
+			.collect::<Result<Vec<f32>, DemError>>()?;
+
+		Self::new(
+			columns,
+			rows,
+			[west, south + rows as f64 * cell_height],
+			[cell_width, cell_height],
+			None,
+			altitudes,
+		)
+	}
+
+	#[cfg(not(feature = "geotiff"))]
+	fn parse_geotiff(_: &[u8]) -> Result<Self, DemError> {
+		Err(DemError::Unsupported("built without the geotiff feature"))
+	}
+
+	// Parses a single band GeoTIFF, georeferenced by a tie point and pixel scale.
+	#[cfg(feature = "geotiff")]
+	fn parse_geotiff(contents: &[u8]) -> Result<Self, DemError> {
+		use tiff::{
+			decoder::{Decoder, DecodingResult},
+			tags::Tag,
+			ColorType,
+		};
+
+		// GeoTIFF's keys
+		const RASTER_TYPE: u16 = 1025;
+		const PIXEL_IS_POINT: u16 = 2;
+		const GEOGRAPHIC_TYPE: u16 = 2048;
+		const PROJECTED_TYPE: u16 = 3072;
+
+		let error = |error: tiff::TiffError| DemError::Tiff(error.to_string());
+		let mut decoder = Decoder::new(io::Cursor::new(contents)).map_err(error)?;
+
+		let (columns, rows) = decoder.dimensions().map_err(error)?;
+		if !matches!(decoder.colortype().map_err(error)?, ColorType::Gray(_)) {
+			return Err(DemError::Unsupported("more than one band"));
+		}
+
+		let scale = decoder.find_tag(Tag::ModelPixelScaleTag).map_err(error)?;
+		let tiepoint = decoder.find_tag(Tag::ModelTiepointTag).map_err(error)?;
+		let (Some(scale), Some(tiepoint)) = (scale, tiepoint) else {
+			return Err(DemError::NotGeoreferenced);
+		};
+		let scale = scale.into_f64_vec().map_err(error)?;
+		let tiepoint = tiepoint.into_f64_vec().map_err(error)?;
+		let ([cell_width, cell_height, ..], [column, row, _, x, y, ..]) =
+			(&scale[..], &tiepoint[..])
+		else {
+			return Err(DemError::NotGeoreferenced);
+		};
+
+		// the directory's header, then entries of key, location, count and value
+		let keys = match decoder.find_tag(Tag::GeoKeyDirectoryTag).map_err(error)? {
+			Some(keys) => keys.into_u16_vec().map_err(error)?,
+			None => Vec::new(),
+		};
+		let key = |id: u16| {
+			keys.chunks_exact(4)
+				.skip(1)
+				.find(|entry| entry[0] == id && entry[1] == 0)
+				.map(|entry| entry[3])
+		};
+
+		let epsg = key(PROJECTED_TYPE)
+			.or(key(GEOGRAPHIC_TYPE))
+			.filter(|code| (1..32767).contains(code));
+
+		// the tie point is the centre of a cell rather than its corner
+		let (mut column, mut row) = (*column, *row);
+		if key(RASTER_TYPE) == Some(PIXEL_IS_POINT) {
+			column += 0.5;
+			row += 0.5;
+		}
+
+		let nodata = match decoder.find_tag(Tag::GdalNodata).map_err(error)? {
+			Some(nodata) => nodata.into_string().map_err(error)?.trim().parse().ok(),
+			None => None,
+		};
+
+		let altitudes: Vec<f64> = match decoder.read_image().map_err(error)? {
+			DecodingResult::U8(values) => values.into_iter().map(f64::from).collect(),
+			DecodingResult::U16(values) => values.into_iter().map(f64::from).collect(),
+			DecodingResult::U32(values) => values.into_iter().map(f64::from).collect(),
+			DecodingResult::I8(values) => values.into_iter().map(f64::from).collect(),
+			DecodingResult::I16(values) => values.into_iter().map(f64::from).collect(),
+			DecodingResult::I32(values) => values.into_iter().map(f64::from).collect(),
+			DecodingResult::F32(values) => values.into_iter().map(f64::from).collect(),
+			DecodingResult::F64(values) => values,
+			DecodingResult::U64(_) | DecodingResult::I64(_) => {
+				return Err(DemError::Unsupported("64 bit integer altitudes"));
+			}
+		};
+
+		let (columns, rows) = (columns as usize, rows as usize);
+
+		let altitudes = altitudes
+			.into_iter()
+			.map(|altitude| match Some(altitude) == nodata {
+				true => f32::NAN,
+				false => altitude as f32,
+			})
+			.collect();
+
+		Self::new(
+			columns,
+			rows,
+			[x - column * cell_width, y + row * cell_height],
+			[*cell_width, *cell_height],
+			epsg,
+			altitudes,
+		)
+	}
+
+	fn new(
+		columns: usize,
+		rows: usize,
+		[west, north]: [f64; 2],
+		[cell_width, cell_height]: [f64; 2],
+		epsg: Option<u16>,
+		altitudes: Vec<f32>,
+	) -> Result<Self, DemError> {
+		let expected = columns
+			.checked_mul(rows)
+			.ok_or_else(|| DemError::InvalidHeader(format!("{columns} by {rows} cells")))?;
+
+		if expected != altitudes.len() {
+			return Err(DemError::WrongSize {
+				expected,
+				found: altitudes.len(),
+			});
+		}
+
+		let valid = |value: f64| value.is_finite() && value > 0.0;
+		if !valid(cell_width) || !valid(cell_height) {
+			return Err(DemError::InvalidHeader(format!(
+				"cell size {cell_width} by {cell_height}"
+			)));
+		}
+
+		Ok(Self {
+			columns,
+			rows,
+			west,
+			north,
+			cell_width,
+			cell_height,
+			epsg,
+			altitudes,
+		})
+	}
+
+	// The altitude at the cell `column` east and `row` south of the
+	// north-west cell.
+	pub fn get(&self, column: usize, row: usize) -> Option<f64> {
+		if column >= self.columns || row >= self.rows {
+			return None;
+		}
+
+		let altitude = self.altitudes[row * self.columns + column];
+		(!altitude.is_nan()).then_some(f64::from(altitude))
+	}
+
+	// The altitude at a point, interpolated bilinearly between the centres
+	// of the cells around it. Points outside the grid, or next to cells
+	// without data, have the nearest cell's altitude if they're within it.
+	pub fn altitude(&self, x: f64, y: f64) -> Option<f64> {
+		// in cells, from the north-west cell's centre
+		let column = (x - self.west) / self.cell_width - 0.5;
+		let row = (self.north - y) / self.cell_height - 0.5;
+
+		let inside = |value: f64, count: usize| (-0.5..count as f64 - 0.5).contains(&value);
+		if !inside(column, self.columns) || !inside(row, self.rows) {
+			return None;
+		}
+
+		let clamp = |value: f64, count: usize| value.clamp(0.0, (count - 1) as f64);
+		let (column, row) = (clamp(column, self.columns), clamp(row, self.rows));
+
+		let (left, top) = (column.floor() as usize, row.floor() as usize);
+		let (right, bottom) = (column.ceil() as usize, row.ceil() as usize);
+		let (across, down) = (column - left as f64, row - top as f64);
+
+		let corners = [
+			self.get(left, top),
+			self.get(right, top),
+			self.get(left, bottom),
+			self.get(right, bottom),
+		];
+
+		match corners {
+			[Some(a), Some(b), Some(c), Some(d)] => {
+				let top = a + (b - a) * across;
+				let bottom = c + (d - c) * across;
+				Some(top + (bottom - top) * down)
+			}
+			_ => self.get(column.round() as usize, row.round() as usize),
+		}
+	}
+}
+
+// A station's position in the references' coordinates, in metres east, north
+// and above sea level, and the surface above it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Cover {
+	pub station: StationId,
+	pub position: Position,
+	// the altitude of the surface, if the model covers the station
+	pub surface: Option<f64>,
+}
+
+impl Cover {
+	// the thickness of rock above the station, negative above the surface
+	pub fn thickness(&self) -> Option<f64> {
+		Some(self.surface? - self.position.up)
+	}
+}
+
+// A point on the centerline and the surface above it, for a profile.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ProfilePoint {
+	// the leg the point is on
+	pub from: StationId,
+	pub to: StationId,
+	// the horizontal distance along the legs so far, in metres
+	pub distance: f64,
+	pub position: Position,
+	pub surface: Option<f64>,
+}
+
+impl ProfilePoint {
+	pub fn thickness(&self) -> Option<f64> {
+		Some(self.surface? - self.position.up)
+	}
+}
+
+// The reduced centerline, placed in the references' coordinates by its
+// anchor, and how to find positions in the model.
+struct Placement {
+	centerline: Centerline,
+	offset: Position,
+	// the CRS to convert positions with, if the model is in latitude and
+	// longitude
+	geographic: Option<Crs>,
+}
+
+impl Placement {
+	fn new(
+		document: &Document,
+		dem: &Dem,
+		options: &reduction::Options,
+	) -> Result<Self, SurfaceError> {
+		let geographic = match (dem.epsg, document.crs) {
+			(Some(WGS84), Some(crs)) => Some(crs),
+			(Some(WGS84), None) => return Err(SurfaceError::NoCrs),
+			(Some(model), Some(crs)) if model != crs.epsg() => {
+				return Err(SurfaceError::MismatchedCrs {
+					model,
+					references: crs.epsg(),
+				});
+			}
+			_ => None,
+		};
+
+		let centerline = reduction::reduce_with_options(document, options);
+
+		let offset = centerline
+			.anchor(document)
+			.map(|anchor| anchor.offset)
+			.ok_or(SurfaceError::NoReference)?;
+
+		Ok(Self {
+			centerline,
+			offset,
+			geographic,
+		})
+	}
+
+	fn surface(&self, dem: &Dem, position: Position) -> Option<f64> {
+		match self.geographic {
+			Some(crs) => {
+				let position = crs.to_wgs84(position.east, position.north);
+				dem.altitude(position.longitude, position.latitude)
+			}
+			None => dem.altitude(position.east, position.north),
+		}
+	}
+}
+
+// The cover above each station connected to a reference, in station order.
+// The references and the model must be in the same coordinate system, unless
+// the model is in WGS84 latitude and longitude and the document has a CRS.
+pub fn cover(
+	document: &Document,
+	dem: &Dem,
+	options: &reduction::Options,
+) -> Result<Vec<Cover>, SurfaceError> {
+	let placement = Placement::new(document, dem, options)?;
+
+	Ok(placement
+		.centerline
+		.stations
+		.iter()
+		.map(|(station, position)| {
+			let position = *position + placement.offset;
+
+			Cover {
+				station: *station,
+				position,
+				surface: placement.surface(dem, position),
+			}
+		})
+		.collect())
+}
+
+// The stations under the model, from the least cover to the most.
+pub fn shallowest(cover: &[Cover]) -> Vec<Cover> {
+	let mut shallowest: Vec<Cover> = cover
+		.iter()
+		.filter(|cover| cover.thickness().is_some())
+		.copied()
+		.collect();
+
+	shallowest.sort_by(|a, b| a.thickness().partial_cmp(&b.thickness()).unwrap());
+	shallowest
+}
+
+// Points along each leg in the order they were surveyed, at most `step`
+// metres apart horizontally, and the surface above them.
+pub fn profile(
+	document: &Document,
+	dem: &Dem,
+	options: &reduction::Options,
+	step: f64,
+) -> Result<Vec<ProfilePoint>, SurfaceError> {
+	let placement = Placement::new(document, dem, options)?;
+	let centerline = &placement.centerline;
+
+	let mut points = Vec::new();
+	let mut distance = 0.0;
+
+	for leg in centerline.legs.iter() {
+		let (Some(from), Some(to)) = (centerline.position(&leg.from), centerline.position(&leg.to))
+		else {
+			continue;
+		};
+
+		let length = (to - from).horizontal_length();
+		let steps = match step > 0.0 {
+			true => (length / step).ceil().max(1.0) as usize,
+			false => 1,
+		};
+
+		for index in 0..=steps {
+			let fraction = index as f64 / steps as f64;
+			let position = from + (to - from) * fraction + placement.offset;
+
+			points.push(ProfilePoint {
+				from: leg.from,
+				to: leg.to,
+				distance: distance + length * fraction,
+				position,
+				surface: placement.surface(dem, position),
+			});
+		}
+
+		distance += length;
+	}
+
+	Ok(points)
+}
+
+// The profile as CSV, with a header, in metres.
+pub fn write_profile_csv<W: Write>(points: &[ProfilePoint], output: &mut W) -> io::Result<()> {
+	writeln!(output, "from,to,distance,east,north,altitude,surface,cover")?;
+
+	let optional = |value: Option<f64>| value.map_or(String::new(), |value| format!("{value:.3}"));
+
+	for point in points {
+		writeln!(
+			output,
+			"{},{},{:.3},{:.3},{:.3},{:.3},{},{}",
+			point.from,
+			point.to,
+			point.distance,
+			point.position.east,
+			point.position.north,
+			point.position.up,
+			optional(point.surface),
+			optional(point.thickness()),
+		)?;
+	}
+
+	Ok(())
+}
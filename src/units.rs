@@ -0,0 +1,21 @@
+// Conversions from the file's units: millimetres, and internal angle units where
+// a full circle is 2^16.
+
+const UNITS_PER_DEGREE: f64 = 65536.0 / 360.0;
+
+pub fn degrees(units: impl Into<f64>) -> f64 {
+	units.into() / UNITS_PER_DEGREE
+}
+
+pub fn metres(millimetres: impl Into<f64>) -> f64 {
+	millimetres.into() / 1000.0
+}
+
+// the smallest difference between two angles, in either direction
+pub fn angle_difference(a: i16, b: i16) -> u16 {
+	a.wrapping_sub(b).unsigned_abs()
+}
+
+pub fn units(degrees: f64) -> f64 {
+	degrees * UNITS_PER_DEGREE
+}
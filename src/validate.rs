@@ -0,0 +1,296 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::{
+	diff::Item,
+	parser::Document,
+	reduction::{self, Position},
+	units, Element, StationId,
+};
+
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum Severity {
+	Warning,
+	Error,
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum Rule {
+	InvalidTripIndex,
+	UndefinedStations,
+	ZeroLengthLeg,
+	DisconnectedStations,
+	UnknownReferenceStation,
+	UnknownCrossSectionStation,
+	InconsistentLeg,
+	ImplausibleInclination,
+}
+
+impl Rule {
+	pub const ALL: [Rule; 8] = [
+		Rule::InvalidTripIndex,
+		Rule::UndefinedStations,
+		Rule::ZeroLengthLeg,
+		Rule::DisconnectedStations,
+		Rule::UnknownReferenceStation,
+		Rule::UnknownCrossSectionStation,
+		Rule::InconsistentLeg,
+		Rule::ImplausibleInclination,
+	];
+
+	pub fn id(self) -> &'static str {
+		match self {
+			Rule::InvalidTripIndex => "invalid-trip-index",
+			Rule::UndefinedStations => "undefined-stations",
+			Rule::ZeroLengthLeg => "zero-length-leg",
+			Rule::DisconnectedStations => "disconnected-stations",
+			Rule::UnknownReferenceStation => "unknown-reference-station",
+			Rule::UnknownCrossSectionStation => "unknown-cross-section-station",
+			Rule::InconsistentLeg => "inconsistent-leg",
+			Rule::ImplausibleInclination => "implausible-inclination",
+		}
+	}
+
+	pub fn severity(self) -> Severity {
+		match self {
+			Rule::InvalidTripIndex | Rule::InconsistentLeg | Rule::ImplausibleInclination => {
+				Severity::Error
+			}
+			_ => Severity::Warning,
+		}
+	}
+
+	pub fn description(self) -> &'static str {
+		match self {
+			Rule::InvalidTripIndex => "a shot's trip index is past the trips",
+			Rule::UndefinedStations => "a shot has neither a from nor a to station",
+			Rule::ZeroLengthLeg => "a leg has a distance of zero",
+			Rule::DisconnectedStations => "stations aren't connected to the first station",
+			Rule::UnknownReferenceStation => "a reference's station isn't in any shot",
+			Rule::UnknownCrossSectionStation => "a cross-section's station isn't in any shot",
+			Rule::InconsistentLeg => "repeated shots of a leg disagree",
+			Rule::ImplausibleInclination => "a shot's inclination is beyond vertical",
+		}
+	}
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Finding {
+	pub rule: Rule,
+	pub severity: Severity,
+	pub location: Item,
+	pub message: String,
+}
+
+// How far repeated shots of a leg may disagree.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Options {
+	pub distance: f64, // m
+	pub angle: f64,    // degrees
+}
+
+impl Default for Options {
+	fn default() -> Self {
+		Self {
+			distance: 0.05,
+			angle: 1.5,
+		}
+	}
+}
+
+// Checks a document against every rule, returning the findings in rule order.
+pub fn validate(document: &Document, options: &Options) -> Vec<Finding> {
+	let mut findings = Vec::new();
+
+	let mut finding = |rule: Rule, location: Item, message: String| {
+		findings.push(Finding {
+			rule,
+			severity: rule.severity(),
+			location,
+			message,
+		});
+	};
+
+	for (index, shot) in document.shots.iter().enumerate() {
+		if shot.trip_index < -1 || shot.trip_index as isize >= document.trips.len() as isize {
+			let message = format!(
+				"trip index {} but {} trips",
+				shot.trip_index,
+				document.trips.len()
+			);
+			finding(Rule::InvalidTripIndex, Item::Shot(index), message);
+		}
+	}
+
+	for (index, shot) in document.shots.iter().enumerate() {
+		if shot.from.is_none() && shot.to.is_none() {
+			finding(
+				Rule::UndefinedStations,
+				Item::Shot(index),
+				"no stations".to_owned(),
+			);
+		}
+	}
+
+	for (index, shot) in document.shots.iter().enumerate() {
+		if let (Some(from), Some(to), 0) = (shot.from, shot.to, shot.distance) {
+			finding(
+				Rule::ZeroLengthLeg,
+				Item::Shot(index),
+				format!("{from} → {to}"),
+			);
+		}
+	}
+
+	let centerline = reduction::reduce(document);
+	for island in islands(document, &centerline.stations) {
+		let stations = island.stations.iter().map(|station| station.to_string());
+		let message = format!(
+			"{} stations not connected to {}: {}",
+			island.stations.len(),
+			centerline
+				.origin
+				.map_or("-".to_owned(), |origin| origin.to_string()),
+			stations.collect::<Vec<_>>().join(", ")
+		);
+		finding(Rule::DisconnectedStations, Item::Shot(island.shot), message);
+	}
+
+	let surveyed: BTreeSet<StationId> = document
+		.shots
+		.iter()
+		.flat_map(|shot| [shot.from, shot.to])
+		.flatten()
+		.collect();
+
+	for (index, reference) in document.references.iter().enumerate() {
+		if let Some(station) = reference
+			.station
+			.filter(|station| !surveyed.contains(station))
+		{
+			let message = format!("{station} isn't surveyed");
+			finding(
+				Rule::UnknownReferenceStation,
+				Item::Reference(index),
+				message,
+			);
+		}
+	}
+
+	let drawings = [
+		(Item::Outline as fn(usize) -> Item, &document.outline),
+		(Item::Sideview, &document.sideview),
+	];
+	for (item, drawing) in drawings {
+		for (index, element) in drawing.elements.iter().enumerate() {
+			if let Element::CrossSection(cross_section) = element {
+				if !surveyed.contains(&cross_section.station) {
+					let message = format!("{} isn't surveyed", cross_section.station);
+					finding(Rule::UnknownCrossSectionStation, item(index), message);
+				}
+			}
+		}
+	}
+
+	for leg in centerline.legs.iter() {
+		let (first, repeats) = leg.shots.split_first().expect("a leg has a shot");
+		let reading = |index: usize| {
+			let shot = &document.shots[index];
+			let vector = reduction::vector(document, shot);
+			match shot.from == Some(leg.from) {
+				true => vector,
+				false => -vector,
+			}
+		};
+
+		let expected = reading(*first);
+		for repeat in repeats {
+			let actual = reading(*repeat);
+
+			let distance = (actual.length() - expected.length()).abs();
+			let angle = actual.angle(expected);
+
+			if distance > options.distance || angle > options.angle {
+				let message = format!(
+					"{} → {} differs from shot {first} by {distance:.3} m and {angle:.1}°",
+					leg.from, leg.to
+				);
+				finding(Rule::InconsistentLeg, Item::Shot(*repeat), message);
+			}
+		}
+	}
+
+	for (index, shot) in document.shots.iter().enumerate() {
+		// ±0x4000 is vertical
+		if !(-0x4000..=0x4000).contains(&shot.inclination) {
+			let message = format!("inclination {:.1}°", units::degrees(shot.inclination));
+			finding(Rule::ImplausibleInclination, Item::Shot(index), message);
+		}
+	}
+
+	findings
+}
+
+// stations connected to each other but not to the first station, with the
+// first shot which surveyed them
+struct Island {
+	shot: usize,
+	stations: BTreeSet<StationId>,
+}
+
+fn islands(document: &Document, positioned: &BTreeMap<StationId, Position>) -> Vec<Island> {
+	let mut islands: Vec<Island> = Vec::new();
+	let mut island_of: BTreeMap<StationId, usize> = BTreeMap::new();
+
+	for (index, shot) in document.shots.iter().enumerate() {
+		let stations = [shot.from, shot.to];
+		let stations = stations
+			.into_iter()
+			.flatten()
+			.filter(|station| !positioned.contains_key(station) && !island_of.contains_key(station))
+			.collect::<Vec<_>>();
+
+		for station in stations {
+			let joined = [shot.from, shot.to]
+				.into_iter()
+				.flatten()
+				.find_map(|other| island_of.get(&other).copied());
+
+			match joined {
+				Some(island) => {
+					island_of.insert(station, island);
+					islands[island].stations.insert(station);
+				}
+				None => {
+					island_of.insert(station, islands.len());
+					islands.push(Island {
+						shot: index,
+						stations: BTreeSet::from([station]),
+					});
+				}
+			}
+		}
+
+		// a shot joining two islands merges them into the earlier one
+		if let (Some(from), Some(to)) = (shot.from, shot.to) {
+			if let (Some(a), Some(b)) = (island_of.get(&from).copied(), island_of.get(&to).copied())
+			{
+				if a != b {
+					let (keep, merge) = (a.min(b), a.max(b));
+					let stations = std::mem::take(&mut islands[merge].stations);
+					for station in stations.iter() {
+						island_of.insert(*station, keep);
+					}
+					islands[keep].stations.extend(stations);
+				}
+			}
+		}
+	}
+
+	islands.retain(|island| !island.stations.is_empty());
+	islands
+}
@@ -0,0 +1,246 @@
+use std::io::{self, Write};
+
+use chrono::NaiveDateTime;
+
+use crate::{
+	parser::Document, Color, CrossSection, Drawing, Element, Mapping, Point, Polygon, Reference,
+	Shot, ShotFlags, StationId, Trip,
+};
+
+const HEADER: &[u8; 3] = b"Top";
+const VERSION: u8 = 0x3;
+
+// Writes `document` in the layout described in `doc/PocketTopoFileFormat.txt`,
+// the inverse of `parser::parse`.
+pub fn write<W: Write>(document: &Document, output: &mut W) -> io::Result<()> {
+	output.write_all(HEADER)?;
+	output.write_all(&[VERSION])?;
+
+	write_count(output, document.trips.len())?;
+	for trip in document.trips.iter() {
+		write_trip(output, trip)?;
+	}
+
+	write_count(output, document.shots.len())?;
+	for shot in document.shots.iter() {
+		write_shot(output, shot)?;
+	}
+
+	write_count(output, document.references.len())?;
+	for reference in document.references.iter() {
+		write_reference(output, reference)?;
+	}
+
+	write_mapping(output, &document.mapping)?;
+	write_drawing(output, &document.outline)?;
+	write_drawing(output, &document.sideview)?;
+
+	Ok(())
+}
+
+fn write_count<W: Write>(output: &mut W, count: usize) -> io::Result<()> {
+	let count = u32::try_from(count)
+		.map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "too many items"))?;
+
+	output.write_all(&count.to_le_bytes())
+}
+
+fn write_cross_section<W: Write>(output: &mut W, cross_section: &CrossSection) -> io::Result<()> {
+	output.write_all(&[0x3_u8])?;
+
+	write_point(output, &cross_section.position)?;
+	write_station_id(output, Some(&cross_section.station))?;
+	output.write_all(&cross_section.direction.to_le_bytes())
+}
+
+fn write_datetime<W: Write>(output: &mut W, time: &NaiveDateTime) -> io::Result<()> {
+	const TICKS_PER_SECOND: i64 = 10000000;
+	const NANOSECONDS_PER_TICK: i64 = 100;
+	const SECONDS_FROM_DOT_NET_EPOCH_TO_UNIX_EPOCH: i64 = 62135596800;
+
+	let time = time.and_utc();
+
+	// only about 29,000 years either side of the .NET epoch fit in an `Int64`
+	let ticks = time
+		.timestamp()
+		.checked_add(SECONDS_FROM_DOT_NET_EPOCH_TO_UNIX_EPOCH)
+		.and_then(|seconds| seconds.checked_mul(TICKS_PER_SECOND))
+		.and_then(|ticks| {
+			ticks.checked_add(i64::from(time.timestamp_subsec_nanos()) / NANOSECONDS_PER_TICK)
+		})
+		.ok_or_else(|| {
+			io::Error::new(
+				io::ErrorKind::InvalidInput,
+				format!("time out of range: {time}"),
+			)
+		})?;
+
+	output.write_all(&ticks.to_le_bytes())
+}
+
+fn write_drawing<W: Write>(output: &mut W, drawing: &Drawing) -> io::Result<()> {
+	write_mapping(output, &drawing.mapping)?;
+
+	for element in drawing.elements.iter() {
+		write_element(output, element)?;
+	}
+
+	output.write_all(&[0x0_u8])
+}
+
+fn write_element<W: Write>(output: &mut W, element: &Element) -> io::Result<()> {
+	match element {
+		Element::Polygon(polygon) => write_polygon(output, polygon),
+		Element::CrossSection(cross_section) => write_cross_section(output, cross_section),
+		Element::Unknown { id, bytes } => {
+			output.write_all(&[*id])?;
+			output.write_all(bytes)
+		}
+	}
+}
+
+fn write_mapping<W: Write>(output: &mut W, mapping: &Mapping) -> io::Result<()> {
+	write_point(output, &mapping.origin)?;
+	output.write_all(&mapping.scale.to_le_bytes())
+}
+
+fn write_point<W: Write>(output: &mut W, point: &Point) -> io::Result<()> {
+	output.write_all(&point.x.to_le_bytes())?;
+	output.write_all(&point.y.to_le_bytes())
+}
+
+fn write_polygon<W: Write>(output: &mut W, polygon: &Polygon) -> io::Result<()> {
+	output.write_all(&[0x1_u8])?;
+
+	write_count(output, polygon.points.len())?;
+	for point in polygon.points.iter() {
+		write_point(output, point)?;
+	}
+
+	let color = match polygon.color {
+		Color::Black => 0x1_u8,
+		Color::Gray => 0x2_u8,
+		Color::Brown => 0x3_u8,
+		Color::Blue => 0x4_u8,
+		Color::Red => 0x5_u8,
+		Color::Green => 0x6_u8,
+		Color::Orange => 0x7_u8,
+	};
+
+	output.write_all(&[color])
+}
+
+fn write_shot<W: Write>(output: &mut W, shot: &Shot) -> io::Result<()> {
+	// the comment flag must agree with the comment, otherwise the file can't be read back
+	let mut flags = shot.flags;
+	flags.set(ShotFlags::HAS_COMMENT, shot.comment.is_some());
+
+	write_station_id(output, shot.from.as_ref())?;
+	write_station_id(output, shot.to.as_ref())?;
+	output.write_all(&shot.distance.to_le_bytes())?;
+	output.write_all(&shot.azimuth.to_le_bytes())?;
+	output.write_all(&shot.inclination.to_le_bytes())?;
+	output.write_all(&[flags.bits(), shot.roll])?;
+	output.write_all(&shot.trip_index.to_le_bytes())?;
+
+	if let Some(comment) = &shot.comment {
+		write_string(output, comment)?;
+	}
+
+	Ok(())
+}
+
+fn write_station_id<W: Write>(output: &mut W, station_id: Option<&StationId>) -> io::Result<()> {
+	const UNDEFINED: u32 = 0b10000000000000000000000000000000;
+
+	let value = match station_id {
+		None => UNDEFINED,
+		Some(StationId::Plain(x)) if *x <= StationId::MAX_PLAIN => (x + 1) | UNDEFINED,
+		Some(StationId::MajorMinor(major, minor)) if *major <= StationId::MAX_MAJOR => {
+			(u32::from(*major) << 16) | u32::from(*minor)
+		}
+		Some(station_id) => {
+			let error = format!("station id out of range: {station_id}");
+			return Err(io::Error::new(io::ErrorKind::InvalidInput, error));
+		}
+	};
+
+	output.write_all(&value.to_le_bytes())
+}
+
+fn write_string<W: Write>(output: &mut W, string: &str) -> io::Result<()> {
+	write_variable_length_little_endian_int(output, string.len())?;
+	output.write_all(string.as_bytes())
+}
+
+fn write_reference<W: Write>(output: &mut W, reference: &Reference) -> io::Result<()> {
+	write_station_id(output, reference.station.as_ref())?;
+	output.write_all(&reference.east.to_le_bytes())?;
+	output.write_all(&reference.north.to_le_bytes())?;
+	output.write_all(&reference.altitude.to_le_bytes())?;
+	write_string(output, &reference.comment)
+}
+
+fn write_trip<W: Write>(output: &mut W, trip: &Trip) -> io::Result<()> {
+	write_datetime(output, &trip.time)?;
+	write_string(output, &trip.comment)?;
+	output.write_all(&trip.declination.to_le_bytes())
+}
+
+// unsigned, encoded in 7 bit chunks, little endian, bit7 set in all but the last byte
+fn write_variable_length_little_endian_int<W: Write>(
+	output: &mut W,
+	mut value: usize,
+) -> io::Result<()> {
+	const BIT_7_SET: u8 = 0b10000000;
+
+	loop {
+		let byte = (value & 0b01111111) as u8;
+		value >>= 7;
+
+		if value == 0 {
+			return output.write_all(&[byte]);
+		}
+
+		output.write_all(&[byte | BIT_7_SET])?;
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn test_write_station_id() {
+		let mut output = Vec::new();
+		write_station_id(&mut output, None).unwrap();
+		assert_eq!(output, [0x00, 0x00, 0x00, 0x80]);
+
+		let mut output = Vec::new();
+		write_station_id(&mut output, Some(&StationId::MajorMinor(42, 1))).unwrap();
+		assert_eq!(output, [0x01, 0x00, 0x2A, 0x00]);
+
+		let mut output = Vec::new();
+		write_station_id(&mut output, Some(&StationId::Plain(0))).unwrap();
+		assert_eq!(output, [0x01, 0x00, 0x00, 0x80]);
+
+		let mut output = Vec::new();
+		write_station_id(&mut output, Some(&StationId::Plain(2147483646))).unwrap();
+		assert_eq!(output, [0xFF, 0xFF, 0xFF, 0xFF]);
+	}
+
+	#[test]
+	fn test_write_variable_length_little_endian_int() {
+		let mut output = Vec::new();
+		write_variable_length_little_endian_int(&mut output, 0).unwrap();
+		assert_eq!(output, [0x00]);
+
+		let mut output = Vec::new();
+		write_variable_length_little_endian_int(&mut output, 43).unwrap();
+		assert_eq!(output, [0x2b]);
+
+		let mut output = Vec::new();
+		write_variable_length_little_endian_int(&mut output, 255).unwrap();
+		assert_eq!(output, [0b11111111, 0b00000001]);
+	}
+}
@@ -0,0 +1,141 @@
+mod common;
+
+use pocket_topo::{
+	blunders::{detect, Evidence, Options, Reading},
+	builder::DocumentBuilder,
+};
+
+use common::station;
+
+#[test]
+fn compares_backsights() {
+	let document = DocumentBuilder::new()
+		.shot("1.0", "1.1", 10.0, 90.0, 0.0)
+		.shot("1.1", "1.0", 10.0, 280.0, 0.0)
+		.build()
+		.unwrap();
+
+	let report = detect(&document, &Options::default());
+
+	let shots: Vec<_> = report.suspects.iter().map(|suspect| suspect.shot).collect();
+	assert_eq!(shots, [0, 1]);
+
+	let Evidence::Backsight { other, angle, .. } = report.suspects[0].evidence[0] else {
+		panic!("not a backsight");
+	};
+	assert_eq!(other, 1);
+	assert!((angle - 10.0).abs() < 0.01);
+}
+
+#[test]
+fn compares_flipped_backsights() {
+	// flipped only changes the extended elevation, so a backsight read the
+	// same way as its foresight is a blunder
+	let document = DocumentBuilder::new()
+		.shot("1.0", "1.1", 10.0, 0.0, 0.0)
+		.shot("1.1", "1.0", 10.0, 0.0, 0.0)
+		.flipped()
+		.build()
+		.unwrap();
+
+	let report = detect(&document, &Options::default());
+
+	let shots: Vec<_> = report.suspects.iter().map(|suspect| suspect.shot).collect();
+	assert_eq!(shots, [0, 1]);
+
+	let Evidence::Backsight { angle, .. } = report.suspects[1].evidence[0] else {
+		panic!("not a backsight");
+	};
+	assert!((angle - 180.0).abs() < 0.01);
+}
+
+#[test]
+fn isolates_loop_blunders() {
+	// a square with a diagonal, where the azimuth of 1.1 → 1.2 is misread
+	let document = DocumentBuilder::new()
+		.shot("1.0", "1.1", 10.0, 90.0, 0.0)
+		.shot("1.1", "1.2", 10.0, 10.0, 0.0)
+		.shot("1.2", "1.3", 10.0, 270.0, 0.0)
+		.shot("1.3", "1.0", 10.0, 180.0, 0.0)
+		.shot("1.0", "1.2", 200.0_f64.sqrt(), 45.0, 0.0)
+		.build()
+		.unwrap();
+
+	let report = detect(&document, &Options::default());
+
+	assert_eq!(report.loops.len(), 2);
+	assert_eq!(report.loops.iter().filter(|l| l.blunder).count(), 1);
+
+	let suspect = &report.suspects[0];
+	assert_eq!(suspect.shot, 1);
+
+	let Evidence::Misclosure { reading, error, .. } = suspect.evidence[0] else {
+		panic!("not a misclosure");
+	};
+	assert_eq!(reading, Reading::Azimuth);
+	assert!((error - 10.0).abs() < 0.1, "{error}");
+
+	// legs which aren't in the loop aren't suspected
+	assert!(report.suspects.iter().all(|suspect| suspect.shot != 2));
+}
+
+#[test]
+fn scores_loops_which_close() {
+	// a loop which closes exactly, but is flagged by a negative tolerance
+	let document = DocumentBuilder::new()
+		.shot("1.0", "1.1", 10.0, 0.0, 0.0)
+		.shot("1.1", "1.2", 10.0, 0.0, 0.0)
+		.shot("1.0", "1.2", 20.0, 0.0, 0.0)
+		.build()
+		.unwrap();
+
+	let options = Options {
+		distance: -1.0,
+		..Options::default()
+	};
+	let report = detect(&document, &options);
+	assert!(report.loops[0].blunder);
+
+	for suspect in report.suspects.iter() {
+		assert!(!suspect.score.is_nan());
+		for evidence in suspect.evidence.iter() {
+			if let Evidence::Misclosure { share, .. } = evidence {
+				assert_eq!(*share, 0.0);
+			}
+		}
+	}
+}
+
+#[test]
+fn finds_magnetic_anomalies() {
+	// shots taken at 1.1 are rotated by 5°
+	let document = DocumentBuilder::new()
+		.shot("1.0", "1.1", 10.0, 90.0, 0.0)
+		.shot("1.1", "1.0", 10.0, 275.0, 0.0)
+		.shot("1.1", "1.2", 10.0, 5.0, 0.0)
+		.shot("1.2", "1.1", 10.0, 180.0, 0.0)
+		.build()
+		.unwrap();
+
+	let report = detect(&document, &Options::default());
+
+	let anomalies: Vec<_> = report
+		.suspects
+		.iter()
+		.filter_map(|suspect| {
+			suspect.evidence.iter().find_map(|evidence| match evidence {
+				Evidence::MagneticAnomaly { station, deviation } => {
+					Some((suspect.shot, *station, *deviation))
+				}
+				_ => None,
+			})
+		})
+		.collect();
+
+	assert_eq!(anomalies.len(), 2);
+	for (shot, anomaly, deviation) in anomalies {
+		assert!(shot == 1 || shot == 2);
+		assert_eq!(anomaly, station("1.1"));
+		assert!((deviation - 5.0).abs() < 0.01);
+	}
+}
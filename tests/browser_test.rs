@@ -0,0 +1,150 @@
+mod common;
+
+use pocket_topo::{
+	browser::{Browser, Filter, Row, Tab},
+	builder::DocumentBuilder,
+	parser::Document,
+};
+
+use common::{date, station};
+
+// two trips, the second adding a side passage from 1.1, and a reference
+fn survey() -> Document<'static> {
+	let day = |day| date(2024, 5, day);
+
+	DocumentBuilder::new()
+		.trip(day(1), 0.0, "entrance\nwith the tape")
+		.shot("1.0", "1.1", 10.0, 90.0, 0.0)
+		.shot("1.1", "1.0", 10.0, 270.0, 0.0)
+		.splay("1.1", 2.0, 0.0, 0.0)
+		.shot("1.1", "1.2", 5.0, 180.0, 0.0)
+		.trip(day(2), 0.0, "side passage")
+		.shot("1.1", "2.0", 4.0, 0.0, 0.0)
+		.comment("squeeze")
+		.shot("2.0", "2.1", 3.0, 0.0, 0.0)
+		.reference("1.0", 1000.0, 2000.0, 300.0, "entrance")
+		.build()
+		.unwrap()
+}
+
+fn leg(from: &str, to: &str, shots: &[usize]) -> Row {
+	Row::Leg {
+		from: station(from),
+		to: station(to),
+		shots: shots.to_vec(),
+	}
+}
+
+#[test]
+fn groups_shots() {
+	let document = survey();
+	let mut browser = Browser::new(&document);
+	browser.set_tab(Tab::Shots);
+
+	assert_eq!(
+		browser.rows(),
+		[
+			leg("1.0", "1.1", &[0, 1]),
+			Row::Splay(2),
+			leg("1.1", "1.2", &[3]),
+			leg("1.1", "2.0", &[4]),
+			leg("2.0", "2.1", &[5]),
+		]
+	);
+}
+
+#[test]
+fn filters_by_trip() {
+	let document = survey();
+	let mut browser = Browser::new(&document);
+	assert_eq!(browser.rows(), [Row::Trip(0), Row::Trip(1)]);
+
+	browser.select(1);
+	browser.open();
+
+	assert_eq!(browser.tab(), Tab::Shots);
+	assert_eq!(browser.filter().trip, Some(1));
+	assert_eq!(
+		browser.rows(),
+		[leg("1.1", "2.0", &[4]), leg("2.0", "2.1", &[5])]
+	);
+
+	browser.set_tab(Tab::Trips);
+	assert_eq!(browser.rows(), [Row::Trip(1)]);
+}
+
+#[test]
+fn filters_by_station() {
+	let document = survey();
+	let mut browser = Browser::new(&document);
+
+	browser.set_filter(Filter {
+		trip: None,
+		station: Some(station("1.0")),
+	});
+
+	// only the first trip has shots at 1.0
+	assert_eq!(browser.rows(), [Row::Trip(0)]);
+
+	browser.set_tab(Tab::References);
+	assert_eq!(browser.rows(), [Row::Reference(0)]);
+
+	browser.set_filter(Filter {
+		trip: None,
+		station: Some(station("2.1")),
+	});
+	assert!(browser.rows().is_empty());
+	assert_eq!(browser.selected(), None);
+}
+
+#[test]
+fn follows_stations() {
+	let document = survey();
+	let mut browser = Browser::new(&document);
+	browser.set_tab(Tab::References);
+
+	// from the reference to the legs at its station
+	browser.open();
+	assert_eq!(browser.filter().station, Some(station("1.0")));
+	assert_eq!(browser.rows(), [leg("1.0", "1.1", &[0, 1])]);
+
+	// and on to the legs at the far end
+	browser.follow(true);
+	assert_eq!(browser.filter().station, Some(station("1.1")));
+	assert_eq!(
+		browser.rows(),
+		[
+			leg("1.0", "1.1", &[0, 1]),
+			Row::Splay(2),
+			leg("1.1", "1.2", &[3]),
+			leg("1.1", "2.0", &[4]),
+		]
+	);
+
+	// the leg followed stays selected
+	assert_eq!(browser.selected(), Some(&leg("1.0", "1.1", &[0, 1])));
+
+	browser.select(3);
+	browser.follow(true);
+	assert_eq!(
+		browser.rows(),
+		[leg("1.1", "2.0", &[4]), leg("2.0", "2.1", &[5])]
+	);
+	assert_eq!(browser.selected_index(), 0);
+}
+
+#[test]
+fn moves_within_rows() {
+	let document = survey();
+	let mut browser = Browser::new(&document);
+	browser.set_tab(Tab::Shots);
+
+	browser.move_by(-1);
+	assert_eq!(browser.selected_index(), 0);
+
+	browser.move_by(100);
+	assert_eq!(browser.selected_index(), 4);
+
+	browser.move_by(-2);
+	assert_eq!(browser.selected_index(), 2);
+}
@@ -0,0 +1,184 @@
+use chrono::NaiveDate;
+use pocket_topo::{
+	builder::{BuildError, DocumentBuilder},
+	parser, writer, Color, Element, InvalidColor, InvalidStationId, Point, ShotFlags, StationId,
+};
+
+#[test]
+fn builds_document() {
+	let time = NaiveDate::from_ymd_opt(2022, 10, 22)
+		.unwrap()
+		.and_hms_opt(0, 0, 0)
+		.unwrap();
+
+	let document = DocumentBuilder::new()
+		.reference("1.0", 24.0, 42.0, 50.0, "entrance")
+		.trip(time, 3.45, "test")
+		.shot("1.0", "1.1", 123.45, 10.0, 30.0)
+		.comment("Comment #1")
+		.splay("1.1", 1.5, 270.0, -90.0)
+		.flipped()
+		.shot("1.1", "2", 26.34, 6.7, 42.4)
+		.trip_index(-1)
+		.outline_polygon("black", &[(0.2, -9.8), (0.6, -9.8)])
+		.outline_cross_section("1.0", (-5.7, -15.6), None)
+		.sideview_cross_section("1.1", (1.0, 2.0), Some(90.0))
+		.build()
+		.expect("invalid document");
+
+	let trip = &document.trips[0];
+	assert_eq!(trip.time, time);
+	assert_eq!(trip.comment, "test");
+	assert_eq!(trip.declination, 628);
+
+	let mut shots = document.shots.iter();
+	assert_eq!(shots.len(), 3);
+
+	let shot = shots.next().unwrap();
+	assert_eq!(shot.from, Some(StationId::MajorMinor(1, 0)));
+	assert_eq!(shot.to, Some(StationId::MajorMinor(1, 1)));
+	assert_eq!(shot.distance, 123450);
+	assert_eq!(shot.azimuth, 1820);
+	assert_eq!(shot.inclination, 5461);
+	assert_eq!(shot.flags, ShotFlags::HAS_COMMENT);
+	assert_eq!(shot.trip_index, 0);
+	assert_eq!(shot.comment.as_deref(), Some("Comment #1"));
+
+	let shot = shots.next().unwrap();
+	assert_eq!(shot.to, None);
+	assert_eq!(shot.azimuth, -16384);
+	assert_eq!(shot.inclination, -16384);
+	assert_eq!(shot.flags, ShotFlags::FLIPPED);
+
+	let shot = shots.next().unwrap();
+	assert_eq!(shot.to, Some(StationId::Plain(2)));
+	assert_eq!(shot.trip_index, -1);
+
+	let reference = &document.references[0];
+	assert_eq!(reference.station, Some(StationId::MajorMinor(1, 0)));
+	assert_eq!(reference.east, 24000);
+	assert_eq!(reference.north, 42000);
+	assert_eq!(reference.altitude, 50000);
+
+	match &document.outline.elements[0] {
+		Element::Polygon(polygon) => {
+			assert_eq!(polygon.color, Color::Black);
+			assert_eq!(
+				polygon.points.as_ref(),
+				[Point { x: 200, y: -9800 }, Point { x: 600, y: -9800 }]
+			);
+		}
+		_ => panic!(),
+	}
+
+	match &document.outline.elements[1] {
+		Element::CrossSection(cross_section) => assert_eq!(cross_section.direction, -1),
+		_ => panic!(),
+	}
+
+	match &document.sideview.elements[0] {
+		Element::CrossSection(cross_section) => assert_eq!(cross_section.direction, 0x4000),
+		_ => panic!(),
+	}
+
+	// the built document can be written and read back
+	let mut output = Vec::new();
+	writer::write(&document, &mut output).unwrap();
+	assert_eq!(parser::parse(&output).unwrap(), document);
+}
+
+#[test]
+fn validates_station_ids() {
+	let result = DocumentBuilder::new()
+		.shot("1.0", "1.x", 1.0, 0.0, 0.0)
+		.build();
+	assert_eq!(
+		result.unwrap_err(),
+		BuildError::InvalidStationId(InvalidStationId("1.x".to_owned()))
+	);
+
+	let result = DocumentBuilder::new()
+		.splay("32768.0", 1.0, 0.0, 0.0)
+		.build();
+	assert_eq!(
+		result.unwrap_err(),
+		BuildError::InvalidStationId(InvalidStationId("32768.0".to_owned()))
+	);
+
+	let result = DocumentBuilder::new()
+		.splay("2147483647", 1.0, 0.0, 0.0)
+		.build();
+	assert_eq!(
+		result.unwrap_err(),
+		BuildError::InvalidStationId(InvalidStationId("2147483647".to_owned()))
+	);
+}
+
+#[test]
+fn validates_trip_indices() {
+	let result = DocumentBuilder::new()
+		.shot("1.0", "1.1", 1.0, 0.0, 0.0)
+		.trip_index(0)
+		.build();
+	assert_eq!(result.unwrap_err(), BuildError::InvalidTripIndex(0));
+
+	let result = DocumentBuilder::new().trip_index(0).build();
+	assert_eq!(result.unwrap_err(), BuildError::NoShot);
+}
+
+#[test]
+fn validates_colors() {
+	let result = DocumentBuilder::new()
+		.sideview_polygon("purple", &[(0.0, 0.0)])
+		.build();
+	assert_eq!(
+		result.unwrap_err(),
+		BuildError::InvalidColor(InvalidColor("purple".to_owned()))
+	);
+}
+
+#[test]
+fn validates_ranges() {
+	let result = DocumentBuilder::new()
+		.shot("1.0", "1.1", 1.0, 0.0, 91.0)
+		.shot("1.1", "1.2", -1.0, 0.0, 0.0)
+		.build();
+	assert_eq!(
+		result.unwrap_err(),
+		BuildError::OutOfRange("inclination", 91.0)
+	);
+
+	let result = DocumentBuilder::new()
+		.shot("1.0", "1.1", 3000000.0, 0.0, 0.0)
+		.build();
+	assert_eq!(
+		result.unwrap_err(),
+		BuildError::OutOfRange("distance", 3000000.0)
+	);
+}
+
+#[test]
+fn validates_trip_count() {
+	let time = NaiveDate::from_ymd_opt(2022, 10, 22)
+		.unwrap()
+		.and_hms_opt(0, 0, 0)
+		.unwrap();
+
+	// the last trip index is i16::MAX
+	let mut builder = DocumentBuilder::new();
+	for _ in 0..=i16::MAX {
+		builder = builder.trip(time, 0.0, "");
+	}
+	let document = builder.shot("1.0", "1.1", 1.0, 0.0, 0.0).build().unwrap();
+	assert_eq!(document.shots[0].trip_index, i16::MAX);
+
+	let mut builder = DocumentBuilder::new();
+	for _ in 0..=i16::MAX as usize + 1 {
+		builder = builder.trip(time, 0.0, "");
+	}
+	let result = builder.shot("1.0", "1.1", 1.0, 0.0, 0.0).build();
+	assert_eq!(
+		result.unwrap_err(),
+		BuildError::OutOfRange("trip index", 32768.0)
+	);
+}
@@ -0,0 +1,437 @@
+use std::{
+	env, fs,
+	path::PathBuf,
+	process::{Command, Output},
+};
+
+use pocket_topo::{builder::DocumentBuilder, parser, writer};
+
+#[test]
+fn prints_info() {
+	let output = pockettopo(&["info", &fixture("unknown.top")]);
+	assert!(output.status.success());
+
+	let stdout = String::from_utf8(output.stdout).unwrap();
+	assert_eq!(
+		stdout,
+		"Trips: 0\n\
+		 Shots: 1 (0 legs, 1 splays)\n\
+		 References: 1\n\
+		 \x20 1.0: east 0.000 m, north 0.000 m, altitude 0.000 m\n\
+		 Outline: 1 polygons, 1 cross-sections, 1 unknown elements\n\
+		 Sideview: 0 polygons, 0 cross-sections, 1 unknown elements\n"
+	);
+}
+
+#[test]
+fn prints_info_with_crs() {
+	let output = pockettopo(&["info", &fixture("unknown.top"), "--crs", "31N"]);
+	assert!(output.status.success());
+
+	let stdout = String::from_utf8(output.stdout).unwrap();
+	assert!(stdout.contains("  CRS: UTM 31N (EPSG:32631)\n"));
+	assert!(stdout.contains(
+		"    latitude 0.0000000°, longitude -1.4887439°, convergence 0.0000°, scale factor 1.002696\n"
+	));
+
+	let output = pockettopo(&["info", &fixture("unknown.top"), "--crs", "31X"]);
+	assert!(!output.status.success());
+}
+
+#[test]
+fn prints_info_with_legacy_encoding() {
+	let output = pockettopo(&["info", &fixture("encoding.top")]);
+	assert!(!output.status.success());
+
+	let output = pockettopo(&[
+		"--encoding",
+		"windows-1252",
+		"info",
+		&fixture("encoding.top"),
+	]);
+	assert!(output.status.success());
+
+	let stdout = String::from_utf8(output.stdout).unwrap();
+	assert!(stdout.contains("0: 2022-10-22 declination 3.45° \"tést\"\n"));
+
+	let stderr = String::from_utf8(output.stderr).unwrap();
+	assert!(stderr.contains("comment of trip 0 isn't valid UTF-8"));
+}
+
+#[test]
+fn dumps_json() {
+	let output = pockettopo(&["dump", "--json", &fixture("comments.top")]);
+	assert!(output.status.success());
+
+	let stdout = String::from_utf8(output.stdout).unwrap();
+	assert!(stdout.starts_with("{\n  \"references\": ["));
+	assert!(stdout.contains("\"comment\": \"Comment #1\\r\\n"));
+}
+
+#[test]
+fn converts_by_extension() {
+	let output = temporary("converts_by_extension.top");
+
+	let result = pockettopo(&["convert", &fixture("outline.top"), &output]);
+	assert!(result.status.success());
+
+	let original = fs::read(fixture("outline.top")).unwrap();
+	let converted = fs::read(&output).unwrap();
+	assert_eq!(
+		parser::parse(&converted).unwrap(),
+		parser::parse(&original).unwrap()
+	);
+}
+
+#[test]
+fn converts_by_format() {
+	let output = temporary("converts_by_format.txt");
+
+	let result = pockettopo(&["convert", &fixture("trips.top"), &output]);
+	assert!(!result.status.success());
+
+	let result = pockettopo(&[
+		"convert",
+		&fixture("trips.top"),
+		&output,
+		"--format",
+		"json",
+	]);
+	assert!(result.status.success());
+
+	let converted = fs::read_to_string(&output).unwrap();
+	assert!(converted.contains("\"comment\": \"2022-10-15 2.34\""));
+}
+
+#[test]
+fn converts_to_exports() {
+	for (extension, format, magic) in [
+		("obj", None, &b"# east north up"[..]),
+		(
+			"ply",
+			None,
+			b"ply\nformat binary_little_endian 1.0\nelement vertex",
+		),
+		(
+			"ply",
+			Some("cloud-ply"),
+			b"ply\nformat binary_little_endian 1.0\ncomment kind",
+		),
+		("stl", None, b"pockettopo passage mesh"),
+		("glb", None, b"glTF"),
+		("las", None, b"LASF"),
+		("png", None, b"\x89PNG"),
+		("pdf", None, b"%PDF"),
+	] {
+		let input = fixture("outline.top");
+		let output = temporary(&format!("converts_to_exports.{extension}"));
+
+		let mut args = vec!["convert", &input, &output];
+		if let Some(format) = format {
+			args.extend(["--format", format]);
+		}
+
+		let result = pockettopo(&args);
+		assert!(result.status.success(), "{extension}");
+		assert!(fs::read(&output).unwrap().starts_with(magic), "{extension}");
+	}
+}
+
+#[test]
+fn checks_declinations() {
+	let location = ["--latitude", "47.5", "--longitude", "13.7"];
+
+	let output = pockettopo(&[&["declination", &fixture("trips.top")], &location[..]].concat());
+	assert!(output.status.success());
+
+	let stdout = String::from_utf8(output.stdout).unwrap();
+	assert!(stdout.starts_with("At 47.50000°, 13.70000°, 0 m\n"));
+	assert!(stdout.contains("  1: 2022-10-15 recorded 2.34°, WMM-2020 4.38°, difference +2.04°\n"));
+
+	let output = pockettopo(
+		&[
+			&["declination", &fixture("trips.top"), "--tolerance", "1"],
+			&location[..],
+		]
+		.concat(),
+	);
+	assert!(!output.status.success());
+	assert!(String::from_utf8(output.stdout)
+		.unwrap()
+		.contains("+2.04° !\n"));
+
+	// without a location
+	let output = pockettopo(&["declination", &fixture("trips.top")]);
+	assert!(!output.status.success());
+
+	let filled = temporary("checks_declinations.top");
+	let output = pockettopo(
+		&[
+			&[
+				"declination",
+				&fixture("trips.top"),
+				"--fill",
+				&filled,
+				"--all",
+			],
+			&location[..],
+		]
+		.concat(),
+	);
+	assert!(output.status.success());
+
+	let contents = fs::read(&filled).unwrap();
+	let document = parser::parse(&contents).unwrap();
+	let declinations: Vec<i16> = document.trips.iter().map(|trip| trip.declination).collect();
+	assert_eq!(declinations[0], declinations[1]);
+	assert_ne!(declinations[0], 0);
+}
+
+#[test]
+fn colours_diff() {
+	let old = fixture("comments.top");
+	let new = fixture("trips.top");
+
+	let output = pockettopo(&["diff", &old, &new, "--color", "always"]);
+	assert!(output.status.success());
+	assert!(String::from_utf8(output.stdout)
+		.unwrap()
+		.starts_with("\x1b[33m~ "));
+
+	// not a terminal
+	let output = pockettopo(&["diff", &old, &new]);
+	assert!(!String::from_utf8(output.stdout).unwrap().contains('\x1b'));
+}
+
+#[test]
+fn prints_diff() {
+	let output = pockettopo(&[
+		"diff",
+		&fixture("comments.top"),
+		&fixture("trips.top"),
+		"--color",
+		"never",
+	]);
+	assert!(output.status.success());
+
+	let stdout = String::from_utf8(output.stdout).unwrap();
+	let mut lines = stdout.lines();
+
+	assert_eq!(
+		lines.next(),
+		Some(
+			"~ trip 0: 2022-10-22 declination 0.00° => \
+			 trip 0: 2022-10-22 declination 3.45° \"test\""
+		)
+	);
+	assert_eq!(
+		lines.next(),
+		Some("+ trip 1: 2022-10-15 declination 2.34° \"2022-10-15 2.34\"")
+	);
+	assert_eq!(
+		lines.nth(2),
+		Some("- shot 1.1 → 2 (trip 0): 26.340 m 6.7° 42.4° \"Comment #2\"")
+	);
+}
+
+#[test]
+fn prints_stats() {
+	let output = pockettopo(&["stats", &fixture("comments.top")]);
+	assert!(output.status.success());
+
+	let stdout = String::from_utf8(output.stdout).unwrap();
+	assert!(stdout.starts_with("Dates: 2022-10-22 to 2022-10-22\nLength: 149.79 m\n"));
+	assert!(stdout.contains("Longest leg: 1.0 → 1.1, 123.45 m\n"));
+
+	let output = pockettopo(&["stats", "--json", &fixture("comments.top")]);
+	assert!(output.status.success());
+
+	let stdout = String::from_utf8(output.stdout).unwrap();
+	assert!(stdout.contains("\"legs\": 2,"));
+}
+
+#[test]
+fn validates() {
+	let output = pockettopo(&["validate", &fixture("trips.top")]);
+	assert!(output.status.success());
+
+	let stdout = String::from_utf8(output.stdout).unwrap();
+	assert_eq!(stdout, "warning[zero-length-leg]: shot 0: 1.0 → 1.1\n");
+
+	let output = pockettopo(&["validate", "--strict", &fixture("trips.top")]);
+	assert!(!output.status.success());
+}
+
+#[test]
+fn ranks_blunders() {
+	let document = DocumentBuilder::new()
+		.shot("1.0", "1.1", 10.0, 90.0, 0.0)
+		.shot("1.1", "1.0", 10.0, 280.0, 0.0)
+		.build()
+		.unwrap();
+
+	let input = temporary("ranks_blunders.top");
+	let mut contents = Vec::new();
+	writer::write(&document, &mut contents).unwrap();
+	fs::write(&input, contents).unwrap();
+
+	let output = pockettopo(&["blunders", &input]);
+	assert!(output.status.success());
+
+	let stdout = String::from_utf8(output.stdout).unwrap();
+	assert!(stdout.starts_with(
+		"1. shot 0 (1.0 → 1.1): score 6.7\n   \
+		 differs from shot 1 (1.1 → 1.0) by 0.000 m and 10.0°\n"
+	));
+}
+
+#[test]
+fn exports_mesh() {
+	let mut builder = DocumentBuilder::new().shot("1.0", "1.1", 10.0, 90.0, 0.0);
+	for station in ["1.0", "1.1"] {
+		for azimuth in [0.0, 180.0] {
+			builder = builder.splay(station, 1.0, azimuth, 0.0);
+		}
+		builder = builder.splay(station, 1.0, 0.0, 90.0);
+	}
+
+	let input = temporary("exports_mesh.top");
+	let mut contents = Vec::new();
+	writer::write(&builder.build().unwrap(), &mut contents).unwrap();
+	fs::write(&input, contents).unwrap();
+
+	let output = temporary("exports_mesh.stl");
+	let result = pockettopo(&["mesh", &input, &output, "--segments", "4"]);
+	assert!(result.status.success());
+
+	// 4 triangles around each end and 8 along the passage
+	assert_eq!(fs::read(&output).unwrap().len(), 84 + 16 * 50);
+
+	let result = pockettopo(&["mesh", &input, &temporary("exports_mesh.txt")]);
+	assert!(!result.status.success());
+}
+
+#[test]
+fn exports_scene() {
+	let output = temporary("exports_scene.glb");
+
+	let result = pockettopo(&["scene", &fixture("references.top"), &output, "--passage"]);
+	assert!(result.status.success());
+
+	assert!(fs::read(&output).unwrap().starts_with(b"glTF"));
+}
+
+#[test]
+fn compares_with_surface() {
+	let document = DocumentBuilder::new()
+		.shot("1.0", "1.1", 10.0, 90.0, 0.0)
+		.reference("1.0", 5.0, 5.0, 80.0, "")
+		.build()
+		.unwrap();
+
+	let input = temporary("compares_with_surface.top");
+	let mut contents = Vec::new();
+	writer::write(&document, &mut contents).unwrap();
+	fs::write(&input, contents).unwrap();
+
+	let dem = temporary("compares_with_surface.asc");
+	fs::write(
+		&dem,
+		"ncols 2\nnrows 1\nxllcorner 0\nyllcorner 0\ncellsize 10\n100 90\n",
+	)
+	.unwrap();
+
+	let profile = temporary("compares_with_surface.csv");
+	let output = pockettopo(&["surface", &input, &dem, "--profile", &profile]);
+	assert!(output.status.success());
+
+	assert_eq!(
+		String::from_utf8(output.stdout).unwrap(),
+		"Surface: 2 × 1 cells of 10 × 10\n\
+		 Stations: 2, 2 under the surface\n\
+		 Shallowest:\n  \
+		 1.1: cover 10.0 m, altitude 80.0 m, surface 90.0 m\n  \
+		 1.0: cover 20.0 m, altitude 80.0 m, surface 100.0 m\n"
+	);
+
+	let profile = fs::read_to_string(&profile).unwrap();
+	assert_eq!(profile.lines().count(), 4);
+
+	let output = pockettopo(&["surface", &fixture("outline.top"), &dem]);
+	assert!(!output.status.success());
+}
+
+#[test]
+fn renders_png() {
+	let output = temporary("renders_png.png");
+
+	let result = pockettopo(&["render", &fixture("outline.top"), &output, "--dpi", "72"]);
+	assert!(result.status.success());
+
+	assert!(fs::read(&output).unwrap().starts_with(b"\x89PNG"));
+}
+
+#[test]
+fn prints_pdf() {
+	let output = temporary("prints_pdf.pdf");
+
+	let arguments = [
+		"pdf",
+		&fixture("outline.top"),
+		&output,
+		"--legend",
+		"blue=water",
+	];
+	let result = pockettopo(&arguments);
+	assert!(result.status.success());
+
+	assert!(fs::read(&output).unwrap().starts_with(b"%PDF"));
+
+	let result = pockettopo(&["pdf", &fixture("outline.top"), &output, "--legend", "blue"]);
+	assert!(!result.status.success());
+}
+
+#[test]
+fn previews_outline() {
+	let result = pockettopo(&["preview", &fixture("outline.top"), "--width", "40"]);
+	assert!(result.status.success());
+
+	// not a terminal, so no colours
+	let output = String::from_utf8(result.stdout).unwrap();
+	assert!(!output.contains('\x1b'));
+	assert!(output.lines().all(|line| line.chars().count() <= 40));
+	assert!(output
+		.chars()
+		.any(|character| ('\u{2801}'..='\u{28FF}').contains(&character)));
+}
+
+#[test]
+fn browse_needs_terminal() {
+	let result = pockettopo(&["browse", &fixture("trips.top")]);
+	assert!(!result.status.success());
+
+	let error = String::from_utf8(result.stderr).unwrap();
+	assert!(error.contains("terminal"));
+}
+
+fn pockettopo(args: &[&str]) -> Output {
+	Command::new(env!("CARGO_BIN_EXE_pockettopo"))
+		.args(args)
+		.output()
+		.unwrap()
+}
+
+fn fixture(fixture: &str) -> String {
+	let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+	path.push("tests/fixtures");
+	path.push(fixture);
+
+	path.to_str().unwrap().to_owned()
+}
+
+fn temporary(name: &str) -> String {
+	let mut path = env::temp_dir();
+	path.push(format!("pocket-topo-{}-{name}", std::process::id()));
+
+	path.to_str().unwrap().to_owned()
+}
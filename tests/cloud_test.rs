@@ -0,0 +1,149 @@
+mod common;
+
+use pocket_topo::{
+	builder::DocumentBuilder,
+	cloud::{self, Kind, LAS_SPLAY, LAS_STATION},
+	geodesy::Crs,
+	parser::Document,
+	reduction::Position,
+};
+
+use common::{date, passage};
+
+// 1.0 → 1.1 east without a trip, and a splay up at 1.1, which is the reference
+fn survey() -> Document<'static> {
+	passage(10.0)
+		.trip(date(2024, 5, 1), 0.0, "")
+		.splay("1.1", 2.0, 0.0, 90.0)
+		.reference("1.1", 500_000.0, 5_200_000.0, 1_200.0, "")
+		.build()
+		.unwrap()
+}
+
+#[test]
+fn georeferences_points() {
+	let points = cloud::points(&survey(), &Default::default());
+
+	let kinds: Vec<(String, Kind, usize, i16)> = points
+		.iter()
+		.map(|point| {
+			(
+				point.station.to_string(),
+				point.kind,
+				point.shot,
+				point.trip_index,
+			)
+		})
+		.collect();
+	assert_eq!(
+		kinds,
+		[
+			("1.0".to_owned(), Kind::Station, 0, -1),
+			("1.1".to_owned(), Kind::Station, 0, -1),
+			("1.1".to_owned(), Kind::Splay, 1, 0),
+		]
+	);
+
+	let expected = [
+		(499_990.0, 5_200_000.0, 1_200.0),
+		(500_000.0, 5_200_000.0, 1_200.0),
+		(500_000.0, 5_200_000.0, 1_202.0),
+	];
+	for (point, (east, north, up)) in points.iter().zip(expected) {
+		assert!((point.position - Position { east, north, up }).length() < 1e-6);
+	}
+}
+
+#[test]
+fn writes_ply() {
+	let points = cloud::points(&survey(), &Default::default());
+
+	let mut ply = Vec::new();
+	cloud::write_ply(&points, &mut ply).unwrap();
+
+	let header = b"end_header\n";
+	let end = ply.windows(header.len()).position(|w| w == header).unwrap() + header.len();
+	assert!(String::from_utf8_lossy(&ply[..end]).contains("element vertex 3\n"));
+	assert_eq!(ply.len() - end, 3 * (3 * 8 + 2 + 4 + 1));
+
+	let east = f64::from_le_bytes(ply[end..end + 8].try_into().unwrap());
+	assert!((east - 499_990.0).abs() < 1e-6);
+}
+
+#[test]
+fn writes_las() {
+	let points = cloud::points(&survey(), &Default::default());
+
+	let mut las = Vec::new();
+	cloud::write_las(&points, None, &mut las).unwrap();
+
+	let u16_at = |offset: usize| u16::from_le_bytes(las[offset..offset + 2].try_into().unwrap());
+	let u32_at = |offset: usize| u32::from_le_bytes(las[offset..offset + 4].try_into().unwrap());
+	let f64_at = |offset: usize| f64::from_le_bytes(las[offset..offset + 8].try_into().unwrap());
+
+	assert_eq!(&las[..4], b"LASF");
+	assert_eq!(las[24..26], [1, 4]);
+	assert_eq!(u16_at(94), 375);
+	assert_eq!(las[104], 6);
+	assert_eq!(u16_at(105), 36);
+	assert_eq!(u32_at(247), 3);
+
+	// the offsets are the minimum, and the maximum altitude is the splay's end
+	assert_eq!(f64_at(155), 499_990.0);
+	assert_eq!(f64_at(163), 5_200_000.0);
+	assert_eq!(f64_at(171), 1_200.0);
+	assert_eq!(f64_at(211), 1_202.0);
+
+	let points_at = u32_at(96) as usize;
+	assert_eq!(las.len(), points_at + 3 * 36);
+
+	// the splay, 10 m east and 2 m up from the offset, with its trip and shot
+	let splay = &las[points_at + 2 * 36..];
+	let i32_at = |offset: usize| i32::from_le_bytes(splay[offset..offset + 4].try_into().unwrap());
+	assert_eq!([i32_at(0), i32_at(4), i32_at(8)], [10_000, 0, 2_000]);
+	assert_eq!(splay[16], LAS_SPLAY);
+	assert_eq!(splay[30..32], 0_i16.to_le_bytes());
+	assert_eq!(splay[32..36], 1_u32.to_le_bytes());
+
+	assert_eq!(las[points_at + 16], LAS_STATION);
+}
+
+#[test]
+fn writes_las_crs() {
+	let points = cloud::points(&survey(), &Default::default());
+	let crs: Crs = "33N".parse().unwrap();
+
+	let mut las = Vec::new();
+	cloud::write_las(&points, Some(crs), &mut las).unwrap();
+
+	let u32_at = |offset: usize| u32::from_le_bytes(las[offset..offset + 4].try_into().unwrap());
+	assert_eq!(u32_at(100), 2);
+
+	// after the extra bytes record
+	let record = 375 + 54 + 2 * 192;
+	assert_eq!(&las[record + 2..record + 17], b"LASF_Projection");
+	assert_eq!(las[record + 18..record + 20], 2112_u16.to_le_bytes());
+
+	let length = u16::from_le_bytes(las[record + 20..record + 22].try_into().unwrap()) as usize;
+	let wkt = &las[record + 54..record + 54 + length];
+	assert_eq!(wkt.last(), Some(&0));
+	assert!(wkt.ends_with(b"AUTHORITY[\"EPSG\",\"32633\"]]\0"));
+
+	let points_at = u32_at(96) as usize;
+	assert_eq!(points_at, record + 54 + length);
+	assert_eq!(las.len(), points_at + 3 * 36);
+}
+
+#[test]
+fn leaves_points_without_reference_local() {
+	let document = DocumentBuilder::new()
+		.shot("1.0", "1.1", 10.0, 90.0, 0.0)
+		.build()
+		.unwrap();
+
+	let points: Vec<Position> = cloud::points(&document, &Default::default())
+		.iter()
+		.map(|point| point.position)
+		.collect();
+	assert_eq!(points[0], Position::default());
+}
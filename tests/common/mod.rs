@@ -0,0 +1,49 @@
+// Helpers shared by the integration tests, each of which only uses some.
+#![allow(dead_code)]
+
+use chrono::{NaiveDate, NaiveDateTime};
+use pocket_topo::{
+	builder::DocumentBuilder, parser::Document, Color, Element, Point, Polygon, StationId,
+};
+
+pub fn station(station: &str) -> StationId {
+	station.parse().unwrap()
+}
+
+pub fn point(x: i32, y: i32) -> Point {
+	Point { x, y }
+}
+
+// midnight at the start of a day
+pub fn date(year: i32, month: u32, day: u32) -> NaiveDateTime {
+	NaiveDate::from_ymd_opt(year, month, day)
+		.unwrap()
+		.and_hms_opt(0, 0, 0)
+		.unwrap()
+}
+
+// a leg of `length` m east, from 1.0 to 1.1
+pub fn passage(length: f64) -> DocumentBuilder<'static> {
+	DocumentBuilder::new().shot("1.0", "1.1", length, 90.0, 0.0)
+}
+
+pub fn build(builder: DocumentBuilder) -> Document {
+	builder.build().expect("invalid document")
+}
+
+// the document with a polygon in its outline and sideview from corner to
+// corner of the drawings' coordinates
+pub fn with_extremes(mut document: Document) -> Document {
+	let polygon = Element::Polygon(Polygon {
+		points: Box::new([point(i32::MIN, i32::MIN), point(i32::MAX, i32::MAX)]),
+		color: Color::Black,
+	});
+
+	for drawing in [&mut document.outline, &mut document.sideview] {
+		let mut elements = drawing.elements.to_vec();
+		elements.push(polygon.clone());
+		drawing.elements = elements.into_boxed_slice();
+	}
+
+	document
+}
@@ -0,0 +1,85 @@
+mod common;
+
+use pocket_topo::{
+	builder::DocumentBuilder,
+	diff::{diff, Change, Item, Tolerances},
+};
+
+use common::{build, date};
+
+fn survey(distance: f64, comment: &str) -> DocumentBuilder<'_> {
+	DocumentBuilder::new()
+		.reference("1.0", 24.0, 42.0, 50.0, "entrance")
+		.trip(date(2022, 10, 22), 3.45, comment)
+		.shot("1.0", "1.1", distance, 10.0, 30.0)
+		.splay("1.1", 1.5, 90.0, 0.0)
+		.splay("1.1", 2.5, 270.0, 0.0)
+		.outline_polygon("black", &[(0.0, 0.0), (1.0, 1.0)])
+}
+
+#[test]
+fn ignores_noise() {
+	let old = build(survey(12.345, "trip"));
+	let new = build(survey(12.349, "trip"));
+
+	assert_eq!(diff(&old, &new, &Tolerances::default()), []);
+
+	let tolerances = Tolerances {
+		distance: 0.001,
+		angle: 0.0,
+	};
+
+	assert_eq!(
+		diff(&old, &new, &tolerances),
+		[Change::Modified(Item::Shot(0), Item::Shot(0))]
+	);
+}
+
+#[test]
+fn reports_changes() {
+	let old = build(survey(12.345, "trip").shot("1.1", "1.2", 5.0, 0.0, 0.0));
+
+	let new = build(
+		survey(12.345, "updated trip")
+			.splay("1.1", 3.5, 180.0, 0.0)
+			.reference("1.2", 0.0, 0.0, 0.0, "")
+			.outline_polygon("black", &[(0.5, 0.5), (1.5, 1.5)])
+			.outline_polygon("red", &[(0.0, 0.0), (1.0, 1.0)]),
+	);
+
+	assert_eq!(
+		diff(&old, &new, &Tolerances::default()),
+		[
+			Change::Modified(Item::Trip(0), Item::Trip(0)),
+			Change::Removed(Item::Shot(3)),
+			Change::Added(Item::Shot(3)),
+			Change::Added(Item::Reference(1)),
+			Change::Added(Item::Outline(1)),
+			Change::Added(Item::Outline(2)),
+		]
+	);
+}
+
+#[test]
+fn matches_closest_readings() {
+	let old = build(survey(12.345, "trip"));
+
+	// the splays are reordered, and one is remeasured
+	let new = build(
+		DocumentBuilder::new()
+			.reference("1.0", 24.0, 42.0, 50.0, "entrance")
+			.trip(date(2022, 10, 22), 3.45, "trip")
+			.shot("1.0", "1.1", 12.345, 10.0, 30.0)
+			.splay("1.1", 2.6, 270.0, 0.0)
+			.splay("1.1", 1.5, 90.0, 0.0)
+			.outline_polygon("black", &[(0.0, 0.0), (1.0, 1.2)]),
+	);
+
+	assert_eq!(
+		diff(&old, &new, &Tolerances::default()),
+		[
+			Change::Modified(Item::Shot(2), Item::Shot(1)),
+			Change::Modified(Item::Outline(0), Item::Outline(0)),
+		]
+	);
+}
@@ -0,0 +1,70 @@
+mod common;
+
+use std::collections::BTreeMap;
+
+use pocket_topo::{
+	builder::DocumentBuilder,
+	extended::{extended_elevation, Direction},
+	Point,
+};
+
+use common::{point, station};
+
+#[test]
+fn unrolls_centerline() {
+	let document = DocumentBuilder::new()
+		.shot("1.0", "1.1", 10.0, 90.0, 0.0)
+		.splay("1.0", 1.0, 270.0, 0.0)
+		.shot("1.1", "1.2", 5.0, 0.0, -30.0)
+		.flipped()
+		.splay("1.2", 2.0, 0.0, 0.0)
+		.shot("1.3", "1.2", 3.0, 180.0, 90.0)
+		.build()
+		.unwrap();
+
+	let elevation = extended_elevation(&document, &BTreeMap::new());
+
+	assert_eq!(elevation.stations[&station("1.0")], point(0, 0));
+	assert_eq!(elevation.stations[&station("1.1")], point(10000, 0));
+	// flipped legs go left
+	assert_eq!(elevation.stations[&station("1.2")], point(5670, 2500));
+	// the backsight goes up from 1.3 to 1.2, so 1.3 is below
+	assert_eq!(elevation.stations[&station("1.3")], point(5670, 5500));
+
+	let directions: Vec<_> = elevation.legs.iter().map(|leg| leg.direction).collect();
+	assert_eq!(
+		directions,
+		[Direction::Right, Direction::Left, Direction::Right]
+	);
+
+	// splays are projected along the leg reaching their station
+	assert_eq!(elevation.splays[0].to, point(-1000, 0));
+	assert_eq!(elevation.splays[1].from, point(5670, 2500));
+	assert_eq!(elevation.splays[1].to, point(3670, 2500));
+}
+
+#[test]
+fn overrides_directions() {
+	let document = DocumentBuilder::new()
+		.shot("1.0", "1.1", 10.0, 90.0, 0.0)
+		.shot("1.1", "1.2", 5.0, 0.0, 0.0)
+		.build()
+		.unwrap();
+
+	let directions = BTreeMap::from([
+		((station("1.1"), station("1.0")), Direction::Right),
+		((station("1.1"), station("1.2")), Direction::Vertical),
+	]);
+	let elevation = extended_elevation(&document, &directions);
+
+	// overrides apply to legs in either direction
+	assert_eq!(elevation.legs[0].direction, Direction::Left);
+	assert_eq!(
+		elevation.stations[&station("1.1")],
+		Point { x: -10000, y: 0 }
+	);
+	assert_eq!(
+		elevation.stations[&station("1.2")],
+		Point { x: -10000, y: 0 }
+	);
+}
@@ -0,0 +1,133 @@
+use pocket_topo::{
+	builder::DocumentBuilder,
+	geodesy::{Crs, Hemisphere, InvalidCrs, LatLon, Utm},
+};
+
+fn utm(zone: u8, hemisphere: Hemisphere) -> Utm {
+	Utm::new(zone, hemisphere).unwrap()
+}
+
+#[test]
+fn projects_to_utm() {
+	// the Eiffel Tower
+	let position = LatLon {
+		latitude: 48.8582,
+		longitude: 2.2945,
+	};
+	let grid = utm(31, Hemisphere::North).from_wgs84(position);
+
+	assert!((grid.east - 448_251.795).abs() < 1e-3);
+	assert!((grid.north - 5_411_932.678).abs() < 1e-3);
+
+	// west of the central meridian, grid north is west of true north
+	assert!((grid.convergence + 0.5313).abs() < 1e-4);
+	assert!((grid.scale_factor - 0.999_633).abs() < 1e-6);
+}
+
+#[test]
+fn projects_central_meridian() {
+	let zone = utm(31, Hemisphere::North);
+
+	let equator = zone.from_wgs84(LatLon {
+		latitude: 0.0,
+		longitude: 3.0,
+	});
+	assert_eq!((equator.east, equator.north), (500_000.0, 0.0));
+	assert_eq!(equator.convergence, 0.0);
+	assert!((equator.scale_factor - 0.9996).abs() < 1e-12);
+
+	// 0.9996 of the meridian arc to 60°
+	let north = zone.from_wgs84(LatLon {
+		latitude: 60.0,
+		longitude: 3.0,
+	});
+	assert!((north.north - 6_651_411.190).abs() < 1e-3);
+}
+
+#[test]
+fn round_trips_southern_hemisphere() {
+	let zone = utm(56, Hemisphere::South);
+	let position = LatLon {
+		latitude: -33.8568,
+		longitude: 151.2153,
+	};
+
+	let grid = zone.from_wgs84(position);
+	assert!(grid.north > 6_000_000.0 && grid.north < 7_000_000.0);
+	assert!(grid.convergence > 0.0);
+
+	let back = zone.to_wgs84(grid.east, grid.north);
+	assert!((back.latitude - position.latitude).abs() < 1e-9);
+	assert!((back.longitude - position.longitude).abs() < 1e-9);
+}
+
+#[test]
+fn round_trips_across_zone() {
+	let zone = utm(33, Hemisphere::North);
+
+	for latitude in [0.5, 30.0, 60.0, 80.0] {
+		for longitude in [12.0, 14.5, 15.0, 17.9] {
+			let position = LatLon {
+				latitude,
+				longitude,
+			};
+			let grid = zone.from_wgs84(position);
+			let back = zone.to_wgs84(grid.east, grid.north);
+
+			assert!((back.latitude - latitude).abs() < 1e-9, "{position:?}");
+			assert!((back.longitude - longitude).abs() < 1e-9, "{position:?}");
+		}
+	}
+}
+
+#[test]
+fn finds_zones() {
+	let zone = |latitude, longitude| {
+		Utm::zone_of(LatLon {
+			latitude,
+			longitude,
+		})
+	};
+
+	assert_eq!(zone(48.8582, 2.2945), utm(31, Hemisphere::North));
+	assert_eq!(zone(-33.8568, 151.2153), utm(56, Hemisphere::South));
+	assert_eq!(zone(0.0, 180.0), utm(1, Hemisphere::North));
+	assert_eq!(zone(60.4, 5.3), utm(32, Hemisphere::North));
+	assert_eq!(zone(78.2, 15.6), utm(33, Hemisphere::North));
+}
+
+#[test]
+fn parses_crs() {
+	let north = Crs::Utm(utm(33, Hemisphere::North));
+	assert_eq!("33N".parse(), Ok(north));
+	assert_eq!("33n".parse(), Ok(north));
+	assert_eq!("EPSG:32633".parse(), Ok(north));
+	assert_eq!(
+		"epsg:32733".parse(),
+		Ok(Crs::Utm(utm(33, Hemisphere::South)))
+	);
+
+	assert_eq!(north.epsg(), 32633);
+	assert_eq!(north.to_string(), "UTM 33N");
+
+	for invalid in ["", "33", "0N", "61S", "EPSG:4326", "EPSG:32661", "N"] {
+		assert_eq!(invalid.parse::<Crs>(), Err(InvalidCrs(invalid.to_owned())));
+	}
+}
+
+#[test]
+fn locates_references() {
+	let crs = Crs::Utm(utm(31, Hemisphere::North));
+	let document = DocumentBuilder::new()
+		.reference("1.0", 448_251.795, 5_411_932.678, 300.0, "")
+		.crs(crs)
+		.build()
+		.unwrap();
+
+	assert_eq!(document.crs, Some(crs));
+
+	let (position, grid) = crs.reference(&document.references[0]);
+	assert!((position.latitude - 48.8582).abs() < 1e-8);
+	assert!((position.longitude - 2.2945).abs() < 1e-8);
+	assert!((grid.convergence + 0.5313).abs() < 1e-4);
+}
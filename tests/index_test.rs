@@ -0,0 +1,77 @@
+use std::{fs::File, io::Read, path::PathBuf};
+
+use pocket_topo::{index, parser};
+
+#[test]
+fn indexes_sections() {
+	let contents = fixture("empty.top");
+
+	let index = index::index(&contents).expect("invalid document");
+
+	let sections = index.sections();
+	assert_eq!(sections.trips, 4..8);
+	assert_eq!(sections.shots, 8..12);
+	assert_eq!(sections.references, 12..16);
+	assert_eq!(sections.mapping, 16..28);
+	assert_eq!(sections.outline, 28..41);
+	assert_eq!(sections.sideview, 41..54);
+}
+
+#[test]
+fn decodes_sections_like_parse() {
+	for name in [
+		"comments.top",
+		"default.top",
+		"empty.top",
+		"outline.top",
+		"references.top",
+		"trips.top",
+		"unknown.top",
+	] {
+		let contents = fixture(name);
+
+		let document = parser::parse(&contents).expect("invalid document");
+		let index = index::index(&contents).expect("invalid document");
+
+		assert_eq!(index.trips().unwrap(), document.trips, "{name} differs");
+		assert_eq!(index.shots().unwrap(), document.shots, "{name} differs");
+		assert_eq!(
+			index.references().unwrap(),
+			document.references,
+			"{name} differs"
+		);
+		assert_eq!(index.mapping().unwrap(), document.mapping, "{name} differs");
+		assert_eq!(index.outline().unwrap(), document.outline, "{name} differs");
+		assert_eq!(
+			index.sideview().unwrap(),
+			document.sideview,
+			"{name} differs"
+		);
+	}
+}
+
+#[test]
+fn defers_decoding_comments() {
+	let contents = fixture("encoding.top");
+
+	let index = index::index(&contents).expect("invalid index");
+
+	assert!(matches!(
+		index.trips(),
+		Err(parser::ParseError::Utf8Error(_))
+	));
+	assert_eq!(index.shots().unwrap().len(), 4);
+}
+
+fn fixture(fixture: &str) -> Vec<u8> {
+	let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+	path.push("tests/fixtures");
+	path.push(fixture);
+
+	let mut file = File::open(path).unwrap();
+
+	let mut buffer = Vec::new();
+	file.read_to_end(&mut buffer).unwrap();
+
+	buffer
+}
@@ -0,0 +1,70 @@
+mod common;
+
+use pocket_topo::{
+	builder::DocumentBuilder,
+	lrud::{lruds, Lrud, Options, Selection},
+};
+
+use common::station;
+
+fn assert_close(actual: Lrud, expected: Lrud) {
+	let close = (actual.left - expected.left).abs() < 1e-3
+		&& (actual.right - expected.right).abs() < 1e-3
+		&& (actual.up - expected.up).abs() < 1e-3
+		&& (actual.down - expected.down).abs() < 1e-3
+		&& (actual.direction - expected.direction).abs() < 1e-3;
+
+	assert!(close, "{actual:?} != {expected:?}");
+}
+
+fn survey() -> DocumentBuilder<'static> {
+	// the passage turns from east to north at 1.1
+	DocumentBuilder::new()
+		.shot("1.0", "1.1", 10.0, 90.0, 0.0)
+		.shot("1.1", "1.2", 10.0, 0.0, 0.0)
+		.splay("1.1", 2.0, 315.0, 0.0)
+		.splay("1.1", 3.0, 335.0, 0.0)
+		.splay("1.1", 1.0, 135.0, 0.0)
+		.splay("1.1", 1.5, 0.0, 80.0)
+		.splay("1.1", 0.5, 0.0, -90.0)
+		.splay("1.1", 4.0, 45.0, 0.0)
+		.splay("1.2", 1.0, 270.0, 0.0)
+}
+
+#[test]
+fn bisects_legs() {
+	let document = survey().build().unwrap();
+	let lruds = lruds(&document, &Options::default());
+
+	// the splay ahead isn't towards a wall
+	let expected = Lrud {
+		left: 3.0 * 20.0_f64.to_radians().cos(),
+		right: 1.0,
+		up: 1.5 * 80.0_f64.to_radians().sin(),
+		down: 0.5,
+		direction: 45.0,
+	};
+	assert_close(lruds[&station("1.1")], expected);
+
+	// at the end of the survey, the passage faces along the last leg
+	let expected = Lrud {
+		left: 1.0,
+		direction: 0.0,
+		..Lrud::default()
+	};
+	assert_close(lruds[&station("1.2")], expected);
+
+	assert!(!lruds.contains_key(&station("1.0")));
+}
+
+#[test]
+fn selects_perpendicular_splays() {
+	let document = survey().build().unwrap();
+	let options = Options {
+		selection: Selection::Perpendicular,
+		..Options::default()
+	};
+
+	let lrud = lruds(&document, &options)[&station("1.1")];
+	assert!((lrud.left - 2.0).abs() < 1e-3, "{lrud:?}");
+}
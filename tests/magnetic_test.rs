@@ -0,0 +1,148 @@
+use chrono::NaiveDate;
+use pocket_topo::{
+	builder::DocumentBuilder,
+	geodesy::{Crs, LatLon},
+	magnetic::{self, Model, ModelError},
+	units,
+};
+
+fn position(latitude: f64, longitude: f64) -> LatLon {
+	LatLon {
+		latitude,
+		longitude,
+	}
+}
+
+#[test]
+fn computes_field() {
+	let model = Model::wmm2020();
+	assert_eq!(model.name, "WMM-2020");
+	assert_eq!(model.epoch, 2020.0);
+
+	// from the WMM2020 report's test values, at sea level
+	for (year, latitude, longitude, declination, inclination) in [
+		(2020.0, 80.0, 0.0, -1.28, 83.14),
+		(2020.0, 0.0, 120.0, 0.16, -15.42),
+		(2020.0, -80.0, 240.0, 69.36, -72.20),
+		(2022.5, 80.0, 0.0, 0.00, 83.19),
+		(2022.5, 0.0, 120.0, -0.06, -15.24),
+		(2022.5, -80.0, 240.0, 69.13, -72.09),
+	] {
+		let field = model.field(position(latitude, longitude), 0.0, year);
+		assert!((field.declination - declination).abs() < 0.01, "{field:?}");
+		assert!((field.inclination - inclination).abs() < 0.01, "{field:?}");
+	}
+
+	let field = model.field(position(80.0, 0.0), 0.0, 2020.0);
+	assert!((field.north - 6570.4).abs() < 1.0);
+	assert!((field.down - 54606.0).abs() < 1.0);
+}
+
+#[test]
+fn selects_model_by_date() {
+	let models = Model::embedded();
+	let names: Vec<&str> = models.iter().map(|model| model.name.as_str()).collect();
+	assert_eq!(names, ["WMM-2020", "WMM-2025"]);
+
+	assert_eq!(magnetic::select(&models, 2018.0), Some(0));
+	assert_eq!(magnetic::select(&models, 2024.9), Some(0));
+	assert_eq!(magnetic::select(&models, 2025.0), Some(1));
+	assert_eq!(magnetic::select(&models, 2026.8), Some(1));
+	assert_eq!(magnetic::select(&[], 2026.8), None);
+
+	assert!(models[1].is_valid(2026.8));
+
+	// the WMM2025 agrees with the WMM2020's forecast to within its accuracy
+	for (latitude, longitude) in [(47.5, 13.7), (40.0, -105.0), (-33.9, 151.2)] {
+		let position = position(latitude, longitude);
+		let forecast = models[0].field(position, 0.0, 2025.0).declination;
+		let declination = models[1].field(position, 0.0, 2025.0).declination;
+		assert!((forecast - declination).abs() < 0.5);
+	}
+}
+
+#[test]
+fn parses_coefficients() {
+	let model = Model::parse("2025.0 TEST\n1 0 -100.0 0.0 10.0 0.0\n999999999999\n").unwrap();
+	assert_eq!(model.epoch, 2025.0);
+	assert!(model.is_valid(2029.9));
+	assert!(!model.is_valid(2030.0));
+
+	// an axial dipole points to true north
+	let field = model.field(position(45.0, 10.0), 0.0, 2026.0);
+	assert!(field.declination.abs() < 1e-9);
+	assert!(field.inclination > 0.0);
+
+	assert_eq!(Model::parse(""), Err(ModelError::Empty));
+	assert_eq!(
+		Model::parse("TEST 2025.0\n"),
+		Err(ModelError::InvalidHeader("TEST 2025.0".to_owned()))
+	);
+	assert_eq!(
+		Model::parse("2025.0 TEST\n1 2 0 0 0 0\n"),
+		Err(ModelError::InvalidLine(2))
+	);
+	assert_eq!(
+		Model::parse("2025.0 TEST\n1 0 x 0 0 0\n"),
+		Err(ModelError::InvalidLine(2))
+	);
+}
+
+#[test]
+fn converts_decimal_year() {
+	let time = |year, month, day| {
+		NaiveDate::from_ymd_opt(year, month, day)
+			.unwrap()
+			.and_hms_opt(0, 0, 0)
+			.unwrap()
+	};
+
+	assert_eq!(magnetic::decimal_year(time(2022, 1, 1)), 2022.0);
+	assert_eq!(magnetic::decimal_year(time(2024, 7, 2)), 2024.5);
+}
+
+#[test]
+fn fills_declinations() {
+	let time = |day| {
+		NaiveDate::from_ymd_opt(2022, 10, day)
+			.unwrap()
+			.and_hms_opt(0, 0, 0)
+			.unwrap()
+	};
+
+	// near Hallstatt
+	let mut document = DocumentBuilder::new()
+		.crs("33N".parse::<Crs>().unwrap())
+		.trip(time(15), 0.0, "")
+		.trip(time(22), 3.0, "")
+		.shot("1.0", "1.1", 10.0, 0.0, 0.0)
+		.reference("1.0", 405_000.0, 5_270_000.0, 1500.0, "")
+		.build()
+		.unwrap();
+
+	let (location, height) = magnetic::reference_location(&document).unwrap();
+	assert!((location.latitude - 47.57).abs() < 0.01);
+	assert!((location.longitude - 13.74).abs() < 0.01);
+	assert_eq!(height, 1500.0);
+
+	let models = Model::embedded();
+	let declinations = magnetic::declinations(&document, &models, location, height);
+	assert_eq!(declinations.len(), 2);
+	assert!(declinations.iter().all(|declination| declination.valid));
+	assert!(declinations
+		.iter()
+		.all(|declination| declination.source == 0));
+	assert!((declinations[0].model - 4.4).abs() < 0.1);
+	assert!((declinations[1].difference() - (declinations[1].model - 3.0)).abs() < 0.01);
+
+	// only the trip without a declination
+	assert_eq!(magnetic::fill(&mut document, &declinations, false), [0]);
+	let filled = units::degrees(document.trips[0].declination);
+	assert!((filled - declinations[0].model).abs() < 0.01);
+	assert_eq!(
+		document.trips[1].declination,
+		units::units(3.0).round() as i16
+	);
+
+	assert_eq!(magnetic::fill(&mut document, &declinations, true), [1]);
+}
@@ -0,0 +1,172 @@
+mod common;
+
+use pocket_topo::{
+	builder::DocumentBuilder,
+	merge::{merge, Collisions, Equate, MergeError, MergeOptions},
+	Element, Point,
+};
+
+use common::{build, date, station};
+
+fn survey(comment: &str) -> DocumentBuilder<'_> {
+	DocumentBuilder::new()
+		.reference("1.0", 24.0, 42.0, 50.0, "entrance")
+		.trip(date(2022, 10, 22), 0.0, comment)
+		.shot("1.0", "1.1", 10.0, 90.0, 0.0)
+		.splay("1.1", 1.5, 0.0, 0.0)
+}
+
+#[test]
+fn renumbers_collisions() {
+	let first = build(survey("first"));
+	let second = build(survey("second").shot("1.1", "3.0", 2.0, 0.0, 0.0));
+
+	let merged = merge(&[first, second], &MergeOptions::default()).unwrap();
+
+	assert_eq!(merged.trips.len(), 2);
+	assert_eq!(merged.trips[1].comment, "second");
+
+	let shots: Vec<_> = merged
+		.shots
+		.iter()
+		.map(|shot| (shot.from, shot.to, shot.trip_index))
+		.collect();
+
+	// series 1 collides and moves past series 3
+	assert_eq!(
+		shots,
+		[
+			(Some(station("1.0")), Some(station("1.1")), 0),
+			(Some(station("1.1")), None, 0),
+			(Some(station("4.0")), Some(station("4.1")), 1),
+			(Some(station("4.1")), None, 1),
+			(Some(station("4.1")), Some(station("3.0")), 1),
+		]
+	);
+
+	let references: Vec<_> = merged.references.iter().map(|r| r.station).collect();
+	assert_eq!(references, [Some(station("1.0")), Some(station("4.0"))]);
+}
+
+#[test]
+fn renumbers_plain_stations() {
+	let first = build(DocumentBuilder::new().shot("1", "2", 1.0, 0.0, 0.0));
+	let second = build(DocumentBuilder::new().shot("2", "3", 1.0, 0.0, 0.0));
+
+	let merged = merge(&[first, second], &MergeOptions::default()).unwrap();
+
+	let shots: Vec<_> = merged
+		.shots
+		.iter()
+		.map(|shot| (shot.from, shot.to))
+		.collect();
+	assert_eq!(
+		shots,
+		[
+			(Some(station("1")), Some(station("2"))),
+			(Some(station("3")), Some(station("4"))),
+		]
+	);
+}
+
+#[test]
+fn equates_stations() {
+	let first = build(survey("first"));
+	let second = build(survey("second"));
+
+	let options = MergeOptions {
+		collisions: Collisions::Equate,
+		equates: Vec::new(),
+	};
+	let merged = merge(&[first, second], &options).unwrap();
+
+	assert_eq!(merged.shots[2].from, Some(station("1.0")));
+	assert_eq!(merged.shots[2].trip_index, 1);
+
+	// the identical references are unioned
+	assert_eq!(merged.references.len(), 1);
+}
+
+#[test]
+fn translates_drawings() {
+	let first = build(
+		survey("first")
+			.outline_polygon("black", &[(0.0, 0.0), (1.0, 1.0)])
+			.sideview_polygon("black", &[(0.0, 0.0), (10.0, 0.0)]),
+	);
+	let second = build(
+		survey("second")
+			.outline_polygon("red", &[(0.0, 0.0), (1.0, 1.0)])
+			.outline_cross_section("1.1", (0.5, 0.5), None)
+			.sideview_polygon("red", &[(0.0, 0.0), (10.0, 0.0)]),
+	);
+
+	// the second survey starts at the end of the first
+	let options = MergeOptions {
+		collisions: Collisions::Renumber,
+		equates: vec![Equate {
+			document: 1,
+			station: station("1.0"),
+			to: station("1.1"),
+		}],
+	};
+	let merged = merge(&[first, second], &options).unwrap();
+
+	assert_eq!(merged.shots[2].from, Some(station("1.1")));
+	assert_eq!(merged.shots[2].to, Some(station("2.1")));
+
+	let Element::Polygon(polygon) = &merged.outline.elements[1] else {
+		panic!("not a polygon");
+	};
+	assert_eq!(
+		&*polygon.points,
+		[Point { x: 10000, y: 0 }, Point { x: 11000, y: 1000 }]
+	);
+
+	let Element::CrossSection(cross_section) = &merged.outline.elements[2] else {
+		panic!("not a cross-section");
+	};
+	assert_eq!(cross_section.station, station("2.1"));
+	assert_eq!(cross_section.position, Point { x: 10500, y: 500 });
+
+	let Element::Polygon(polygon) = &merged.sideview.elements[1] else {
+		panic!("not a polygon");
+	};
+	assert_eq!(
+		&*polygon.points,
+		[Point { x: 10000, y: 0 }, Point { x: 20000, y: 0 }]
+	);
+}
+
+#[test]
+fn requires_connected_drawings() {
+	let first = build(survey("first"));
+	let second = build(survey("second").outline_polygon("red", &[(0.0, 0.0)]));
+
+	assert_eq!(
+		merge(&[first, second], &MergeOptions::default()),
+		Err(MergeError::Disconnected(1))
+	);
+
+	assert_eq!(merge(&[], &MergeOptions::default()), Err(MergeError::Empty));
+}
+
+#[test]
+fn limits_trips() {
+	let trips = |count: usize| {
+		let mut builder = survey("");
+		for _ in 1..count {
+			builder = builder.trip(date(2022, 10, 23), 0.0, "");
+		}
+		build(builder.shot("1.1", "1.2", 1.0, 0.0, 0.0))
+	};
+
+	// the last shot is in the last possible trip
+	let documents = [trips(i16::MAX as usize), trips(1)];
+	let merged = merge(&documents, &MergeOptions::default()).unwrap();
+	assert_eq!(merged.shots.last().unwrap().trip_index, i16::MAX);
+
+	let documents = [trips(i16::MAX as usize), trips(2)];
+	let result = merge(&documents, &MergeOptions::default());
+	assert_eq!(result.unwrap_err(), MergeError::TooManyTrips(32769));
+}
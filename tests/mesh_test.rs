@@ -0,0 +1,157 @@
+mod common;
+
+use std::{f64::consts::PI, io::ErrorKind};
+
+use pocket_topo::{
+	mesh::{self, depth_colour, Options},
+	parser::Document,
+};
+
+use common::passage;
+
+// a straight passage east, 2 m wide and high
+fn survey() -> Document<'static> {
+	let mut builder = passage(10.0);
+
+	for station in ["1.0", "1.1"] {
+		builder = builder
+			.splay(station, 1.0, 0.0, 0.0)
+			.splay(station, 1.0, 180.0, 0.0)
+			.splay(station, 1.0, 0.0, 90.0)
+			.splay(station, 1.0, 0.0, -90.0);
+	}
+
+	builder.build().unwrap()
+}
+
+#[test]
+fn models_passage() {
+	let options = Options {
+		segments: 8,
+		caps: true,
+		..Options::default()
+	};
+	let mesh = mesh::mesh(&survey(), &options);
+
+	// two rings and two cap centres
+	assert_eq!(mesh.vertices.len(), 18);
+	// the tube and the caps
+	assert_eq!(mesh.triangles.len(), 16 + 16);
+
+	// the ring around 1.0 starts above it
+	let first = mesh.vertices[0];
+	assert!((first.up - 1.0).abs() < 1e-9 && first.north.abs() < 1e-9);
+
+	// all the vertices are on the walls
+	for vertex in mesh.vertices.iter() {
+		let radius = (vertex.north * vertex.north + vertex.up * vertex.up).sqrt();
+		assert!(radius < 1.0 + 1e-9);
+	}
+
+	// the triangles face outwards
+	for triangle in mesh.triangles.iter() {
+		let [a, b, c] = triangle.map(|vertex| mesh.vertices[vertex as usize]);
+		let (u, v) = (b - a, c - a);
+		let normal_north = u.up * v.east - u.east * v.up;
+		let normal_up = u.east * v.north - u.north * v.east;
+		let centroid = (a + b + c) * (1.0 / 3.0);
+		let outwards = normal_north * centroid.north + normal_up * centroid.up;
+		let normal_east = u.north * v.up - u.up * v.north;
+		let cap = centroid.east * normal_east;
+		assert!(outwards > 0.0 || cap > 0.0);
+	}
+}
+
+#[test]
+fn joins_rings_facing_opposite_ways() {
+	// 1.2 is surveyed back to 1.1, so it faces west while 1.1 faces east
+	let mut builder = passage(10.0).shot("1.2", "1.1", 10.0, 270.0, 0.0);
+	for station in ["1.0", "1.1", "1.2"] {
+		builder = builder
+			.splay(station, 1.0, 0.0, 0.0)
+			.splay(station, 1.0, 180.0, 0.0)
+			.splay(station, 1.0, 0.0, 90.0)
+			.splay(station, 1.0, 0.0, -90.0);
+	}
+
+	// an odd number of segments, so no vertex is opposite the first
+	let options = Options {
+		segments: 5,
+		caps: false,
+		..Options::default()
+	};
+	let mesh = mesh::mesh(&builder.build().unwrap(), &options);
+	assert_eq!(mesh.triangles.len(), 2 * 2 * 5);
+
+	// without a twist, the tube is two pentagonal prisms 10 m long
+	let area: f64 = mesh
+		.triangles
+		.iter()
+		.map(|triangle| {
+			let [a, b, c] = triangle.map(|vertex| mesh.vertices[vertex as usize]);
+			(b - a).cross(c - a).length() / 2.0
+		})
+		.sum();
+	let perimeter = 10.0 * (PI / 5.0).sin();
+	assert!((area - 2.0 * 10.0 * perimeter).abs() < 1e-9);
+}
+
+#[test]
+fn writes_formats() {
+	let options = Options {
+		segments: 8,
+		caps: false,
+		..Options::default()
+	};
+	let mesh = mesh::mesh(&survey(), &options);
+	let colours = mesh.depth_colours();
+
+	let mut obj = Vec::new();
+	mesh::write_obj(&mesh, Some(&colours), &mut obj).unwrap();
+	let obj = String::from_utf8(obj).unwrap();
+	assert_eq!(
+		obj.lines().filter(|line| line.starts_with("v ")).count(),
+		16
+	);
+	assert_eq!(
+		obj.lines().filter(|line| line.starts_with("f ")).count(),
+		16
+	);
+
+	// the first vertex is the highest, so it's red
+	let first: Vec<f64> = obj.lines().nth(1).unwrap()[2..]
+		.split(' ')
+		.map(|value| value.parse().unwrap())
+		.collect();
+	assert_eq!(first, [0.0, 0.0, 1.0, 1.0, 0.0, 0.0]);
+
+	let mut ply = Vec::new();
+	mesh::write_ply(&mesh, None, &mut ply).unwrap();
+	let header = b"end_header\n";
+	let end = ply.windows(header.len()).position(|w| w == header).unwrap() + header.len();
+	assert_eq!(ply.len() - end, 16 * 12 + 16 * 13);
+
+	let mut stl = Vec::new();
+	mesh::write_stl(&mesh, &mut stl).unwrap();
+	assert_eq!(stl.len(), 84 + 16 * 50);
+	assert_eq!(stl[80..84], 16_u32.to_le_bytes());
+}
+
+#[test]
+fn rejects_colours_for_other_vertices() {
+	let mesh = mesh::mesh(&survey(), &Options::default());
+	let colours = vec![[0, 0, 0]; mesh.vertices.len() - 1];
+
+	let error = mesh::write_obj(&mesh, Some(&colours), &mut Vec::new()).unwrap_err();
+	assert_eq!(error.kind(), ErrorKind::InvalidInput);
+
+	let error = mesh::write_ply(&mesh, Some(&colours), &mut Vec::new()).unwrap_err();
+	assert_eq!(error.kind(), ErrorKind::InvalidInput);
+}
+
+#[test]
+fn colours_by_depth() {
+	assert_eq!(depth_colour(0.0), [0, 0, 255]);
+	assert_eq!(depth_colour(0.5), [0, 255, 0]);
+	assert_eq!(depth_colour(1.0), [255, 0, 0]);
+}
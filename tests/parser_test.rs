@@ -1,7 +1,9 @@
 use std::{fs::File, io::Read, path::PathBuf};
 
 use chrono::NaiveDate;
-use pocket_topo::{parser, Color, Element, Point, Reference, Shot, ShotFlags, StationId, Trip};
+use pocket_topo::{
+	encoding_rs, parser, Color, Element, Point, Reference, Shot, ShotFlags, StationId, Trip,
+};
 
 #[test]
 fn parses_default() {
@@ -25,7 +27,7 @@ fn parses_default() {
 	assert!(!shot.flags.contains(ShotFlags::HAS_COMMENT));
 	assert_eq!(shot.roll, 0x0);
 	assert_eq!(shot.trip_index, -1);
-	assert_eq!(shot.comment, None);
+	assert_eq!(shot.comment.as_deref(), None);
 
 	assert!(shots.next().is_none());
 
@@ -75,7 +77,7 @@ fn parses_shots_with_comments() {
 	assert_eq!(shot.roll, 0x0);
 	assert_eq!(shot.trip_index, -1);
 	assert_eq!(
-		shot.comment,
+		shot.comment.as_deref(),
 		Some("Comment #1\r\n\r\nFrom station: 1.0 to station: 1.1\r\n123.45 / 10.0 / 30,0")
 	);
 
@@ -90,7 +92,7 @@ fn parses_shots_with_comments() {
 	assert_eq!(shot.roll, 0x0);
 	assert_eq!(shot.trip_index, 0);
 	assert_eq!(
-		shot.comment,
+		shot.comment.as_deref(),
 		Some("Comment #2\r\n\r\nfrom station: 1.1 to station 2\r\n26.340 / 6.7 / 42.4")
 	);
 
@@ -112,7 +114,10 @@ fn parses_trips() {
 	trip = trips.next().unwrap();
 	assert_eq!(
 		trip.time,
-		NaiveDate::from_ymd(2022, 10, 22).and_hms(0, 0, 0)
+		NaiveDate::from_ymd_opt(2022, 10, 22)
+			.unwrap()
+			.and_hms_opt(0, 0, 0)
+			.unwrap()
 	);
 	assert_eq!(trip.comment, "test");
 	assert_eq!(trip.declination, 628); // 3.45 deg
@@ -120,7 +125,10 @@ fn parses_trips() {
 	trip = trips.next().unwrap();
 	assert_eq!(
 		trip.time,
-		NaiveDate::from_ymd(2022, 10, 15).and_hms(0, 0, 0)
+		NaiveDate::from_ymd_opt(2022, 10, 15)
+			.unwrap()
+			.and_hms_opt(0, 0, 0)
+			.unwrap()
 	);
 	assert_eq!(trip.comment, "2022-10-15 2.34");
 	assert_eq!(trip.declination, 426); // 2.34 deg
@@ -128,7 +136,10 @@ fn parses_trips() {
 	trip = trips.next().unwrap();
 	assert_eq!(
 		trip.time,
-		NaiveDate::from_ymd(2022, 10, 22).and_hms(0, 0, 0)
+		NaiveDate::from_ymd_opt(2022, 10, 22)
+			.unwrap()
+			.and_hms_opt(0, 0, 0)
+			.unwrap()
 	);
 	assert_eq!(trip.comment, "2022-10-22 3.45");
 	assert_eq!(trip.declination, 628); // 3.45 deg
@@ -136,6 +147,57 @@ fn parses_trips() {
 	assert!(trips.next().is_none());
 }
 
+#[test]
+fn fails_on_invalid_utf8_comments() {
+	let contents = fixture("encoding.top");
+
+	let error = parser::parse(&contents).expect_err("expected `ParseError`");
+	assert!(matches!(error, parser::ParseError::Utf8Error(_)));
+}
+
+#[test]
+fn parses_invalid_utf8_comments_lossily() {
+	let contents = fixture("encoding.top");
+
+	let options = parser::ParseOptions {
+		comment_encoding: parser::CommentEncoding::Utf8Lossy,
+		..Default::default()
+	};
+
+	let (document, decoded) =
+		parser::parse_with_options(&contents, &options).expect("invalid document");
+
+	assert_eq!(decoded.as_ref(), [parser::Record::Trip(0)]);
+
+	let mut trips = document.trips.iter();
+	assert_eq!(trips.next().unwrap().comment, "t\u{FFFD}st");
+	assert_eq!(trips.next().unwrap().comment, "2022-10-15 2.34");
+	assert_eq!(trips.next().unwrap().comment, "2022-10-22 3.45");
+}
+
+#[test]
+fn parses_legacy_encoded_comments() {
+	let contents = fixture("encoding.top");
+
+	let options = parser::ParseOptions {
+		comment_encoding: parser::CommentEncoding::Legacy(encoding_rs::WINDOWS_1252),
+		..Default::default()
+	};
+
+	let (document, decoded) =
+		parser::parse_with_options(&contents, &options).expect("invalid document");
+
+	assert_eq!(decoded.as_ref(), [parser::Record::Trip(0)]);
+	assert_eq!(document.trips[0].comment, "t\u{E9}st");
+	assert_eq!(document.trips[1].comment, "2022-10-15 2.34");
+
+	// valid UTF-8 comments aren't reported
+	let contents = fixture("comments.top");
+
+	let (_, decoded) = parser::parse_with_options(&contents, &options).expect("invalid document");
+	assert!(decoded.is_empty());
+}
+
 #[test]
 fn parses_references() {
 	let contents = fixture("references.top");
@@ -498,6 +560,57 @@ fn parses_elements() {
 	assert!(elements.next().is_none());
 }
 
+#[test]
+fn parses_unknown_elements() {
+	let contents = fixture("unknown.top");
+
+	let document = parser::parse(&contents).expect("invalid document");
+
+	let mut elements = document.outline.elements.iter();
+	assert_eq!(elements.len(), 3);
+
+	assert!(matches!(elements.next().unwrap(), Element::Polygon(_)));
+
+	match elements.next().unwrap() {
+		Element::Unknown { id, bytes } => {
+			assert_eq!(*id, 0x2);
+			assert_eq!(
+				bytes.as_ref(),
+				[0x04, 0x00, 0x00, 0x00, 0x10, 0x27, 0x00, 0x00, 0x00, 0x02]
+			);
+		}
+		_ => panic!(),
+	};
+
+	let cross_section = match elements.next().unwrap() {
+		Element::CrossSection(cross_section) => cross_section,
+		_ => panic!(),
+	};
+	assert_eq!(cross_section.station, StationId::MajorMinor(1, 0));
+	assert_eq!(cross_section.direction, -1);
+
+	let mut elements = document.sideview.elements.iter();
+	assert_eq!(elements.len(), 1);
+
+	match elements.next().unwrap() {
+		Element::Unknown { id, bytes } => {
+			assert_eq!(*id, 0x9);
+			assert_eq!(bytes.as_ref(), [0x00, 0x2A]);
+		}
+		_ => panic!(),
+	};
+}
+
+#[test]
+fn fails_on_unterminated_unknown_element() {
+	let mut contents = fixture("unknown.top");
+	contents.truncate(contents.len() - 5);
+
+	// without a terminated sideview, the outline's unknown element can't be resynchronised either
+	let error = parser::parse(&contents).expect_err("expected `ParseError`");
+	assert_eq!(error, parser::ParseError::UnknownElement(0x2));
+}
+
 #[test]
 fn parses_mappings() {
 	let contents = fixture("outline.top");
@@ -529,3 +642,35 @@ fn fixture(fixture: &str) -> Vec<u8> {
 
 	buffer
 }
+
+// unknown.top with `count` polygons of one point after the outline's unknown
+// element, before its cross-section
+fn large_unknown(count: usize) -> Vec<u8> {
+	let mut contents = fixture("unknown.top");
+
+	let mut polygon = vec![0x01, 0x01, 0x00, 0x00, 0x00];
+	polygon.extend_from_slice(&[0x01, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01]);
+
+	let position = 0x76;
+	assert_eq!(contents[position], 0x03);
+	contents.splice(position..position, polygon.repeat(count));
+	contents
+}
+
+#[test]
+fn parses_unknown_elements_in_large_files() {
+	let contents = large_unknown(100_000);
+
+	let document = parser::parse(&contents).expect("invalid document");
+	assert_eq!(document.outline.elements.len(), 100_003);
+	assert!(matches!(
+		document.outline.elements[1],
+		Element::Unknown { id: 0x2, .. }
+	));
+
+	// with the sideview truncated, every candidate length is rejected
+	let mut contents = large_unknown(100_000);
+	contents.truncate(contents.len() - 5);
+	let error = parser::parse(&contents).expect_err("expected `ParseError`");
+	assert_eq!(error, parser::ParseError::UnknownElement(0x2));
+}
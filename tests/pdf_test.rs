@@ -0,0 +1,134 @@
+mod common;
+
+use pocket_topo::{
+	builder::DocumentBuilder,
+	map::Sheet,
+	parser::Document,
+	pdf::{self, Options, PdfError},
+	Color,
+};
+
+use common::{date, with_extremes};
+
+// a leg of `length` m east from the reference, under a blue wall
+fn survey(length: f64) -> Document<'static> {
+	DocumentBuilder::new()
+		.trip(date(2024, 5, 1), 2.5, "Grotte du Test\nsecond line")
+		.shot("1.0", "1.1", length, 90.0, 0.0)
+		.outline_polygon("blue", &[(0.0, -1.0), (length, -1.0)])
+		.reference("1.0", 500_000.0, 5_200_000.0, 1_200.0, "")
+		.build()
+		.unwrap()
+}
+
+fn export(document: &Document, options: &Options) -> lopdf::Document {
+	let mut bytes = Vec::new();
+	pdf::write_pdf(document, options, &mut bytes).unwrap();
+
+	lopdf::Document::load_mem(&bytes).unwrap()
+}
+
+fn text(pdf: &lopdf::Document, page: u32) -> String {
+	pdf.extract_text(&[page]).unwrap()
+}
+
+#[test]
+fn maps_plan_and_profile() {
+	let pdf = export(&survey(10.0), &Options::default());
+	assert_eq!(pdf.get_pages().len(), 2);
+
+	let plan = text(&pdf, 1);
+	assert!(plan.contains("Grotte du Test"));
+	assert!(!plan.contains("second line"));
+	assert!(plan.contains("Surveyed 2024-05-01"));
+	assert!(plan.contains("Scale 1:500"));
+	assert!(plan.contains("Plan 1:500"));
+	assert!(plan.contains("MN"));
+	assert!(!plan.contains("GN"));
+	assert!(plan.contains("Blue"));
+
+	// the grid is in the reference's coordinates, every 10 m at 1:500
+	assert!(plan.contains("500000"));
+	assert!(plan.contains("5200000"));
+	assert!(!plan.contains("500005"));
+
+	let profile = text(&pdf, 2);
+	assert!(profile.contains("Profile 1:500"));
+	assert!(profile.contains("1200"));
+	assert!(!profile.contains("MN"));
+}
+
+#[test]
+fn turns_grid_to_grid_north() {
+	// at the Eiffel Tower, where grid north is half a degree west of true north
+	let document = DocumentBuilder::new()
+		.trip(date(2024, 5, 1), 2.5, "")
+		.shot("1.0", "1.1", 10.0, 90.0, 0.0)
+		.reference("1.0", 448_251.795, 5_411_932.678, 35.0, "")
+		.crs("31N".parse().unwrap())
+		.build()
+		.unwrap();
+
+	let options = Options {
+		sheets: vec![Sheet::Outline],
+		..Options::default()
+	};
+	let plan = text(&export(&document, &options), 1);
+	assert!(plan.contains("GN"));
+	assert!(plan.contains("448250"));
+	assert!(plan.contains("5411930"));
+}
+
+#[test]
+fn uses_legend_and_title() {
+	let options = Options {
+		sheets: vec![Sheet::Outline],
+		title: Some("Test Cave".to_owned()),
+		legend: vec![(Color::Blue, "Water".to_owned())],
+		..Options::default()
+	};
+	let pdf = export(&survey(10.0), &options);
+	assert_eq!(pdf.get_pages().len(), 1);
+
+	let plan = text(&pdf, 1);
+	assert!(plan.contains("Test Cave"));
+	assert!(plan.contains("Water"));
+	assert!(!plan.contains("Blue"));
+}
+
+#[test]
+fn tiles_large_surveys() {
+	let options = Options {
+		sheets: vec![Sheet::Outline],
+		..Options::default()
+	};
+
+	// 1 m of paper across 277 mm wide pages
+	let pdf = export(&survey(500.0), &options);
+	assert_eq!(pdf.get_pages().len(), 4);
+	assert!(text(&pdf, 2).contains("Plan 1:500, sheet 2 of 4 (row 1, column 2)"));
+}
+
+#[test]
+fn rejects_options() {
+	let mut bytes = Vec::new();
+
+	let options = Options {
+		scale: 0.0,
+		..Options::default()
+	};
+	let result = pdf::write_pdf(&survey(10.0), &options, &mut bytes);
+	assert!(matches!(result, Err(PdfError::InvalidOptions)));
+
+	let options = Options {
+		scale: 1.0,
+		..Options::default()
+	};
+	let result = pdf::write_pdf(&survey(500.0), &options, &mut bytes);
+	assert!(matches!(result, Err(PdfError::TooManyPages(_))));
+
+	// without overflowing
+	let document = with_extremes(survey(10.0));
+	let result = pdf::write_pdf(&document, &Options::default(), &mut bytes);
+	assert!(matches!(result, Err(PdfError::TooManyPages(_))));
+}
@@ -0,0 +1,101 @@
+mod common;
+
+use pocket_topo::{
+	builder::DocumentBuilder,
+	map::Sheet,
+	parser::Document,
+	preview::{preview, Charset, Options},
+};
+
+use common::{passage, with_extremes};
+
+// a leg 10 m east, under a blue wall 5 m to the north
+fn survey() -> Document<'static> {
+	passage(10.0)
+		.outline_polygon("blue", &[(0.0, -5.0), (10.0, -5.0)])
+		.build()
+		.unwrap()
+}
+
+// a dot to a metre
+const OPTIONS: Options = Options {
+	sheet: Sheet::Outline,
+	width: 11,
+	height: None,
+	charset: Charset::Blocks,
+	colour: false,
+	labels: false,
+};
+
+#[test]
+fn draws_blocks() {
+	let text = preview(&survey(), &OPTIONS);
+	assert_eq!(text, "▀▀▀▀▀▀▀▀▀▀▀\n\n▄▄▄▄▄▄▄▄▄▄▄\n");
+}
+
+#[test]
+fn draws_braille() {
+	let options = Options {
+		width: 6,
+		charset: Charset::Braille,
+		..OPTIONS
+	};
+
+	// 12 dots across, so the wall is at the top row of dots and the leg is at
+	// the seventh, 0.9 m to a dot
+	let text = preview(&survey(), &options);
+	let lines: Vec<&str> = text.lines().collect();
+	assert_eq!(lines.len(), 2);
+	assert_eq!(lines[0], "⠉⠉⠉⠉⠉⠉");
+	assert_eq!(lines[1], "⠤⠤⠤⠤⠤⠤");
+}
+
+#[test]
+fn fits_height() {
+	let options = Options {
+		width: 100,
+		height: Some(2),
+		..OPTIONS
+	};
+
+	// 4 dots down, so 5 m is 3 dots and 10 m is 6 dots across
+	let text = preview(&survey(), &options);
+	assert_eq!(text, "▀▀▀▀▀▀▀\n▄▄▄▄▄▄▄\n");
+}
+
+#[test]
+fn colours() {
+	let options = Options {
+		colour: true,
+		..OPTIONS
+	};
+
+	let text = preview(&survey(), &options);
+	let lines: Vec<&str> = text.lines().collect();
+	assert_eq!(lines[0], "\x1b[34m▀▀▀▀▀▀▀▀▀▀▀\x1b[0m");
+	assert_eq!(lines[1], "");
+	assert_eq!(lines[2], "\x1b[91m▄▄▄▄▄▄▄▄▄▄▄\x1b[0m");
+}
+
+#[test]
+fn labels_stations() {
+	let options = Options {
+		labels: true,
+		..OPTIONS
+	};
+
+	let text = preview(&survey(), &options);
+	assert_eq!(text.lines().nth(2), Some("▄1.0▄▄▄▄▄▄▄"));
+}
+
+#[test]
+fn empty() {
+	let document = DocumentBuilder::new().build().unwrap();
+	assert_eq!(preview(&document, &OPTIONS), "");
+}
+
+#[test]
+fn fits_extreme_coordinates() {
+	let text = preview(&with_extremes(survey()), &OPTIONS);
+	assert_eq!(text.lines().count(), 6);
+}
@@ -0,0 +1,96 @@
+mod common;
+
+use std::collections::BTreeMap;
+
+use pocket_topo::{
+	builder::DocumentBuilder,
+	lrud::Lrud,
+	projection::{project, View},
+	Element,
+};
+
+use common::{point, station};
+
+#[test]
+fn projects_plan_and_elevations() {
+	let document = DocumentBuilder::new()
+		.shot("1.0", "1.1", 10.0, 90.0, 0.0)
+		.shot("1.1", "1.2", 5.0, 0.0, 90.0)
+		.splay("1.1", 2.0, 0.0, 0.0)
+		.build()
+		.unwrap();
+
+	let plan = project(&document, View::Plan, &BTreeMap::new());
+	assert_eq!(plan.stations[&station("1.1")], point(10000, 0));
+	assert_eq!(plan.centerline[0], [point(0, 0), point(10000, 0)]);
+	assert_eq!(plan.splays[0], [point(10000, 0), point(10000, -2000)]);
+	assert!(plan.walls.is_empty());
+
+	// looking north, east is to the right
+	let north = project(&document, View::Elevation(0.0), &BTreeMap::new());
+	assert_eq!(north.stations[&station("1.2")], point(10000, -5000));
+
+	// looking east, north is to the left
+	let east = project(&document, View::Elevation(90.0), &BTreeMap::new());
+	assert_eq!(east.stations[&station("1.1")], point(0, 0));
+	assert_eq!(east.splays[0], [point(0, 0), point(-2000, 0)]);
+}
+
+#[test]
+fn projects_walls() {
+	let document = DocumentBuilder::new()
+		.shot("1.0", "1.1", 10.0, 90.0, 0.0)
+		.build()
+		.unwrap();
+
+	let lrud = Lrud {
+		left: 1.0,
+		right: 2.0,
+		up: 3.0,
+		down: 4.0,
+		direction: 90.0,
+	};
+	let lruds = BTreeMap::from([(station("1.0"), lrud), (station("1.1"), lrud)]);
+
+	// facing east, the left wall is north
+	let plan = project(&document, View::Plan, &lruds);
+	assert_eq!(
+		plan.walls,
+		[
+			[point(0, -1000), point(10000, -1000)],
+			[point(0, 2000), point(10000, 2000)],
+		]
+	);
+
+	let elevation = project(&document, View::Elevation(0.0), &lruds);
+	assert_eq!(
+		elevation.walls,
+		[
+			[point(0, -3000), point(10000, -3000)],
+			[point(0, 4000), point(10000, 4000)],
+		]
+	);
+}
+
+#[test]
+fn uses_cross_section_directions() {
+	let document = DocumentBuilder::new()
+		.outline_cross_section("1.0", (0.0, 0.0), Some(270.0))
+		.outline_cross_section("1.0", (0.0, 0.0), None)
+		.build()
+		.unwrap();
+
+	let views: Vec<_> = document
+		.outline
+		.elements
+		.iter()
+		.map(|element| match element {
+			Element::CrossSection(cross_section) => {
+				View::from_cross_section(cross_section.direction)
+			}
+			_ => panic!("not a cross-section"),
+		})
+		.collect();
+
+	assert_eq!(views, [View::Elevation(270.0), View::Plan]);
+}
@@ -0,0 +1,165 @@
+mod common;
+
+use pocket_topo::{
+	builder::DocumentBuilder,
+	reduction::{reduce, reduce_with_options, Corrections, North, Options, Position},
+	units,
+};
+
+use common::{date, station};
+
+fn assert_close(actual: Position, expected: Position) {
+	let difference = (actual - expected).length();
+	assert!(difference < 1e-3, "{actual:?} != {expected:?}");
+}
+
+#[test]
+fn positions_stations() {
+	let document = DocumentBuilder::new()
+		.trip(date(2022, 10, 22), 90.0, "")
+		.shot("1.0", "1.1", 10.0, 0.0, 0.0)
+		.shot("1.0", "1.1", 10.2, 0.0, 0.0)
+		.shot("1.2", "1.1", 5.0, 0.0, 90.0)
+		.splay("1.2", 1.0, 0.0, -90.0)
+		.shot("2.0", "2.1", 1.0, 0.0, 0.0)
+		.build()
+		.unwrap();
+
+	let centerline = reduce(&document);
+
+	assert_eq!(centerline.origin, Some(station("1.0")));
+
+	// the declination turns north to east, and repeated shots are averaged
+	let position = |east, north, up| Position { east, north, up };
+	assert_close(
+		centerline.stations[&station("1.1")],
+		position(10.1, 0.0, 0.0),
+	);
+	assert_close(
+		centerline.stations[&station("1.2")],
+		position(10.1, 0.0, -5.0),
+	);
+
+	// disconnected stations aren't positioned
+	assert_eq!(centerline.position(&station("2.0")), None);
+
+	assert_eq!(centerline.legs.len(), 3);
+	assert_eq!(&*centerline.legs[0].shots, [0, 1]);
+	assert_eq!(centerline.splays.len(), 1);
+	assert_close(centerline.splays[0].vector, position(0.0, 0.0, -1.0));
+}
+
+#[test]
+fn reduces_to_each_north() {
+	// at the Eiffel Tower, west of zone 31's central meridian
+	let document = DocumentBuilder::new()
+		.trip(date(2022, 10, 22), 2.0, "")
+		.shot("1.0", "1.1", 10.0, 0.0, 0.0)
+		.reference("1.0", 448_251.795, 5_411_932.678, 35.0, "")
+		.crs("31N".parse().unwrap())
+		.build()
+		.unwrap();
+
+	let azimuth = |north| {
+		let centerline = reduce_with_options(&document, &Options { north });
+		let azimuth = centerline.stations[&station("1.1")].azimuth();
+		(azimuth, centerline.corrections)
+	};
+
+	let (magnetic, corrections) = azimuth(North::Magnetic);
+	assert!(magnetic.abs() < 1e-9);
+	assert_eq!(
+		corrections,
+		Corrections {
+			north: North::Magnetic,
+			declination: false,
+			convergence: None,
+		}
+	);
+
+	// 2° to the file's precision
+	let declination = units::degrees(document.trips[0].declination);
+
+	let (true_north, corrections) = azimuth(North::True);
+	assert!((true_north - declination).abs() < 1e-9);
+	assert_eq!(corrections, Corrections::default());
+	assert_eq!(reduce(&document).corrections, corrections);
+
+	// grid north is west of true north here, so grid azimuths are larger
+	let (grid, corrections) = azimuth(North::Grid);
+	let convergence = corrections.convergence.unwrap();
+	assert_eq!(corrections.north, North::Grid);
+	assert!(corrections.declination);
+	assert!((convergence + 0.5313).abs() < 1e-4);
+	assert!((grid - declination + convergence).abs() < 1e-9);
+}
+
+#[test]
+fn reduces_to_true_north_without_crs() {
+	let document = DocumentBuilder::new()
+		.shot("1.0", "1.1", 10.0, 0.0, 0.0)
+		.reference("1.0", 448_251.795, 5_411_932.678, 35.0, "")
+		.build()
+		.unwrap();
+
+	let options = Options { north: North::Grid };
+	let centerline = reduce_with_options(&document, &options);
+	assert_eq!(centerline.corrections, Corrections::default());
+	assert_eq!(centerline.stations, reduce(&document).stations);
+}
+
+#[test]
+fn anchors_on_the_first_surveyed_reference() {
+	let document = DocumentBuilder::new()
+		.shot("1.0", "1.1", 10.0, 0.0, 0.0)
+		.reference("2.0", 1000.0, 2000.0, 300.0, "")
+		.reference("1.1", 448_251.795, 5_411_932.678, 35.0, "")
+		.build()
+		.unwrap();
+
+	let centerline = reduce(&document);
+	let anchor = centerline.anchor(&document).unwrap();
+	assert_eq!(anchor.station, station("1.1"));
+	assert_eq!(anchor.reference, &document.references[1]);
+	let offset = Position {
+		east: 448_251.795,
+		north: 5_411_922.678,
+		up: 35.0,
+	};
+	assert_close(anchor.offset, offset);
+}
+
+#[test]
+fn bisects_legs() {
+	let document = DocumentBuilder::new()
+		.shot("1.0", "1.1", 10.0, 0.0, 0.0)
+		.shot("1.1", "1.2", 2.0, 90.0, 30.0)
+		.build()
+		.unwrap();
+
+	let centerline = reduce(&document);
+	let east = Position {
+		east: 1.0,
+		north: 0.0,
+		up: 0.0,
+	};
+	let north = Position {
+		east: 0.0,
+		north: 1.0,
+		up: 0.0,
+	};
+
+	// halfway between north and east, whatever the legs' lengths
+	let bisector = centerline.bisector(station("1.1"), true);
+	assert_close(bisector, (north + east).unit());
+	assert!((bisector.length() - 1.0).abs() < 1e-9);
+	assert_eq!(east.cross(north).up, 1.0);
+
+	// the only leg, inclined unless horizontal
+	assert_close(centerline.bisector(station("1.2"), true), east);
+	assert!(centerline.bisector(station("1.2"), false).up > 0.4);
+	assert_eq!(
+		centerline.bisector(station("2.0"), false),
+		Position::default()
+	);
+}
@@ -0,0 +1,138 @@
+mod common;
+
+use pocket_topo::{
+	builder::DocumentBuilder,
+	map::Sheet,
+	parser::Document,
+	render::{self, Options, RenderError},
+};
+
+use common::{passage, with_extremes};
+
+// a leg 10 m east, under a blue wall 5 m to the north
+fn survey() -> Document<'static> {
+	passage(10.0)
+		.outline_polygon("blue", &[(0.0, -5.0), (10.0, -5.0)])
+		.outline_cross_section("1.1", (10.0, 0.0), None)
+		.build()
+		.unwrap()
+}
+
+// 10 pixels per mm of paper, at 1:500
+const OPTIONS: Options = Options {
+	sheet: Sheet::Outline,
+	dpi: 254.0,
+	scale: 500.0,
+	margin: 5.0,
+	labels: false,
+};
+
+struct Image {
+	width: u32,
+	height: u32,
+	pixels: Vec<u8>,
+}
+
+impl Image {
+	fn pixel(&self, x: u32, y: u32) -> [u8; 3] {
+		let index = 3 * (y * self.width + x) as usize;
+		self.pixels[index..index + 3].try_into().unwrap()
+	}
+}
+
+fn render(document: &Document, options: &Options) -> Image {
+	let mut png = Vec::new();
+	render::write_png(document, options, &mut png).unwrap();
+
+	let mut reader = png::Decoder::new(png.as_slice()).read_info().unwrap();
+	let dims = reader.info().pixel_dims.unwrap();
+	assert_eq!(dims.xppu, 10_000);
+
+	let mut pixels = vec![0; reader.output_buffer_size()];
+	let info = reader.next_frame(&mut pixels).unwrap();
+	assert_eq!(info.color_type, png::ColorType::Rgb);
+
+	Image {
+		width: info.width,
+		height: info.height,
+		pixels,
+	}
+}
+
+#[test]
+fn renders_to_scale() {
+	let image = render(&survey(), &OPTIONS);
+
+	// 20 mm of paper each way, and the margins
+	assert_eq!((image.width, image.height), (300, 200));
+
+	assert_eq!(image.pixel(10, 10), [255, 255, 255]);
+
+	// the wall in its colour, 2.5 pixels wide
+	assert_eq!(image.pixel(150, 49), [0, 0, 255]);
+	assert_eq!(image.pixel(150, 50), [0, 0, 255]);
+
+	// the centerline is red
+	let [r, g, b] = image.pixel(150, 149);
+	assert!(r > 200 && g < 100 && b < 100);
+
+	// the cross-section's circle around 1.1
+	let [r, g, b] = image.pixel(250, 150 - 12);
+	assert!(r < 128 && g < 128 && b < 128);
+}
+
+#[test]
+fn renders_labels() {
+	let options = Options {
+		labels: true,
+		..OPTIONS
+	};
+	let image = render(&survey(), &options);
+
+	assert!(image.width > 300);
+	assert_eq!(image.height, 200);
+
+	// the label's text is above and to the right of 1.1
+	let text = (255..300).any(|x| (130..150).any(|y| image.pixel(x, y) == [0, 0, 0]));
+	assert!(text);
+}
+
+#[test]
+fn renders_sideview() {
+	let document = DocumentBuilder::new()
+		.shot("1.0", "1.1", 10.0, 90.0, -30.0)
+		.build()
+		.unwrap();
+
+	let options = Options {
+		sheet: Sheet::Sideview,
+		..OPTIONS
+	};
+	let image = render(&document, &options);
+
+	// the leg is 8.66 m along and 5 m down
+	assert_eq!((image.width, image.height), (274, 200));
+}
+
+#[test]
+fn rejects_options() {
+	let mut png = Vec::new();
+
+	let options = Options {
+		scale: 0.0,
+		..OPTIONS
+	};
+	let result = render::write_png(&survey(), &options, &mut png);
+	assert!(matches!(result, Err(RenderError::InvalidOptions)));
+
+	let options = Options {
+		scale: 0.01,
+		..OPTIONS
+	};
+	let result = render::write_png(&survey(), &options, &mut png);
+	assert!(matches!(result, Err(RenderError::TooLarge { .. })));
+
+	// without overflowing
+	let result = render::write_png(&with_extremes(survey()), &OPTIONS, &mut png);
+	assert!(matches!(result, Err(RenderError::TooLarge { .. })));
+}
@@ -0,0 +1,133 @@
+mod common;
+
+use std::collections::BTreeMap;
+
+use pocket_topo::{
+	builder::DocumentBuilder,
+	parser::Document,
+	renumber::{self, RenumberError},
+	Element, StationId,
+};
+
+use common::station;
+
+fn survey() -> Document<'static> {
+	DocumentBuilder::new()
+		.reference("3.0", 24.0, 42.0, 50.0, "entrance")
+		.shot("3.0", "3.1", 10.0, 90.0, 0.0)
+		.shot("3.1", "4.0", 2.0, 0.0, 0.0)
+		.splay("3.1", 1.5, 0.0, 0.0)
+		.outline_cross_section("3.1", (10.0, 0.0), None)
+		.sideview_cross_section("4.0", (12.0, 0.0), None)
+		.build()
+		.expect("invalid document")
+}
+
+fn stations(document: &Document) -> Vec<String> {
+	let mut stations = Vec::new();
+
+	for shot in document.shots.iter() {
+		stations.extend(
+			shot.from
+				.iter()
+				.chain(shot.to.iter())
+				.map(|s| s.to_string()),
+		);
+	}
+	for reference in document.references.iter() {
+		stations.extend(reference.station.map(|s| s.to_string()));
+	}
+	for element in document
+		.outline
+		.elements
+		.iter()
+		.chain(document.sideview.elements.iter())
+	{
+		if let Element::CrossSection(cross_section) = element {
+			stations.push(cross_section.station.to_string());
+		}
+	}
+
+	stations
+}
+
+#[test]
+fn renames_station() {
+	let mut document = survey();
+	renumber::rename(&mut document, station("3.1"), station("3.2")).unwrap();
+
+	assert_eq!(
+		stations(&document),
+		["3.0", "3.2", "3.2", "4.0", "3.2", "3.0", "3.2", "4.0"]
+	);
+
+	assert_eq!(
+		renumber::rename(&mut document, station("3.1"), station("3.3")),
+		Err(RenumberError::UnknownStation(station("3.1")))
+	);
+}
+
+#[test]
+fn shifts_series() {
+	let mut document = survey();
+	renumber::shift_series(&mut document, 3, 7).unwrap();
+
+	assert_eq!(
+		stations(&document),
+		["7.0", "7.1", "7.1", "4.0", "7.1", "7.0", "7.1", "4.0"]
+	);
+}
+
+#[test]
+fn converts_schemes() {
+	let mut document = survey();
+	renumber::series_to_plain(&mut document, 3, 100).unwrap();
+	renumber::series_to_plain(&mut document, 4, 200).unwrap();
+
+	assert_eq!(
+		stations(&document),
+		["100", "101", "101", "200", "101", "100", "101", "200"]
+	);
+
+	renumber::plain_to_series(&mut document, 1).unwrap();
+
+	assert_eq!(
+		stations(&document),
+		["1.100", "1.101", "1.101", "1.200", "1.101", "1.100", "1.101", "1.200"]
+	);
+}
+
+#[test]
+fn applies_mapping() {
+	let mut document = survey();
+
+	// stations can be swapped
+	let mapping = BTreeMap::from([
+		(station("3.0"), station("4.0")),
+		(station("4.0"), station("3.0")),
+	]);
+	renumber::apply(&mut document, &mapping).unwrap();
+
+	assert_eq!(
+		stations(&document),
+		["4.0", "3.1", "3.1", "3.0", "3.1", "4.0", "3.1", "3.0"]
+	);
+}
+
+#[test]
+fn rejects_collisions() {
+	let mut document = survey();
+
+	assert_eq!(
+		renumber::rename(&mut document, station("3.1"), station("4.0")),
+		Err(RenumberError::Collision(station("4.0")))
+	);
+
+	assert_eq!(
+		renumber::shift_series(&mut document, 3, 0x8000),
+		Err(RenumberError::OutOfRange(StationId::MajorMinor(0x8000, 0)))
+	);
+
+	// the document is unchanged
+	assert_eq!(document, survey());
+}
@@ -0,0 +1,180 @@
+mod common;
+
+use std::io::ErrorKind;
+
+use gltf::{mesh::Mode, Gltf};
+use pocket_topo::{
+	builder::DocumentBuilder,
+	mesh,
+	parser::Document,
+	reduction::North,
+	scene::{self, Options},
+};
+
+use common::passage;
+
+// 1.0 → 1.1 east, then 1.1 → 1.2 north and up, with the reference at 1.1
+fn survey() -> Document<'static> {
+	let mut builder = passage(10.0).shot("1.1", "1.2", 5.0, 0.0, 90.0);
+
+	for station in ["1.0", "1.1"] {
+		for azimuth in [0.0, 120.0, 240.0] {
+			builder = builder.splay(station, 1.0, azimuth, 0.0);
+		}
+	}
+
+	builder
+		.reference("1.1", 500_000.0, 5_200_000.5, 1200.0, "entrance")
+		.build()
+		.unwrap()
+}
+
+fn export(document: &Document, options: &Options) -> Gltf {
+	let mut glb = Vec::new();
+	scene::write_glb(document, options, &mut glb).unwrap();
+	assert_eq!(glb.len() % 4, 0);
+
+	Gltf::from_slice(&glb).unwrap()
+}
+
+#[test]
+fn exports_scene() {
+	let gltf = export(&survey(), &Options::default());
+	let blob = gltf.blob.as_deref().unwrap();
+
+	let names: Vec<&str> = gltf
+		.scenes()
+		.next()
+		.unwrap()
+		.nodes()
+		.filter_map(|node| node.name())
+		.collect();
+	assert_eq!(names, ["centerline", "splays", "stations"]);
+
+	// the stations are named, relative to the reference with y up and z south
+	let stations = gltf
+		.nodes()
+		.find(|node| node.name() == Some("stations"))
+		.unwrap();
+	let stations: Vec<(&str, [f32; 3])> = stations
+		.children()
+		.map(|node| (node.name().unwrap(), node.transform().decomposed().0))
+		.collect();
+	assert_eq!(stations.len(), 3);
+	assert_eq!(stations[0].0, "1.0");
+	assert!(close(stations[0].1, [-10.0, 0.0, 0.0]));
+	assert_eq!(stations[1].0, "1.1");
+	assert!(close(stations[1].1, [0.0, 0.0, 0.0]));
+	assert_eq!(stations[2].0, "1.2");
+	assert!(close(stations[2].1, [0.0, 5.0, 0.0]));
+
+	let centerline = gltf
+		.meshes()
+		.find(|mesh| mesh.name() == Some("centerline"))
+		.unwrap();
+	let primitive = centerline.primitives().next().unwrap();
+	assert_eq!(primitive.mode(), Mode::Lines);
+
+	let reader = primitive.reader(|_| Some(blob));
+	let indices: Vec<u32> = reader.read_indices().unwrap().into_u32().collect();
+	assert_eq!(indices, [0, 1, 1, 2]);
+
+	let splays = gltf
+		.meshes()
+		.find(|mesh| mesh.name() == Some("splays"))
+		.unwrap();
+	let primitive = splays.primitives().next().unwrap();
+	let positions: Vec<[f32; 3]> = primitive
+		.reader(|_| Some(blob))
+		.read_positions()
+		.unwrap()
+		.collect();
+	assert_eq!(positions.len(), 12);
+	assert!(close(positions[0], [-10.0, 0.0, 0.0]));
+	assert!(close(positions[1], [-10.0, 0.0, -1.0]));
+
+	let extras = gltf
+		.scenes()
+		.next()
+		.unwrap()
+		.extras()
+		.as_ref()
+		.unwrap()
+		.get();
+	assert_eq!(
+		extras,
+		r#"{"reduction":{"north":"true","declination":true},"reference":{"station":"1.1","east":500000,"north":5200000.5,"altitude":1200}}"#
+	);
+}
+
+#[test]
+fn exports_crs() {
+	let mut document = survey();
+	document.crs = Some("31N".parse().unwrap());
+
+	let options = Options {
+		north: North::Grid,
+		..Options::default()
+	};
+	let gltf = export(&document, &options);
+	let extras = gltf
+		.scenes()
+		.next()
+		.unwrap()
+		.extras()
+		.as_ref()
+		.unwrap()
+		.get();
+
+	// on the central meridian, where the reference's easting is
+	assert!(extras.contains(r#""altitude":1200,"crs":"EPSG:32631","latitude":46.9"#));
+	assert!(extras.ends_with(r#""longitude":3}}"#));
+	assert!(
+		extras.starts_with(r#"{"reduction":{"north":"grid","declination":true,"convergence":0},"#)
+	);
+}
+
+#[test]
+fn rejects_numbers_which_are_not_finite() {
+	// too far east for the CRS, so the reference has no latitude or longitude
+	let mut document = survey();
+	document.crs = Some("31N".parse().unwrap());
+	document.references[0].east = 1_000_000_000_000;
+
+	let error = scene::write_glb(&document, &Options::default(), &mut Vec::new()).unwrap_err();
+	assert_eq!(error.kind(), ErrorKind::InvalidData);
+}
+
+#[test]
+fn exports_passage() {
+	let options = Options {
+		passage: Some(mesh::Options {
+			segments: 6,
+			caps: true,
+			..mesh::Options::default()
+		}),
+		..Options::default()
+	};
+	let gltf = export(&survey(), &options);
+
+	let passage = gltf
+		.meshes()
+		.find(|mesh| mesh.name() == Some("passage"))
+		.unwrap();
+	let primitive = passage.primitives().next().unwrap();
+	assert_eq!(primitive.mode(), Mode::Triangles);
+	assert_eq!(primitive.indices().unwrap().count(), 3 * (12 + 12));
+}
+
+#[test]
+fn exports_empty_scene() {
+	let document = DocumentBuilder::new().build().unwrap();
+	let gltf = export(&document, &Options::default());
+
+	assert_eq!(gltf.nodes().count(), 0);
+	assert!(gltf.blob.is_none());
+}
+
+fn close(a: [f32; 3], b: [f32; 3]) -> bool {
+	a.iter().zip(b).all(|(a, b)| (a - b).abs() < 1e-5)
+}
@@ -0,0 +1,87 @@
+mod common;
+
+use pocket_topo::{builder::DocumentBuilder, reduction::reduce, stats::statistics};
+
+use common::{date, station};
+
+fn assert_close(actual: f64, expected: f64) {
+	assert!((actual - expected).abs() < 1e-6, "{actual} != {expected}");
+}
+
+#[test]
+fn computes_statistics() {
+	let day = |day| date(2022, 10, day);
+
+	let document = DocumentBuilder::new()
+		.reference("1.1", 0.0, 0.0, 500.0, "entrance")
+		.trip(day(22), 0.0, "")
+		.shot("1.0", "1.1", 10.0, 90.0, 0.0)
+		.shot("1.1", "1.0", 10.0, 270.0, 0.0)
+		.splay("1.1", 1.0, 0.0, 0.0)
+		.shot("1.1", "1.2", 5.0, 0.0, -90.0)
+		.trip(day(15), 0.0, "")
+		.shot("1.2", "1.3", 4.0, 0.0, 0.0)
+		.splay("1.3", 1.0, 0.0, 0.0)
+		.splay("1.3", 1.0, 90.0, 0.0)
+		.build()
+		.unwrap();
+
+	let report = statistics(&document);
+	let statistics = &report.document;
+
+	// the backsight doesn't count as a separate leg
+	assert_close(statistics.length, 19.0);
+	assert_eq!(statistics.legs, 3);
+	assert_eq!(statistics.splays, 3);
+	assert_eq!(statistics.stations, 4);
+
+	// from 1.0 to 1.3
+	assert_close(statistics.horizontal_extent, 116.0_f64.sqrt());
+
+	assert_eq!(statistics.datum, Some(station("1.1")));
+	let highest = statistics.highest.unwrap();
+	let deepest = statistics.deepest.unwrap();
+	assert_eq!(highest.station, station("1.0"));
+	assert_close(highest.height, 0.0);
+	assert_eq!(deepest.station, station("1.2"));
+	assert_close(deepest.height, -5.0);
+	assert_close(statistics.vertical_range, 5.0);
+
+	let longest = statistics.longest_leg.unwrap();
+	assert_eq!((longest.from, longest.to), (station("1.0"), station("1.1")));
+
+	assert_eq!(statistics.first_date, Some(day(15).date()));
+	assert_eq!(statistics.last_date, Some(day(22).date()));
+
+	assert_eq!(report.trips.len(), 2);
+	let trip = &report.trips[1];
+	assert_close(trip.length, 4.0);
+	assert_eq!((trip.legs, trip.splays, trip.stations), (1, 2, 2));
+	assert_eq!(trip.first_date, Some(day(15).date()));
+	assert_close(trip.vertical_range, 0.0);
+}
+
+#[test]
+fn measures_horizontal_extent_of_a_winding_passage() {
+	// a spiral, with stations inside the hull and a vertical leg
+	let mut builder = DocumentBuilder::new().shot("1.0", "1.1", 3.0, 0.0, -90.0);
+	for index in 1..60 {
+		let from = format!("1.{index}");
+		let to = format!("1.{}", index + 1);
+		let length = 1.0 + f64::from(index) * 0.3;
+		let azimuth = f64::from(index * 37 % 360);
+		builder = builder.shot(&from, &to, length, azimuth, 5.0);
+	}
+	let document = builder.build().unwrap();
+
+	let positions: Vec<_> = reduce(&document).stations.into_values().collect();
+	let mut expected: f64 = 0.0;
+	for a in positions.iter() {
+		for b in positions.iter() {
+			expected = expected.max((*a - *b).horizontal_length());
+		}
+	}
+
+	let report = statistics(&document);
+	assert_close(report.document.horizontal_extent, expected);
+}
@@ -0,0 +1,224 @@
+use pocket_topo::{
+	builder::DocumentBuilder,
+	parser::Document,
+	reduction::{self, Position},
+	surface::{self, Dem, DemError, SurfaceError},
+};
+
+// 4 by 3 cells of 10 m from the origin, without data in the east of the middle row
+const GRID: &str = "ncols 4
+nrows 3
+xllcorner 0
+yllcorner 0
+cellsize 10
+NODATA_value -9999
+100 110 120 130
+100 110 120 -9999
+100 110 120 130
+";
+
+fn document() -> Document<'static> {
+	DocumentBuilder::new()
+		.shot("1.0", "1.1", 10.0, 90.0, 0.0)
+		.shot("1.1", "1.2", 20.0, 0.0, -30.0)
+		.reference("1.0", 5.0, 5.0, 80.0, "entrance")
+		.build()
+		.unwrap()
+}
+
+#[test]
+fn parses_ascii_grid() {
+	let dem = Dem::parse(GRID.as_bytes()).unwrap();
+	assert_eq!((dem.columns, dem.rows), (4, 3));
+	assert_eq!((dem.west, dem.north), (0.0, 30.0));
+	assert_eq!(dem.epsg, None);
+
+	assert_eq!(dem.get(0, 0), Some(100.0));
+	assert_eq!(dem.get(3, 1), None);
+	assert_eq!(dem.get(4, 0), None);
+
+	// centres, between them and the edges
+	assert_eq!(dem.altitude(15.0, 25.0), Some(110.0));
+	assert_eq!(dem.altitude(10.0, 25.0), Some(105.0));
+	assert_eq!(dem.altitude(1.0, 29.0), Some(100.0));
+	assert_eq!(dem.altitude(-1.0, 25.0), None);
+	assert_eq!(dem.altitude(5.0, 30.5), None);
+
+	// next to the cell without data, and in it
+	assert_eq!(dem.altitude(28.0, 16.0), Some(120.0));
+	assert_eq!(dem.altitude(35.0, 15.0), None);
+
+	let centre = GRID.replace("xllcorner 0", "xllcenter 5");
+	let centre = centre.replace("yllcorner 0", "yllcenter 5");
+	let centre = Dem::parse_ascii(&centre).unwrap();
+	assert_eq!((centre.west, centre.north), (0.0, 30.0));
+}
+
+#[test]
+fn rejects_invalid_grid() {
+	assert_eq!(
+		Dem::parse_ascii(&GRID.replace("ncols 4\n", "")),
+		Err(DemError::MissingHeader("ncols"))
+	);
+	assert_eq!(
+		Dem::parse_ascii(&GRID.replace("130\n100", "130\nx")),
+		Err(DemError::InvalidValue("x".to_owned()))
+	);
+	assert_eq!(
+		Dem::parse_ascii(&GRID.replace("nrows 3", "nrows 4")),
+		Err(DemError::WrongSize {
+			expected: 16,
+			found: 12
+		})
+	);
+	assert_eq!(
+		Dem::parse_ascii(&GRID.replace("ncols 4\nnrows 3", "ncols 4294967296\nnrows 4294967296")),
+		Err(DemError::InvalidHeader(
+			"4294967296 by 4294967296 cells".to_owned()
+		))
+	);
+	assert!(matches!(
+		Dem::parse_ascii(&GRID.replace("cellsize 10", "cellsize 0")),
+		Err(DemError::InvalidHeader(_))
+	));
+}
+
+#[test]
+fn computes_cover() {
+	let dem = Dem::parse(GRID.as_bytes()).unwrap();
+	let cover = surface::cover(&document(), &dem, &reduction::Options::default()).unwrap();
+
+	assert_eq!(cover.len(), 3);
+	assert_eq!(
+		cover[0].position,
+		Position {
+			east: 5.0,
+			north: 5.0,
+			up: 80.0
+		}
+	);
+	assert_eq!(cover[0].thickness(), Some(20.0));
+	assert!((cover[1].thickness().unwrap() - 30.0).abs() < 1e-3);
+
+	// 17.32 m north and 10 m down
+	assert!((cover[2].position.north - 22.320_508).abs() < 1e-3);
+	assert!((cover[2].thickness().unwrap() - 40.0).abs() < 1e-3);
+
+	let shallowest: Vec<String> = surface::shallowest(&cover)
+		.iter()
+		.map(|cover| cover.station.to_string())
+		.collect();
+	assert_eq!(shallowest, ["1.0", "1.1", "1.2"]);
+
+	let unreferenced = DocumentBuilder::new()
+		.shot("1.0", "1.1", 10.0, 90.0, 0.0)
+		.build()
+		.unwrap();
+	assert_eq!(
+		surface::cover(&unreferenced, &dem, &reduction::Options::default()),
+		Err(SurfaceError::NoReference)
+	);
+}
+
+#[test]
+fn exports_profile() {
+	let dem = Dem::parse(GRID.as_bytes()).unwrap();
+	let profile = surface::profile(&document(), &dem, &reduction::Options::default(), 5.0).unwrap();
+
+	// 3 points along the first leg, and 5 along the second's 17.32 m
+	assert_eq!(profile.len(), 8);
+	let distances: Vec<f64> = profile[..3].iter().map(|point| point.distance).collect();
+	assert_eq!(distances, [0.0, 5.0, 10.0]);
+	assert_eq!(profile[1].surface, Some(105.0));
+	assert_eq!(profile[3].distance, 10.0);
+	assert!((profile[7].distance - 27.320_508).abs() < 1e-3);
+
+	let mut csv = Vec::new();
+	surface::write_profile_csv(&profile[..2], &mut csv).unwrap();
+	assert_eq!(
+		String::from_utf8(csv).unwrap(),
+		"from,to,distance,east,north,altitude,surface,cover\n\
+		1.0,1.1,0.000,5.000,5.000,80.000,100.000,20.000\n\
+		1.0,1.1,5.000,10.000,5.000,80.000,105.000,25.000\n"
+	);
+}
+
+#[cfg(feature = "geotiff")]
+#[test]
+fn parses_geotiff() {
+	use pocket_topo::geodesy::Crs;
+	use tiff::{
+		encoder::{
+			colortype::{Gray32Float, RGB8},
+			TiffEncoder,
+		},
+		tags::Tag,
+	};
+
+	// 10 by 10 cells of 0.001° around the Eiffel Tower, rising to the east
+	let mut contents = std::io::Cursor::new(Vec::new());
+	let mut encoder = TiffEncoder::new(&mut contents).unwrap();
+	let mut image = encoder.new_image::<Gray32Float>(10, 10).unwrap();
+	let directory = image.encoder();
+	directory
+		.write_tag(Tag::ModelPixelScaleTag, &[0.001, 0.001, 0.0][..])
+		.unwrap();
+	directory
+		.write_tag(
+			Tag::ModelTiepointTag,
+			&[0.0, 0.0, 0.0, 2.29, 48.862, 0.0][..],
+		)
+		.unwrap();
+	directory
+		.write_tag(
+			Tag::GeoKeyDirectoryTag,
+			&[1_u16, 1, 0, 2, 1024, 0, 1, 2, 2048, 0, 1, 4326][..],
+		)
+		.unwrap();
+	let altitudes: Vec<f32> = (0..100).map(|index| 30.0 + (index % 10) as f32).collect();
+	image.write_data(&altitudes).unwrap();
+
+	let dem = Dem::parse(contents.get_ref()).unwrap();
+	assert_eq!((dem.columns, dem.rows), (10, 10));
+	assert_eq!(dem.epsg, Some(4326));
+	assert_eq!(dem.get(3, 0), Some(33.0));
+
+	let mut colour = std::io::Cursor::new(Vec::new());
+	TiffEncoder::new(&mut colour)
+		.unwrap()
+		.write_image::<RGB8>(1, 1, &[0, 0, 0])
+		.unwrap();
+	assert_eq!(
+		Dem::parse(colour.get_ref()),
+		Err(DemError::Unsupported("more than one band"))
+	);
+
+	let mut document = DocumentBuilder::new()
+		.shot("1.0", "1.1", 10.0, 90.0, 0.0)
+		.reference("1.0", 448_251.795, 5_411_932.678, 20.0, "")
+		.build()
+		.unwrap();
+	assert_eq!(
+		surface::cover(&document, &dem, &reduction::Options::default()),
+		Err(SurfaceError::NoCrs)
+	);
+
+	document.crs = Some("31N".parse::<Crs>().unwrap());
+	let cover = surface::cover(&document, &dem, &reduction::Options::default()).unwrap();
+
+	// 2.2945° is 4.5 cells east of the grid's edge
+	assert!((cover[0].surface.unwrap() - 34.0).abs() < 0.01);
+
+	document.crs = Some("32N".parse::<Crs>().unwrap());
+	assert!(surface::cover(&document, &dem, &reduction::Options::default()).is_ok());
+
+	let mut projected = Dem::parse(GRID.as_bytes()).unwrap();
+	projected.epsg = Some(32631);
+	assert_eq!(
+		surface::cover(&document, &projected, &reduction::Options::default()),
+		Err(SurfaceError::MismatchedCrs {
+			model: 32631,
+			references: 32632
+		})
+	);
+}
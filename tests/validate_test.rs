@@ -0,0 +1,75 @@
+use chrono::NaiveDate;
+use pocket_topo::{
+	builder::DocumentBuilder,
+	diff::Item,
+	validate::{validate, Options, Rule, Severity},
+};
+
+#[test]
+fn finds_problems() {
+	let time = NaiveDate::from_ymd_opt(2022, 10, 22)
+		.unwrap()
+		.and_hms_opt(0, 0, 0)
+		.unwrap();
+
+	let mut document = DocumentBuilder::new()
+		.reference("1.0", 0.0, 0.0, 0.0, "")
+		.reference("9.0", 0.0, 0.0, 0.0, "")
+		.trip(time, 0.0, "")
+		.shot("1.0", "1.1", 10.0, 90.0, 0.0)
+		.shot("1.1", "1.0", 10.02, 270.5, 0.0)
+		.shot("1.0", "1.1", 10.5, 90.0, 0.0)
+		.shot("1.1", "1.2", 0.0, 0.0, 0.0)
+		.shot("2.0", "2.1", 5.0, 0.0, 0.0)
+		.shot("2.2", "2.1", 5.0, 0.0, 0.0)
+		.splay("3.0", 1.0, 0.0, 0.0)
+		.splay("1.2", 1.0, 0.0, 0.0)
+		.splay("1.2", 1.0, 0.0, 0.0)
+		.outline_cross_section("1.1", (0.0, 0.0), None)
+		.sideview_cross_section("8.0", (0.0, 0.0), None)
+		.build()
+		.unwrap();
+
+	// values the builder won't produce
+	document.shots[7].trip_index = 1;
+	document.shots[8].from = None;
+	document.shots[8].inclination = 0x4001;
+
+	let findings = validate(&document, &Options::default());
+	let findings: Vec<_> = findings
+		.iter()
+		.map(|finding| (finding.rule, finding.severity, finding.location))
+		.collect();
+
+	assert_eq!(
+		findings,
+		[
+			(Rule::InvalidTripIndex, Severity::Error, Item::Shot(7)),
+			(Rule::UndefinedStations, Severity::Warning, Item::Shot(8)),
+			(Rule::ZeroLengthLeg, Severity::Warning, Item::Shot(3)),
+			(Rule::DisconnectedStations, Severity::Warning, Item::Shot(4)),
+			(Rule::DisconnectedStations, Severity::Warning, Item::Shot(6)),
+			(
+				Rule::UnknownReferenceStation,
+				Severity::Warning,
+				Item::Reference(1)
+			),
+			(
+				Rule::UnknownCrossSectionStation,
+				Severity::Warning,
+				Item::Sideview(0)
+			),
+			(Rule::InconsistentLeg, Severity::Error, Item::Shot(2)),
+			(Rule::ImplausibleInclination, Severity::Error, Item::Shot(8)),
+		]
+	);
+}
+
+#[test]
+fn catalogues_rules() {
+	let ids: Vec<_> = Rule::ALL.iter().map(|rule| rule.id()).collect();
+
+	assert_eq!(ids.len(), 8);
+	assert!(ids.contains(&"disconnected-stations"));
+	assert!(Rule::ALL.iter().all(|rule| !rule.description().is_empty()));
+}
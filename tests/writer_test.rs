@@ -0,0 +1,107 @@
+use std::{
+	fs::File,
+	io::{self, Read},
+	path::PathBuf,
+};
+
+use chrono::{NaiveDate, NaiveDateTime};
+use pocket_topo::{builder::DocumentBuilder, parser, writer, StationId};
+
+#[test]
+fn round_trips_fixtures() {
+	for name in [
+		"comments.top",
+		"default.top",
+		"empty.top",
+		"outline.top",
+		"references.top",
+		"trips.top",
+		"unknown.top",
+	] {
+		let contents = fixture(name);
+
+		let document = parser::parse(&contents).expect("invalid document");
+
+		let mut output = Vec::new();
+		writer::write(&document, &mut output).expect("failed to write document");
+
+		// PocketTopo pads the end of the file with zeros, which aren't written back
+		let (written, padding) = contents.split_at(output.len());
+		assert_eq!(output, written, "{name} differs");
+		assert!(padding.iter().all(|byte| *byte == 0x0), "{name} differs");
+	}
+}
+
+#[test]
+fn round_trips_station_id_limits() {
+	let document = DocumentBuilder::new()
+		.shot("0", "2147483646", 1.0, 0.0, 0.0)
+		.shot("0.0", "32767.65535", 1.0, 0.0, 0.0)
+		.build()
+		.unwrap();
+
+	assert_eq!(
+		document.shots[0].to,
+		Some(StationId::Plain(StationId::MAX_PLAIN))
+	);
+	assert_eq!(
+		document.shots[1].to,
+		Some(StationId::MajorMinor(StationId::MAX_MAJOR, u16::MAX))
+	);
+
+	let mut output = Vec::new();
+	writer::write(&document, &mut output).unwrap();
+	assert_eq!(parser::parse(&output).unwrap(), document);
+}
+
+#[test]
+fn rejects_station_ids_out_of_range() {
+	for station in [
+		StationId::Plain(StationId::MAX_PLAIN + 1),
+		StationId::Plain(u32::MAX),
+		StationId::MajorMinor(StationId::MAX_MAJOR + 1, 0),
+	] {
+		let mut document = DocumentBuilder::new()
+			.shot("1.0", "1.1", 1.0, 0.0, 0.0)
+			.build()
+			.unwrap();
+		document.shots[0].to = Some(station);
+
+		let error = writer::write(&document, &mut Vec::new()).unwrap_err();
+		assert_eq!(error.kind(), io::ErrorKind::InvalidInput, "{station}");
+	}
+}
+
+#[test]
+fn rejects_times_out_of_range() {
+	for time in [NaiveDateTime::MIN, NaiveDateTime::MAX] {
+		let document = DocumentBuilder::new().trip(time, 0.0, "").build().unwrap();
+
+		let error = writer::write(&document, &mut Vec::new()).unwrap_err();
+		assert_eq!(error.kind(), io::ErrorKind::InvalidInput, "{time}");
+	}
+
+	// the last tick an `Int64` holds, as PocketTopo reads it back
+	let time = NaiveDate::from_ymd_opt(29_228, 9, 14)
+		.unwrap()
+		.and_hms_opt(2, 48, 5)
+		.unwrap();
+	let document = DocumentBuilder::new().trip(time, 0.0, "").build().unwrap();
+
+	let mut output = Vec::new();
+	writer::write(&document, &mut output).unwrap();
+	assert_eq!(parser::parse(&output).unwrap().trips[0].time, time);
+}
+
+fn fixture(fixture: &str) -> Vec<u8> {
+	let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+	path.push("tests/fixtures");
+	path.push(fixture);
+
+	let mut file = File::open(path).unwrap();
+
+	let mut buffer = Vec::new();
+	file.read_to_end(&mut buffer).unwrap();
+
+	buffer
+}